@@ -3,7 +3,6 @@ extern crate types;
 use std::path::{Path, PathBuf};
 use types::{traits::Host,render::{StyleShadow, StyleChange, Style, StyleTable}};
 use std::collections::HashMap;
-use std::borrow::Borrow;
 
 type RcCell<T> = std::rc::Rc<std::cell::RefCell<T>>;
 
@@ -12,7 +11,8 @@ pub struct DefaultStyleTable<H: Host + ?Sized> {
 }
 
 impl<H: Host + ?Sized> DefaultStyleTable<H> {
-    fn new<'p>(data: impl Iterator<Item = (&'p Path,Style<H>)>) -> Self {
+    /// A root table with styles at the given paths
+    pub fn new<'p>(data: impl Iterator<Item = (&'p Path,Style<H>)>) -> Self {
         let mut inner = Inner {previous: None, rules: Default::default()};
         for (p,s) in data {
             inner.rules.insert(p.to_owned(),Some(s));
@@ -52,12 +52,12 @@ impl<H: Host + ?Sized> Inner<H> {
     fn get(&self, which: &Path) -> Option<Style<H>> {
         match self.rules.get(which) {
             Some(Some(style)) => {
-                let ret = (*style).clone();
+                let ret = *style;
                 Some(ret)
             },
             Some(None) => {
                 if let Some(previous) = &self.previous {
-                    std::cell::RefCell::borrow(&*previous).get(which)
+                    std::cell::RefCell::borrow(previous).get(which)
                 } else {
                     None
                 }
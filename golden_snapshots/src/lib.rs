@@ -0,0 +1,276 @@
+//! Golden image testing for `Host::render` output.
+//!
+//! A `Snapshot` drives a host through a number of update rounds in headless mode,
+//! takes the last frame of a root portal and compares it with a stored PNG.
+//! On mismatch the actual image and a diff heatmap are written next to the golden one.
+//! Setting `UPDATE_GOLDEN` in the environment (re)writes golden images instead of failing.
+extern crate types;
+extern crate raster_primitive;
+extern crate png;
+
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+
+use raster_primitive::{Color, Primitive};
+use types::app::{Application, Event};
+use types::render::{Viewport, Visitor};
+use types::traits::Host;
+
+/// Environment variable which turns comparisons into golden image updates
+pub const UPDATE_ENV: &str = "UPDATE_GOLDEN";
+
+#[derive(Debug)]
+pub enum SnapshotError {
+    /// there is no golden image yet, the actual one has been written instead
+    Missing { actual: PathBuf },
+    /// images are of different sizes, as (width,height)
+    SizeMismatch { expected: (u32,u32), actual: (u32,u32), actual_path: PathBuf },
+    /// `differing` pixels exceed the tolerance
+    Mismatch { differing: usize, actual: PathBuf, diff: PathBuf },
+    Io(std::io::Error),
+    Decoding(png::DecodingError),
+    Encoding(png::EncodingError),
+}
+
+impl From<std::io::Error> for SnapshotError {
+    fn from(e: std::io::Error) -> Self {
+        SnapshotError::Io(e)
+    }
+}
+
+impl From<png::DecodingError> for SnapshotError {
+    fn from(e: png::DecodingError) -> Self {
+        SnapshotError::Decoding(e)
+    }
+}
+
+impl From<png::EncodingError> for SnapshotError {
+    fn from(e: png::EncodingError) -> Self {
+        SnapshotError::Encoding(e)
+    }
+}
+
+impl std::fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SnapshotError::Missing { actual } => write!(f,"no golden image, actual one written to {}",actual.display()),
+            SnapshotError::SizeMismatch { expected, actual, actual_path } => write!(
+                f,"expected {}x{} image, got {}x{} (written to {})",
+                expected.0,expected.1,actual.0,actual.1,actual_path.display()
+            ),
+            SnapshotError::Mismatch { differing, actual, diff } => write!(
+                f,"{} pixels differ; actual image: {}, diff: {}",
+                differing,actual.display(),diff.display()
+            ),
+            SnapshotError::Io(e) => write!(f,"io error: {}",e),
+            SnapshotError::Decoding(e) => write!(f,"failed to decode golden image: {}",e),
+            SnapshotError::Encoding(e) => write!(f,"failed to encode image: {}",e),
+        }
+    }
+}
+
+impl std::error::Error for SnapshotError {}
+
+/// A snapshot test of one root portal
+pub struct Snapshot<H: Host<Event = Event, Primitive = Primitive>> {
+    app: Application<H>,
+    rounds: usize,
+    portal: usize,
+    tolerance: u8,
+}
+
+impl<H: Host<Event = Event, Primitive = Primitive>> Snapshot<H>
+    where H::EntityData: for<'c> Visitor<'c, Primitive>
+{
+    /// `host` is expected to have its root entity set up
+    pub fn new(host: H, vp: Viewport) -> Self {
+        Self {
+            app: Application::new(host,vp),
+            rounds: 0,
+            portal: 0,
+            tolerance: 0,
+        }
+    }
+
+    /// Number of update rounds to run before the frame is taken
+    pub fn rounds(mut self, rounds: usize) -> Self {
+        self.rounds = rounds;
+        self
+    }
+
+    /// Root portal to take the frame of
+    pub fn portal(mut self, portal: usize) -> Self {
+        self.portal = portal;
+        self
+    }
+
+    /// Maximal per channel difference which still counts as equal
+    pub fn tolerance(mut self, tolerance: u8) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    pub fn host(&mut self) -> &mut H {
+        self.app.host()
+    }
+
+    /// Run the update rounds with empty event batches and return the last frame drawn
+    pub fn capture(&mut self) -> Primitive {
+        let portal = self.portal;
+        let mut last = None;
+        self.app.run_headless_with(std::iter::repeat_with(Vec::new).take(self.rounds),|frame| {
+            if frame.portal == portal {
+                last = Some(frame.primitive);
+            }
+        });
+        last.expect("root entity has no such portal")
+    }
+
+    /// Capture a frame and compare it with the golden image at `golden`
+    pub fn compare(&mut self, golden: impl AsRef<Path>) -> Result<(),SnapshotError> {
+        let actual = self.capture();
+        compare(&actual,golden.as_ref(),self.tolerance)
+    }
+
+    /// Panicking version of `compare`, for use in tests
+    pub fn assert_matches(&mut self, golden: impl AsRef<Path>) {
+        if let Err(e) = self.compare(golden.as_ref()) {
+            panic!("snapshot {} doesn't match: {}",golden.as_ref().display(),e)
+        }
+    }
+}
+
+/// Compare `actual` with a golden image, writing the actual image and a diff heatmap on mismatch
+pub fn compare(actual: &Primitive, golden: &Path, tolerance: u8) -> Result<(),SnapshotError> {
+    let actual_path = sibling(golden,"actual");
+    if std::env::var_os(UPDATE_ENV).is_some() {
+        return write_png(actual,golden);
+    }
+    if !golden.exists() {
+        write_png(actual,&actual_path)?;
+        return Err(SnapshotError::Missing { actual: actual_path });
+    }
+    let expected = read_png(golden)?;
+    if (expected.width(),expected.height()) != (actual.width(),actual.height()) {
+        write_png(actual,&actual_path)?;
+        return Err(SnapshotError::SizeMismatch {
+            expected: (expected.width(),expected.height()),
+            actual: (actual.width(),actual.height()),
+            actual_path,
+        });
+    }
+
+    let (heatmap,differing) = diff(&expected,actual,tolerance);
+    if differing == 0 {
+        return Ok(());
+    }
+    let diff_path = sibling(golden,"diff");
+    write_png(actual,&actual_path)?;
+    write_png(&heatmap,&diff_path)?;
+    Err(SnapshotError::Mismatch { differing, actual: actual_path, diff: diff_path })
+}
+
+/// Per pixel heatmap: matching pixels are a dimmed gray version of the expected image,
+/// differing ones go from yellow to red with the size of the largest channel difference.
+/// Also returns the count of differing pixels.
+pub fn diff(expected: &Primitive, actual: &Primitive, tolerance: u8) -> (Primitive,usize) {
+    let mut differing = 0;
+    let pixels = expected.pixels().iter().zip(actual.pixels().iter()).map(|(e,a)| {
+        let delta = [e.r.abs_diff(a.r),e.g.abs_diff(a.g),e.b.abs_diff(a.b),e.a.abs_diff(a.a)]
+            .into_iter().max().unwrap_or(0);
+        if delta > tolerance {
+            differing += 1;
+            Color::rgb(255,255 - delta,0)
+        } else {
+            let luma = ((e.r as u32 * 3 + e.g as u32 * 6 + e.b as u32) / 10) as u8;
+            let gray = (luma as u32 * e.a as u32 / 255 / 4) as u8;
+            Color::rgb(gray,gray,gray)
+        }
+    }).collect();
+    let heatmap = Primitive::from_pixels(expected.viewport(),pixels).expect("images of the same size");
+    (heatmap,differing)
+}
+
+/// `dir/name.png` -> `dir/name.<suffix>.png`
+fn sibling(golden: &Path, suffix: &str) -> PathBuf {
+    let stem = golden.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+    golden.with_file_name(format!("{}.{}.png",stem,suffix))
+}
+
+pub fn read_png(path: &Path) -> Result<Primitive,SnapshotError> {
+    Ok(raster_primitive::decode_png(File::open(path)?)?)
+}
+
+pub fn write_png(image: &Primitive, path: &Path) -> Result<(),SnapshotError> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let mut encoder = png::Encoder::new(BufWriter::new(File::create(path)?),image.width(),image.height());
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.write_header()?.write_image_data(&image.to_rgba8())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use types::render::Point;
+
+    use super::*;
+
+    const GOLDEN: &str = concat!(env!("CARGO_MANIFEST_DIR"),"/golden/gradient.png");
+
+    fn gradient() -> Primitive {
+        let pixels = (0..64u32).map(|i| Color::rgba((i % 8 * 32) as u8,(i / 8 * 32) as u8,128,255 - i as u8)).collect();
+        Primitive::from_pixels(Viewport { width: 8, height: 8 },pixels).unwrap()
+    }
+
+    /// A copy of the golden image in a directory of its own, so actual images and diffs don't end up in the tree
+    fn scratch(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join("golden_snapshots").join(name);
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("gradient.png");
+        std::fs::copy(GOLDEN,&path).unwrap();
+        path
+    }
+
+    /// `gradient` with pixels of the diagonal off by `by` in the red channel
+    fn off_by(by: u8) -> Primitive {
+        let mut image = gradient();
+        for i in 0..8 {
+            let c = image.get(Point::absolute(i,i)).unwrap();
+            image.set(Point::absolute(i,i),Color::rgba(c.r + by,c.g,c.b,c.a));
+        }
+        image
+    }
+
+    #[test]
+    fn identical() {
+        compare(&gradient(),Path::new(GOLDEN),0).unwrap();
+        assert_eq!(read_png(Path::new(GOLDEN)).unwrap(),gradient());
+    }
+
+    #[test]
+    fn within_tolerance() {
+        let golden = scratch("within_tolerance");
+        compare(&off_by(3),&golden,3).unwrap();
+        assert!(!sibling(&golden,"actual").exists() && !sibling(&golden,"diff").exists());
+    }
+
+    #[test]
+    fn beyond_tolerance() {
+        let golden = scratch("beyond_tolerance");
+        match compare(&off_by(3),&golden,2) {
+            Err(SnapshotError::Mismatch { differing, actual, diff }) => {
+                assert_eq!(differing,8);
+                assert_eq!(read_png(&actual).unwrap(),off_by(3));
+                let heatmap = read_png(&diff).unwrap();
+                assert_eq!(heatmap.get(Point::absolute(2,2)),Some(Color::rgb(255,252,0)));
+                assert_eq!(heatmap.get(Point::absolute(3,2)).map(|c| c.r == c.g && c.g == c.b),Some(true));
+            }
+            other => panic!("expected a mismatch, got {:?}",other),
+        }
+        assert!(matches!(compare(&Primitive::filled(Viewport { width: 4, height: 8 },Color::WHITE),&golden,255),Err(SnapshotError::SizeMismatch { .. })));
+    }
+}
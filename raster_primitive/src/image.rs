@@ -0,0 +1,215 @@
+use std::io::Read;
+use std::path::Path;
+
+use types::assets::Asset;
+use types::errors::assets::AssetError;
+use types::render::Viewport;
+
+use crate::{Color, Primitive};
+
+#[derive(Debug)]
+pub enum ImageError {
+    Io(std::io::Error),
+    Png(png::DecodingError),
+    /// malformed PPM data
+    Ppm(&'static str),
+    /// neither a PNG nor a PPM file
+    UnknownFormat,
+}
+
+impl From<std::io::Error> for ImageError {
+    fn from(e: std::io::Error) -> Self {
+        ImageError::Io(e)
+    }
+}
+
+impl From<png::DecodingError> for ImageError {
+    fn from(e: png::DecodingError) -> Self {
+        ImageError::Png(e)
+    }
+}
+
+impl std::fmt::Display for ImageError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ImageError::Io(e) => write!(f,"io error: {}",e),
+            ImageError::Png(e) => write!(f,"invalid png: {}",e),
+            ImageError::Ppm(e) => write!(f,"invalid ppm: {}",e),
+            ImageError::UnknownFormat => write!(f,"unknown image format"),
+        }
+    }
+}
+
+impl std::error::Error for ImageError {}
+
+impl From<ImageError> for AssetError {
+    fn from(e: ImageError) -> Self {
+        match e {
+            ImageError::Io(e) => AssetError::Io(e.kind()),
+            e => AssetError::Decoding(e.to_string()),
+        }
+    }
+}
+
+const PNG_SIGNATURE: &[u8] = &[0x89,b'P',b'N',b'G',b'\r',b'\n',0x1a,b'\n'];
+
+/// Decode a PNG image of any color type and bit depth
+pub fn decode_png<R: Read>(r: R) -> Result<Primitive,png::DecodingError> {
+    let mut decoder = png::Decoder::new(r);
+    decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+    let mut reader = decoder.read_info()?;
+    let mut buf = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buf)?;
+    let buf = &buf[..info.buffer_size()];
+    let pixels = match info.color_type {
+        png::ColorType::Rgba => buf.chunks_exact(4).map(|c| Color::rgba(c[0],c[1],c[2],c[3])).collect(),
+        png::ColorType::Rgb => buf.chunks_exact(3).map(|c| Color::rgb(c[0],c[1],c[2])).collect(),
+        png::ColorType::GrayscaleAlpha => buf.chunks_exact(2).map(|c| Color::rgba(c[0],c[0],c[0],c[1])).collect(),
+        png::ColorType::Grayscale => buf.iter().map(|&c| Color::rgb(c,c,c)).collect(),
+        png::ColorType::Indexed => unreachable!("palette is expanded by the decoder"),
+    };
+    Ok(Primitive::from_pixels(Viewport { width: info.width, height: info.height },pixels).expect("decoder output matches its size"))
+}
+
+/// Cursor over the header of a netpbm file
+struct Header<'b> {
+    bytes: &'b [u8],
+    pos: usize,
+}
+
+impl<'b> Header<'b> {
+    /// Skip whitespace and `#` comments
+    fn skip(&mut self) {
+        while let Some(&b) = self.bytes.get(self.pos) {
+            if b == b'#' {
+                while self.bytes.get(self.pos).is_some_and(|&b| b != b'\n') {
+                    self.pos += 1;
+                }
+            } else if b.is_ascii_whitespace() {
+                self.pos += 1;
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn number(&mut self) -> Result<u32,ImageError> {
+        self.skip();
+        let start = self.pos;
+        while self.bytes.get(self.pos).is_some_and(u8::is_ascii_digit) {
+            self.pos += 1;
+        }
+        std::str::from_utf8(&self.bytes[start..self.pos]).ok()
+            .and_then(|s| s.parse().ok())
+            .ok_or(ImageError::Ppm("expected a number"))
+    }
+}
+
+/// Decode a binary (`P6`) or plain (`P3`) PPM image
+pub fn decode_ppm(bytes: &[u8]) -> Result<Primitive,ImageError> {
+    let binary = match bytes.get(..2) {
+        Some(b"P6") => true,
+        Some(b"P3") => false,
+        _ => return Err(ImageError::Ppm("not a P3 or P6 file")),
+    };
+    let mut header = Header { bytes, pos: 2 };
+    let (width,height,max) = (header.number()?,header.number()?,header.number()?);
+    if max == 0 || max > u16::MAX as u32 {
+        return Err(ImageError::Ppm("maximum value out of range"));
+    }
+    let count = (width as usize).checked_mul(height as usize).and_then(|n| n.checked_mul(3))
+        .ok_or(ImageError::Ppm("image too large"))?;
+    // nothing is allocated for the size in the header before the file is known to hold that much
+    let samples: Vec<u32> = if binary {
+        // exactly one whitespace separates the header from the raster
+        let data = bytes.get(header.pos + 1..).unwrap_or(&[]);
+        let wide = max > 255;
+        let size = if wide { 2 } else { 1 };
+        if count.checked_mul(size).is_none_or(|len| data.len() < len) {
+            return Err(ImageError::Ppm("truncated raster"));
+        }
+        data.chunks_exact(size).take(count).map(|s| {
+            if wide { u16::from_be_bytes([s[0],s[1]]) as u32 } else { s[0] as u32 }
+        }).collect()
+    } else {
+        let mut samples = vec![];
+        for _ in 0..count {
+            samples.push(header.number()?);
+        }
+        samples
+    };
+    let scale = |v: u32| ((v.min(max) * 255 + max / 2) / max) as u8;
+    let pixels = samples.chunks_exact(3).map(|c| Color::rgb(scale(c[0]),scale(c[1]),scale(c[2]))).collect();
+    Ok(Primitive::from_pixels(Viewport { width, height },pixels).expect("sample count matches the size"))
+}
+
+impl Primitive {
+    /// Decode a PNG or a PPM image, the format is detected by its signature
+    pub fn decode(bytes: &[u8]) -> Result<Self,ImageError> {
+        if bytes.starts_with(PNG_SIGNATURE) {
+            Ok(decode_png(bytes)?)
+        } else if bytes.starts_with(b"P6") || bytes.starts_with(b"P3") {
+            decode_ppm(bytes)
+        } else {
+            Err(ImageError::UnknownFormat)
+        }
+    }
+
+    /// Read and decode an image file
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self,ImageError> {
+        Self::decode(&std::fs::read(path)?)
+    }
+}
+
+/// Images can be requested through the asset server
+impl Asset for Primitive {
+    fn from_bytes(bytes: Vec<u8>, _path: &Path) -> Result<Self,AssetError> {
+        Ok(Self::decode(&bytes)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use types::render::{Point, Primitive as _};
+
+    use super::*;
+
+    fn ppm_error(bytes: &[u8]) -> Option<&'static str> {
+        match decode_ppm(bytes) {
+            Err(ImageError::Ppm(what)) => Some(what),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn decodes_binary_and_plain() {
+        let binary = decode_ppm(b"P6 2 1 255\n\xff\x00\x00\x00\x80\xff").unwrap();
+        let plain = decode_ppm(b"P3\n# two pixels\n2 1\n15\n15 0 0  0 8 15\n").unwrap();
+        assert_eq!(binary.size(),Viewport { width: 2, height: 1 });
+        assert_eq!(binary.get(Point::absolute(1,0)),Some(Color::rgb(0,128,255)));
+        assert_eq!(plain.get(Point::absolute(0,0)),Some(Color::rgb(255,0,0)));
+        assert_eq!(plain.get(Point::absolute(1,0)),Some(Color::rgb(0,136,255)));
+        let wide = decode_ppm(b"P6 1 1 65535\n\xff\xff\x00\x00\x80\x00").unwrap();
+        assert_eq!(wide.get(Point::absolute(0,0)),Some(Color::rgb(255,0,128)));
+    }
+
+    #[test]
+    fn truncated_headers_and_rasters() {
+        assert_eq!(ppm_error(b"P6 2"),Some("expected a number"));
+        assert_eq!(ppm_error(b"P6 2 1"),Some("expected a number"));
+        assert_eq!(ppm_error(b"P6 2 1 0\n"),Some("maximum value out of range"));
+        assert_eq!(ppm_error(b"P6 2 1 255\n\xff\x00\x00"),Some("truncated raster"));
+        assert_eq!(ppm_error(b"P6 1 1 65535\n\xff\xff\x00"),Some("truncated raster"));
+        assert_eq!(ppm_error(b"P3 2 1 255\n1 2 3 4"),Some("expected a number"));
+        assert_eq!(ppm_error(b"P5 1 1 255\n\x00"),Some("not a P3 or P6 file"));
+    }
+
+    #[test]
+    fn oversized_headers() {
+        // the header claims far more than the file holds, nothing is allocated for it
+        assert_eq!(ppm_error(b"P6 100000 100000 255\n\x00\x00\x00"),Some("truncated raster"));
+        assert_eq!(ppm_error(b"P6 4000000000 4000000000 65535\n"),Some("image too large"));
+        assert_eq!(ppm_error(b"P3 100000 100000 255\n1 2 3\n"),Some("expected a number"));
+        assert_eq!(ppm_error(b"P6 99999999999 1 255\n"),Some("expected a number"));
+    }
+}
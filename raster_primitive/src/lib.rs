@@ -0,0 +1,494 @@
+extern crate types;
+extern crate fontdue;
+extern crate png;
+
+mod image;
+mod shapes;
+mod text;
+
+pub use image::{decode_png, decode_ppm, ImageError};
+pub use text::BOLD_WEIGHT;
+
+use types::render::{self, BlendMode, Compositing, Point, Rect, Transform, Viewport};
+
+/// A straight (not premultiplied) 8 bit per channel RGBA color
+#[derive(Clone,Copy,Debug,Default,Hash,Eq,PartialEq)]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub a: u8,
+}
+
+impl Color {
+    pub const TRANSPARENT: Color = Color::rgba(0,0,0,0);
+    pub const BLACK: Color = Color::rgb(0,0,0);
+    pub const WHITE: Color = Color::rgb(255,255,255);
+
+    pub const fn rgba(r: u8,g: u8,b: u8,a: u8) -> Self {
+        Self { r, g, b, a }
+    }
+
+    pub const fn rgb(r: u8,g: u8,b: u8) -> Self {
+        Self::rgba(r,g,b,255)
+    }
+
+    /// Premultiplied channels in `0.0..=1.0`
+    fn premultiplied(self) -> [f32; 4] {
+        let a = self.a as f32 / 255.;
+        [
+            self.r as f32 / 255. * a,
+            self.g as f32 / 255. * a,
+            self.b as f32 / 255. * a,
+            a,
+        ]
+    }
+
+    fn from_premultiplied([r,g,b,a]: [f32; 4]) -> Self {
+        if a <= 0. {
+            return Color::TRANSPARENT;
+        }
+        let channel = |c: f32| ((c / a).clamp(0.,1.) * 255.).round() as u8;
+        Color::rgba(channel(r),channel(g),channel(b),(a.clamp(0.,1.) * 255.).round() as u8)
+    }
+
+    /// Composition of `self` onto `dst` with a blend mode
+    pub fn blend(self, dst: Color, mode: BlendMode) -> Color {
+        let mix = |f: fn(f32,f32) -> f32| {
+            let (s,d) = (self.premultiplied(),dst.premultiplied());
+            let (sa,da) = (s[3],d[3]);
+            let mut out = [0f32; 4];
+            for i in 0..3 {
+                // straight channels go into the blend function
+                let (cs,cd) = (if sa > 0. { s[i] / sa } else { 0. }, if da > 0. { d[i] / da } else { 0. });
+                out[i] = s[i] * (1. - da) + d[i] * (1. - sa) + sa * da * f(cs,cd);
+            }
+            out[3] = sa + da - sa * da;
+            Color::from_premultiplied(out)
+        };
+        match mode {
+            BlendMode::Normal => self.over(dst),
+            _ if self.a == 0 => dst,
+            BlendMode::Multiply => mix(|s,d| s * d),
+            BlendMode::Screen => mix(|s,d| s + d - s * d),
+            BlendMode::Additive => {
+                let (s,d) = (self.premultiplied(),dst.premultiplied());
+                Color::from_premultiplied([s[0] + d[0], s[1] + d[1], s[2] + d[2], (s[3] + d[3]).min(1.)])
+            }
+        }
+    }
+
+    /// Source-over composition of `self` onto `dst`
+    pub fn over(self, dst: Color) -> Color {
+        match self.a {
+            255 => self,
+            0 => dst,
+            _ => {
+                let (s,d) = (self.premultiplied(),dst.premultiplied());
+                let k = 1. - s[3];
+                Color::from_premultiplied([s[0] + d[0] * k, s[1] + d[1] * k, s[2] + d[2] * k, s[3] + d[3] * k])
+            }
+        }
+    }
+}
+
+/// Mixed premultiplied, so fading into transparency doesn't darken
+impl types::anim::Lerp for Color {
+    fn lerp(&self, to: &Self, t: f32) -> Self {
+        let (from,to) = (self.premultiplied(),to.premultiplied());
+        Color::from_premultiplied(std::array::from_fn(|i| from[i].lerp(&to[i],t)))
+    }
+}
+
+/// A software RGBA8 image, stored row by row starting from the upper left corner
+#[derive(Clone,Debug,PartialEq)]
+pub struct Primitive {
+    width: u32,
+    height: u32,
+    pixels: Vec<Color>,
+    /// pixels outside of it are not drawn to, as (x0,y0,x1,y1)
+    scissor: Option<(u32,u32,u32,u32)>,
+}
+
+impl Primitive {
+    /// Wrap a buffer of `vp.width * vp.height` pixels; `None` if the length doesn't match
+    pub fn from_pixels(vp: Viewport, pixels: Vec<Color>) -> Option<Self> {
+        if pixels.len() == vp.width as usize * vp.height as usize {
+            Some(Self { width: vp.width, height: vp.height, pixels, scissor: None })
+        } else {
+            None
+        }
+    }
+
+    /// Build from a tightly packed RGBA8 byte buffer
+    pub fn from_rgba8(vp: Viewport, bytes: &[u8]) -> Option<Self> {
+        if bytes.len() != vp.width as usize * vp.height as usize * 4 {
+            return None;
+        }
+        let pixels = bytes.chunks_exact(4).map(|c| Color::rgba(c[0],c[1],c[2],c[3])).collect();
+        Self::from_pixels(vp,pixels)
+    }
+
+    /// A primitive filled with a single color
+    pub fn filled(vp: Viewport, color: Color) -> Self {
+        Self {
+            width: vp.width,
+            height: vp.height,
+            pixels: vec![color; vp.width as usize * vp.height as usize],
+            scissor: None,
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn viewport(&self) -> Viewport {
+        Viewport { width: self.width, height: self.height }
+    }
+
+    pub fn pixels(&self) -> &[Color] {
+        &self.pixels[..]
+    }
+
+    pub fn pixels_mut(&mut self) -> &mut [Color] {
+        &mut self.pixels[..]
+    }
+
+    /// Tightly packed RGBA8 bytes, suitable for texture uploads and image encoders
+    pub fn to_rgba8(&self) -> Vec<u8> {
+        self.pixels.iter().flat_map(|c| [c.r,c.g,c.b,c.a]).collect()
+    }
+
+    pub fn get(&self, p: Point<u32>) -> Option<Color> {
+        if p.x() < self.width && p.y() < self.height {
+            Some(self.pixels[self.offset(p.x(),p.y())])
+        } else {
+            None
+        }
+    }
+
+    /// Overwrite a pixel, writes out of bounds or outside of the scissor are ignored
+    pub fn set(&mut self, p: Point<u32>, color: Color) {
+        if self.writable(p.x(),p.y()) {
+            let off = self.offset(p.x(),p.y());
+            self.pixels[off] = color;
+        }
+    }
+
+    /// Composite `color` over a pixel, writes out of bounds or outside of the scissor are ignored
+    pub fn blend(&mut self, p: Point<u32>, color: Color) {
+        if self.writable(p.x(),p.y()) {
+            let off = self.offset(p.x(),p.y());
+            self.pixels[off] = color.over(self.pixels[off]);
+        }
+    }
+
+    /// Fill the whole primitive, or what the scissor lets through
+    pub fn fill(&mut self, color: Color) {
+        if self.scissor.is_none() {
+            self.pixels.iter_mut().for_each(|p| *p = color);
+            return;
+        }
+        let (x0,y0,x1,y1) = self.bounds();
+        for y in y0..y1 {
+            let row = self.offset(0,y);
+            self.pixels[row + x0 as usize..row + x1 as usize].fill(color);
+        }
+    }
+
+    /// Current scissor rect, in pixels
+    pub fn scissor(&self) -> Option<render::Rect> {
+        self.scissor.map(|(x0,y0,x1,y1)| render::Rect::<(),()>::zero()
+            .upper_left_absolute(Point::absolute(x0,y0))
+            .down_right_absolute(Point::absolute(x1,y1)))
+    }
+
+    fn offset(&self, x: u32, y: u32) -> usize {
+        y as usize * self.width as usize + x as usize
+    }
+
+    /// Pixels which may be drawn to, as (x0,y0,x1,y1)
+    fn bounds(&self) -> (u32,u32,u32,u32) {
+        let (x0,y0,x1,y1) = self.scissor.unwrap_or((0,0,self.width,self.height));
+        (x0.min(self.width),y0.min(self.height),x1.min(self.width),y1.min(self.height))
+    }
+
+    fn writable(&self, x: u32, y: u32) -> bool {
+        let (x0,y0,x1,y1) = self.bounds();
+        x >= x0 && x < x1 && y >= y0 && y < y1
+    }
+
+    /// Bilinear sample at pixel space coordinates, pixel centers are at `n + 0.5`.
+    /// Interpolation is done on premultiplied values, so transparent neighbours don't bleed their color.
+    fn sample(&self, x: f32, y: f32) -> Color {
+        if self.width == 0 || self.height == 0 {
+            return Color::TRANSPARENT;
+        }
+        let (x,y) = (x - 0.5, y - 0.5);
+        let (x0,y0) = (x.floor(),y.floor());
+        let (tx,ty) = (x - x0, y - y0);
+        let clamp_x = |v: f32| v.clamp(0.,(self.width - 1) as f32) as u32;
+        let clamp_y = |v: f32| v.clamp(0.,(self.height - 1) as f32) as u32;
+        let (xa,xb,ya,yb) = (clamp_x(x0),clamp_x(x0 + 1.),clamp_y(y0),clamp_y(y0 + 1.));
+        let px = |x,y| self.pixels[self.offset(x,y)].premultiplied();
+        let (c00,c10,c01,c11) = (px(xa,ya),px(xb,ya),px(xa,yb),px(xb,yb));
+        let mut out = [0f32; 4];
+        for i in 0..4 {
+            let top = c00[i] + (c10[i] - c00[i]) * tx;
+            let bottom = c01[i] + (c11[i] - c01[i]) * tx;
+            out[i] = top + (bottom - top) * ty;
+        }
+        Color::from_premultiplied(out)
+    }
+
+    /// Scale `src` into `place`, then composite it over `self` according to `params`
+    fn paint(&mut self, place: Rect<f32,f32>, src: &Self, params: Compositing) {
+        let (x0,y0,x1,y1) = self.absolute_bounds(place);
+        let (dw,dh) = (x1 - x0, y1 - y0);
+        if dw <= 0. || dh <= 0. || src.width == 0 || src.height == 0 || params.opacity <= 0. {
+            return;
+        }
+        // scale factors from destination pixels to source pixels
+        let (sx,sy) = (src.width as f32 / dw, src.height as f32 / dh);
+        let (bx0,by0,bx1,by1) = self.bounds();
+        let inverse = match params.transform {
+            Some(t) => match t.placed(place,self.viewport()).invert() {
+                Some(inverse) => Some(inverse),
+                None => return,
+            },
+            None => None,
+        };
+        let ((from_x,to_x),(from_y,to_y)) = match params.transform {
+            Some(t) => {
+                let area = self.viewport().pixel_box(place.transformed_bounds(&t,self.viewport()));
+                let (ul,dr) = (area.upper_left(),area.down_right());
+                ((ul.x().max(bx0),dr.x().min(bx1)),(ul.y().max(by0),dr.y().min(by1)))
+            }
+            None => (
+                ((x0.round().max(0.) as u32).max(bx0), (x1.round().max(0.) as u32).min(bx1)),
+                ((y0.round().max(0.) as u32).max(by0), (y1.round().max(0.) as u32).min(by1)),
+            ),
+        };
+        let exact = inverse.is_none() && (sx - 1.).abs() < f32::EPSILON && (sy - 1.).abs() < f32::EPSILON
+            && x0.fract() == 0. && y0.fract() == 0.;
+        let opacity = params.opacity.min(1.);
+
+        for y in from_y..to_y {
+            for x in from_x..to_x {
+                let (color,coverage) = if let Some(inverse) = inverse {
+                    // where the pixel center comes from, in untransformed destination pixels
+                    let p = inverse.apply(Point::relative(x as f32 + 0.5,y as f32 + 0.5));
+                    let (px,py) = (p.x() - x0, p.y() - y0);
+                    // anti-aliased edges: coverage falls off within half a pixel of the place's border
+                    let coverage = (px.min(dw - px) + 0.5).clamp(0.,1.) * (py.min(dh - py) + 0.5).clamp(0.,1.);
+                    if coverage <= 0. {
+                        continue;
+                    }
+                    (src.sample(px.clamp(0.,dw) * sx, py.clamp(0.,dh) * sy),coverage)
+                } else if exact {
+                    let (ox,oy) = ((x as f32 - x0) as u32, (y as f32 - y0) as u32);
+                    (src.pixels[src.offset(ox.min(src.width - 1),oy.min(src.height - 1))],1.)
+                } else {
+                    (src.sample((x as f32 + 0.5 - x0) * sx, (y as f32 + 0.5 - y0) * sy),1.)
+                };
+                let color = match coverage * opacity {
+                    k if k >= 1. => color,
+                    k => Color { a: (color.a as f32 * k).round() as u8, ..color },
+                };
+                let off = self.offset(x,y);
+                self.pixels[off] = color.blend(self.pixels[off],params.blend);
+            }
+        }
+    }
+
+    /// Absolute pixel bounds of a relative rect, may lie partially outside of `self`
+    fn absolute_bounds(&self, place: Rect<f32,f32>) -> (f32,f32,f32,f32) {
+        let (ul,dr) = (place.upper_left(),place.down_right());
+        (
+            ul.x() * self.width as f32,
+            ul.y() * self.height as f32,
+            dr.x() * self.width as f32,
+            dr.y() * self.height as f32,
+        )
+    }
+}
+
+impl render::Primitive for Primitive {
+    type Color = Color;
+
+    fn copy_from(&mut self, place: Rect<f32,f32>, src: &Self) {
+        self.paint(place,src,Compositing::default());
+    }
+
+    fn cut(&self, part: Rect) -> Self {
+        let (ul,dr) = (part.upper_left(),part.down_right());
+        let (x0,x1) = (ul.x().min(dr.x()).min(self.width), ul.x().max(dr.x()).min(self.width));
+        let (y0,y1) = (ul.y().min(dr.y()).min(self.height), ul.y().max(dr.y()).min(self.height));
+        let mut pixels = Vec::with_capacity(((x1 - x0) * (y1 - y0)) as usize);
+        for y in y0..y1 {
+            let row = self.offset(0,y);
+            pixels.extend_from_slice(&self.pixels[row + x0 as usize..row + x1 as usize]);
+        }
+        Self { width: x1 - x0, height: y1 - y0, pixels, scissor: None }
+    }
+
+    fn resize(&self, scale: (f32, f32)) -> Self {
+        let width = (self.width as f32 * scale.0).round().max(0.) as u32;
+        let height = (self.height as f32 * scale.1).round().max(0.) as u32;
+        if width == 0 || height == 0 {
+            return Self::blank(Viewport { width, height });
+        }
+        let (sx,sy) = (self.width as f32 / width as f32, self.height as f32 / height as f32);
+        let mut pixels = Vec::with_capacity(width as usize * height as usize);
+        for y in 0..height {
+            for x in 0..width {
+                pixels.push(self.sample((x as f32 + 0.5) * sx, (y as f32 + 0.5) * sy));
+            }
+        }
+        Self { width, height, pixels, scissor: None }
+    }
+
+    fn blank(size: Viewport) -> Self {
+        Self::filled(size,Color::TRANSPARENT)
+    }
+
+    fn size(&self) -> Viewport {
+        self.viewport()
+    }
+
+    fn copy_transformed(&mut self, place: Rect<f32,f32>, src: &Self, transform: Transform) {
+        self.paint(place,src,Compositing { transform: Some(transform), ..Compositing::default() });
+    }
+
+    fn blend_from(&mut self, place: Rect<f32,f32>, src: &Self, params: Compositing) {
+        self.paint(place,src,params);
+    }
+
+    fn set_scissor(&mut self, part: Option<Rect>) {
+        self.scissor = part.map(|r| {
+            let (ul,dr) = (r.upper_left(),r.down_right());
+            (ul.x().min(dr.x()),ul.y().min(dr.y()),ul.x().max(dr.x()),ul.y().max(dr.y()))
+        });
+    }
+
+    fn clear(&mut self, part: Rect) {
+        let (ul,dr) = (part.upper_left(),part.down_right());
+        let (bx0,by0,bx1,by1) = self.bounds();
+        let (x0,x1) = (ul.x().min(dr.x()).max(bx0), ul.x().max(dr.x()).min(bx1));
+        let (y0,y1) = (ul.y().min(dr.y()).max(by0), ul.y().max(dr.y()).min(by1));
+        if x0 >= x1 {
+            return;
+        }
+        for y in y0..y1 {
+            let row = self.offset(0,y);
+            self.pixels[row + x0 as usize..row + x1 as usize].fill(Color::TRANSPARENT);
+        }
+    }
+
+    fn footprint(&self) -> usize {
+        std::mem::size_of::<Self>() + self.pixels.len() * std::mem::size_of::<Color>()
+    }
+}
+
+impl types::app::Present for Primitive {
+    fn rgba8(&self) -> Vec<u8> {
+        self.to_rgba8()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use types::render::Primitive as _;
+
+    use super::*;
+
+    fn vp(width: u32, height: u32) -> Viewport {
+        Viewport { width, height }
+    }
+
+    fn pixels(p: &Primitive) -> Vec<Color> {
+        p.pixels().to_vec()
+    }
+
+    /// Pixel rect from (x0,y0) to (x1,y1)
+    fn part(x0: u32, y0: u32, x1: u32, y1: u32) -> render::Rect {
+        render::Rect::<(),()>::zero().upper_left_absolute(Point::absolute(x0,y0)).down_right_absolute(Point::absolute(x1,y1))
+    }
+
+    #[test]
+    fn translucent_copies_are_composited() {
+        let mut dst = Primitive::filled(vp(2,2),Color::rgb(0,0,255));
+        dst.copy_from(Rect::full_box(),&Primitive::filled(vp(2,2),Color::rgba(255,0,0,128)));
+        assert_eq!(pixels(&dst),vec![Color::rgb(128,0,127); 4]);
+    }
+
+    #[test]
+    fn transparent_copies_leave_the_destination() {
+        let mut dst = Primitive::filled(vp(2,2),Color::rgba(10,20,30,200));
+        dst.copy_from(Rect::full_box(),&Primitive::filled(vp(2,2),Color::TRANSPARENT));
+        assert_eq!(pixels(&dst),vec![Color::rgba(10,20,30,200); 4]);
+    }
+
+    #[test]
+    fn resizing_interpolates_between_pixels() {
+        let src = Primitive::from_pixels(vp(2,1),vec![Color::BLACK,Color::WHITE]).unwrap();
+        let grey = |v| Color::rgb(v,v,v);
+        assert_eq!(pixels(&src.resize((2.,1.))),vec![grey(0),grey(64),grey(191),grey(255)]);
+        // transparent neighbours fade the alpha, not the color
+        let src = Primitive::from_pixels(vp(2,1),vec![Color::TRANSPARENT,Color::rgb(255,0,0)]).unwrap();
+        assert_eq!(pixels(&src.resize((2.,1.)))[1..3],[Color::rgba(255,0,0,64),Color::rgba(255,0,0,191)]);
+    }
+
+    #[test]
+    fn cuts_are_clamped_to_the_edges() {
+        let src = Primitive::from_pixels(vp(3,3),(0..9).map(|i| Color::rgb(i,0,0)).collect()).unwrap();
+        let cut = src.cut(part(1,2,5,5));
+        assert_eq!(cut.viewport(),vp(2,1));
+        assert_eq!(pixels(&cut),vec![Color::rgb(7,0,0),Color::rgb(8,0,0)]);
+        // swapped corners cut the same pixels
+        assert_eq!(src.cut(part(5,5,1,2)),cut);
+        assert_eq!(pixels(&src.cut(part(0,0,3,3))),pixels(&src));
+        let outside = src.cut(part(4,4,6,6));
+        assert_eq!(outside.viewport(),vp(0,0));
+        assert!(outside.pixels().is_empty());
+    }
+
+    #[test]
+    fn blend_modes_over_opaque_destinations() {
+        let (src,dst) = (Color::rgb(255,128,0),Color::rgb(128,128,255));
+        assert_eq!(src.blend(dst,BlendMode::Multiply),Color::rgb(128,64,0));
+        assert_eq!(src.blend(dst,BlendMode::Screen),Color::rgb(255,192,255));
+        assert_eq!(Color::rgb(100,50,0).blend(Color::rgb(100,100,100),BlendMode::Additive),Color::rgb(200,150,100));
+        assert_eq!(src.blend(Color::rgb(200,200,200),BlendMode::Additive),Color::rgb(255,255,200));
+    }
+
+    #[test]
+    fn blend_modes_over_translucent_destinations() {
+        // where the destination is uncovered the source is kept as it is
+        let (src,dst) = (Color::rgb(255,128,0),Color::rgba(0,0,255,128));
+        assert_eq!(src.blend(dst,BlendMode::Multiply),Color::rgb(127,64,0));
+        assert_eq!(src.blend(dst,BlendMode::Screen),Color::rgb(255,128,128));
+        assert_eq!(Color::rgb(100,50,0).blend(dst,BlendMode::Additive),Color::rgb(100,50,128));
+        assert_eq!(Color::TRANSPARENT.blend(dst,BlendMode::Screen),dst);
+        assert_eq!(src.blend(Color::TRANSPARENT,BlendMode::Multiply),src);
+    }
+
+    #[test]
+    fn opacity_scales_the_source() {
+        let params = |opacity,blend| Compositing { transform: None, opacity, blend };
+        let mut dst = Primitive::filled(vp(2,2),Color::WHITE);
+        dst.blend_from(Rect::full_box(),&Primitive::filled(vp(2,2),Color::BLACK),params(0.5,BlendMode::Normal));
+        assert_eq!(pixels(&dst),vec![Color::rgb(127,127,127); 4]);
+        let mut dst = Primitive::filled(vp(2,2),Color::rgb(128,128,255));
+        dst.blend_from(Rect::full_box(),&Primitive::filled(vp(2,2),Color::rgb(255,128,0)),params(0.5,BlendMode::Multiply));
+        assert_eq!(pixels(&dst),vec![Color::rgb(128,96,127); 4]);
+        let mut dst = Primitive::filled(vp(2,2),Color::WHITE);
+        dst.blend_from(Rect::full_box(),&Primitive::filled(vp(2,2),Color::BLACK),params(0.,BlendMode::Normal));
+        assert_eq!(pixels(&dst),vec![Color::WHITE; 4]);
+    }
+}
@@ -0,0 +1,392 @@
+use std::f32::consts::{FRAC_PI_2, PI};
+
+use types::render::{PathSegment, Point, Rect, Shape, ShapePrimitive};
+
+use crate::{Color, Primitive};
+
+/// Vertical samples taken per pixel row; horizontal coverage is computed exactly
+const SUBSAMPLES: usize = 16;
+
+/// Longest miter of a join, relative to the stroke width, longer ones are bevelled like in SVG
+const MITER_LIMIT: f32 = 4.;
+
+/// A point in pixel space
+type Px = (f32,f32);
+
+/// A flattened outline in pixel space
+struct Contour {
+    points: Vec<Px>,
+    closed: bool,
+}
+
+/// Maps points relative to a place into pixel space
+struct Mapping {
+    origin: Px,
+    size: Px,
+}
+
+impl Mapping {
+    fn new(target: &Primitive, place: Rect<f32,f32>) -> Self {
+        let (x0,y0,x1,y1) = target.absolute_bounds(place);
+        Self { origin: (x0,y0), size: (x1 - x0, y1 - y0) }
+    }
+
+    fn point(&self, p: Point<f32>) -> Px {
+        (self.origin.0 + p.x() * self.size.0, self.origin.1 + p.y() * self.size.1)
+    }
+
+    fn rect(&self, r: Rect<f32,f32>) -> (Px,Px) {
+        (self.point(r.upper_left()),self.point(r.down_right()))
+    }
+}
+
+/// Segments used to approximate an arc, about one per two pixels of its length
+fn arc_steps(radius: f32, sweep: f32) -> usize {
+    ((radius * sweep.abs()) / 2.).ceil().clamp(4.,256.) as usize
+}
+
+/// Append points of an elliptic arc, angles go clockwise on screen starting from the positive x axis
+fn arc(out: &mut Vec<Px>, center: Px, radii: Px, start: f32, sweep: f32) {
+    let steps = arc_steps(radii.0.max(radii.1),sweep);
+    for i in 0..=steps {
+        let a = start + sweep * i as f32 / steps as f32;
+        out.push((center.0 + radii.0 * a.cos(), center.1 + radii.1 * a.sin()));
+    }
+}
+
+fn distance(a: Px, b: Px) -> f32 {
+    ((b.0 - a.0).powi(2) + (b.1 - a.1).powi(2)).sqrt()
+}
+
+/// Append a bezier curve given by its control points (the first one is already in `out`)
+fn bezier(out: &mut Vec<Px>, ctrl: &[Px]) {
+    let length: f32 = ctrl.windows(2).map(|w| distance(w[0],w[1])).sum();
+    let steps = (length / 4.).ceil().clamp(4.,64.) as usize;
+    for i in 1..=steps {
+        let t = i as f32 / steps as f32;
+        // de Casteljau
+        let mut pts = ctrl.to_vec();
+        while pts.len() > 1 {
+            pts = pts.windows(2).map(|w| (w[0].0 + (w[1].0 - w[0].0) * t, w[0].1 + (w[1].1 - w[0].1) * t)).collect();
+        }
+        out.push(pts[0]);
+    }
+}
+
+fn contours(shape: &Shape, map: &Mapping) -> Vec<Contour> {
+    match shape {
+        Shape::Rect { rect, radii } => {
+            let ((x0,y0),(x1,y1)) = map.rect(*rect);
+            let (x0,x1,y0,y1) = (x0.min(x1),x0.max(x1),y0.min(y1),y0.max(y1));
+            let max = ((x1 - x0).min(y1 - y0) / 2.).max(0.);
+            let r: Vec<f32> = radii.iter().map(|r| r.clamp(0.,max)).collect();
+            let mut points = vec![];
+            // clockwise on screen, starting with the upper left corner
+            let corners = [
+                ((x0 + r[0], y0 + r[0]), r[0], PI),
+                ((x1 - r[1], y0 + r[1]), r[1], -FRAC_PI_2),
+                ((x1 - r[2], y1 - r[2]), r[2], 0.),
+                ((x0 + r[3], y1 - r[3]), r[3], FRAC_PI_2),
+            ];
+            for (center,radius,start) in corners {
+                if radius > 0. {
+                    arc(&mut points,center,(radius,radius),start,FRAC_PI_2);
+                } else {
+                    points.push(center);
+                }
+            }
+            vec![Contour { points, closed: true }]
+        }
+        Shape::Line { from, to } => vec![Contour { points: vec![map.point(*from),map.point(*to)], closed: false }],
+        Shape::Ellipse { bounds } => {
+            let ((x0,y0),(x1,y1)) = map.rect(*bounds);
+            let mut points = vec![];
+            arc(&mut points,((x0 + x1) / 2., (y0 + y1) / 2.),(((x1 - x0) / 2.).abs(), ((y1 - y0) / 2.).abs()),0.,2. * PI);
+            points.pop();
+            vec![Contour { points, closed: true }]
+        }
+        Shape::Path(segments) => {
+            let mut out = vec![];
+            let mut current: Vec<Px> = vec![];
+            let origin = map.point(Point::relative(0.,0.));
+            for seg in segments {
+                // a path without a MoveTo starts at the origin of its place
+                if current.is_empty() && !matches!(seg,PathSegment::MoveTo(_) | PathSegment::Close) {
+                    current.push(origin);
+                }
+                match *seg {
+                    PathSegment::MoveTo(p) => {
+                        if current.len() > 1 {
+                            out.push(Contour { points: std::mem::take(&mut current), closed: false });
+                        }
+                        current = vec![map.point(p)];
+                    }
+                    PathSegment::LineTo(p) => current.push(map.point(p)),
+                    PathSegment::QuadTo(c,p) => {
+                        let start = current[current.len() - 1];
+                        bezier(&mut current,&[start,map.point(c),map.point(p)]);
+                    }
+                    PathSegment::CubicTo(c1,c2,p) => {
+                        let start = current[current.len() - 1];
+                        bezier(&mut current,&[start,map.point(c1),map.point(c2),map.point(p)]);
+                    }
+                    PathSegment::Close => {
+                        if current.len() > 1 {
+                            let start = current[0];
+                            out.push(Contour { points: std::mem::take(&mut current), closed: true });
+                            current.push(start);
+                        }
+                    }
+                }
+            }
+            if current.len() > 1 {
+                out.push(Contour { points: current, closed: false });
+            }
+            out
+        }
+    }
+}
+
+/// Polygons covering the stroke of a contour: a quad per segment and a miter or bevel per join, ends are butt like in SVG.
+/// All of them are oriented the same way, so the non-zero rule unites them and overlaps are painted once.
+fn stroke_polygons(contour: &Contour, width: f32) -> Vec<Vec<Px>> {
+    let hw = width / 2.;
+    let mut pts = contour.points.clone();
+    pts.dedup();
+    if contour.closed && pts.len() > 1 && pts[0] == pts[pts.len() - 1] {
+        pts.pop();
+    }
+    let closed = contour.closed && pts.len() > 2;
+    let segments = if closed { pts.len() } else { pts.len().saturating_sub(1) };
+    // normals of the segments, `hw` long
+    let normals: Vec<Px> = (0..segments).map(|i| {
+        let (a,b) = (pts[i],pts[(i + 1) % pts.len()]);
+        let len = distance(a,b);
+        (-(b.1 - a.1) / len * hw, (b.0 - a.0) / len * hw)
+    }).collect();
+    let mut polys = vec![];
+    for (i,n) in normals.iter().enumerate() {
+        let (a,b) = (pts[i],pts[(i + 1) % pts.len()]);
+        polys.push(oriented(vec![(a.0 + n.0, a.1 + n.1),(b.0 + n.0, b.1 + n.1),(b.0 - n.0, b.1 - n.1),(a.0 - n.0, a.1 - n.1)]));
+    }
+    let joins = if closed { 0..segments } else { 1..segments };
+    for i in joins {
+        let (n0,n1) = (normals[(i + segments - 1) % segments],normals[i]);
+        // the outer side of the turn, away from where the next segment goes
+        let turn = n0.0 * n1.1 - n0.1 * n1.0;
+        if turn == 0. {
+            continue;
+        }
+        let (n0,n1) = if turn > 0. { ((-n0.0,-n0.1),(-n1.0,-n1.1)) } else { (n0,n1) };
+        let p = pts[i];
+        let mut join = vec![p,(p.0 + n0.0, p.1 + n0.1)];
+        let mid = ((n0.0 + n1.0) / 2., (n0.1 + n1.1) / 2.);
+        let mid_sq = mid.0 * mid.0 + mid.1 * mid.1;
+        if mid_sq * MITER_LIMIT * MITER_LIMIT >= hw * hw {
+            let k = hw * hw / mid_sq;
+            join.push((p.0 + mid.0 * k, p.1 + mid.1 * k));
+        }
+        join.push((p.0 + n1.0, p.1 + n1.1));
+        polys.push(oriented(join));
+    }
+    polys
+}
+
+/// Make the polygon go clockwise on screen
+fn oriented(mut poly: Vec<Px>) -> Vec<Px> {
+    let area: f32 = (0..poly.len()).map(|i| {
+        let (a,b) = (poly[i],poly[(i + 1) % poly.len()]);
+        a.0 * b.1 - b.0 * a.1
+    }).sum();
+    if area < 0. {
+        poly.reverse();
+    }
+    poly
+}
+
+/// A non-horizontal polygon edge, `y0 < y1`
+struct Edge {
+    x0: f32,
+    y0: f32,
+    x1: f32,
+    y1: f32,
+    winding: i32,
+}
+
+/// Add a horizontal span `[a,b)` of a sample row to the coverage accumulator
+fn add_span(acc: &mut [f32], a: f32, b: f32, weight: f32) {
+    let max = acc.len() as f32;
+    let (a,b) = (a.clamp(0.,max), b.clamp(0.,max));
+    if b <= a {
+        return;
+    }
+    let (ia,ib) = (a.floor() as usize, b.floor() as usize);
+    if ia == ib {
+        acc[ia] += (b - a) * weight;
+        return;
+    }
+    acc[ia] += (ia as f32 + 1. - a) * weight;
+    for c in acc[ia + 1..ib].iter_mut() {
+        *c += weight;
+    }
+    if ib < acc.len() {
+        acc[ib] += (b - ib as f32) * weight;
+    }
+}
+
+/// Anti-aliased fill of polygons with the non-zero winding rule
+fn fill_polygons(target: &mut Primitive, polys: &[Vec<Px>], color: Color) {
+    let mut edges = vec![];
+    for poly in polys {
+        for i in 0..poly.len() {
+            let (a,b) = (poly[i],poly[(i + 1) % poly.len()]);
+            if a.1 < b.1 {
+                edges.push(Edge { x0: a.0, y0: a.1, x1: b.0, y1: b.1, winding: 1 });
+            } else if a.1 > b.1 {
+                edges.push(Edge { x0: b.0, y0: b.1, x1: a.0, y1: a.1, winding: -1 });
+            }
+        }
+    }
+    if edges.is_empty() || target.width() == 0 {
+        return;
+    }
+    let top = edges.iter().map(|e| e.y0).fold(f32::MAX,f32::min).floor().max(0.) as u32;
+    let bottom = (edges.iter().map(|e| e.y1).fold(f32::MIN,f32::max).ceil().max(0.) as u32).min(target.height());
+
+    let mut acc = vec![0f32; target.width() as usize];
+    let mut crossings: Vec<(f32,i32)> = vec![];
+    let weight = 1. / SUBSAMPLES as f32;
+    for py in top..bottom {
+        acc.iter_mut().for_each(|c| *c = 0.);
+        for s in 0..SUBSAMPLES {
+            let sy = py as f32 + (s as f32 + 0.5) * weight;
+            crossings.clear();
+            crossings.extend(edges.iter()
+                .filter(|e| sy >= e.y0 && sy < e.y1)
+                .map(|e| (e.x0 + (sy - e.y0) * (e.x1 - e.x0) / (e.y1 - e.y0), e.winding)));
+            crossings.sort_by(|a,b| a.0.total_cmp(&b.0));
+            let mut winding = 0;
+            for i in 0..crossings.len().saturating_sub(1) {
+                winding += crossings[i].1;
+                if winding != 0 {
+                    add_span(&mut acc,crossings[i].0,crossings[i + 1].0,weight);
+                }
+            }
+        }
+        for (px,coverage) in acc.iter().enumerate() {
+            if *coverage > 0. {
+                let a = (color.a as f32 * coverage.min(1.)).round() as u8;
+                target.blend(Point::absolute(px as u32,py),Color { a, ..color });
+            }
+        }
+    }
+}
+
+impl ShapePrimitive for Primitive {
+    fn fill_shape(&mut self, place: Rect<f32,f32>, shape: &Shape, color: Color) {
+        let map = Mapping::new(self,place);
+        let polys: Vec<_> = contours(shape,&map).into_iter()
+            .filter(|c| c.points.len() > 2)
+            .map(|c| c.points)
+            .collect();
+        fill_polygons(self,&polys,color);
+    }
+
+    fn stroke_shape(&mut self, place: Rect<f32,f32>, shape: &Shape, color: Color, width: f32) {
+        if width <= 0. {
+            return;
+        }
+        let map = Mapping::new(self,place);
+        let polys: Vec<_> = contours(shape,&map).iter()
+            .flat_map(|c| stroke_polygons(c,width))
+            .collect();
+        fill_polygons(self,&polys,color);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use types::render::{Point, Rect, Viewport};
+
+    use super::*;
+
+    const INK: Color = Color::rgba(0,0,255,128);
+
+    /// A path through points given in pixels of a 24x24 primitive
+    fn polyline(points: &[(f32,f32)]) -> Shape {
+        let at = |(x,y): (f32,f32)| Point::relative(x / 24.,y / 24.);
+        let mut segments = vec![PathSegment::MoveTo(at(points[0]))];
+        segments.extend(points[1..].iter().map(|p| PathSegment::LineTo(at(*p))));
+        Shape::Path(segments)
+    }
+
+    fn stroked(shape: &Shape, width: f32) -> Primitive {
+        let mut p = Primitive::filled(Viewport { width: 24, height: 24 },Color::TRANSPARENT);
+        p.stroke_shape(Rect::full_box(),shape,INK,width);
+        p
+    }
+
+    fn filled(shape: &Shape) -> Primitive {
+        let mut p = Primitive::filled(Viewport { width: 24, height: 24 },Color::TRANSPARENT);
+        p.fill_shape(Rect::full_box(),shape,INK);
+        p
+    }
+
+    fn alpha(p: &Primitive, x: u32, y: u32) -> u8 {
+        p.get(Point::absolute(x,y)).unwrap().a
+    }
+
+    #[test]
+    fn mitered_joins_and_butt_ends() {
+        let p = stroked(&polyline(&[(2.,10.),(10.,10.),(10.,18.)]),4.);
+        // the outer corner of the join is square, ends stop where the path does
+        assert_eq!(alpha(&p,11,8),128);
+        assert_eq!(alpha(&p,1,10),0);
+        assert_eq!(alpha(&p,10,18),0);
+        assert_eq!(alpha(&p,2,9),128);
+        // translucent strokes are painted once where segments and joins overlap
+        assert!(p.pixels().iter().all(|c| c.a <= 128));
+        assert_eq!(alpha(&p,9,11),128);
+    }
+
+    #[test]
+    fn long_miters_are_bevelled() {
+        let p = stroked(&polyline(&[(2.,8.),(18.,10.),(2.,12.)]),4.);
+        assert_eq!(alpha(&p,16,10),128);
+        assert_eq!(alpha(&p,20,10),0);
+        // a blunt turn keeps its miter
+        let p = stroked(&polyline(&[(4.,4.),(12.,12.),(20.,4.)]),2.);
+        assert!(alpha(&p,12,13) > 0);
+    }
+
+    #[test]
+    fn closed_contours_join_at_their_start() {
+        let square = Shape::Rect { rect: Rect::full_box(), radii: [0.; 4] };
+        let p = stroked(&square,2.);
+        assert_eq!(alpha(&p,0,0),128);
+        assert_eq!(alpha(&p,23,23),128);
+        assert_eq!(alpha(&p,1,1),0);
+        assert_eq!(alpha(&p,12,12),0);
+    }
+
+    #[test]
+    fn paths_without_move_start_at_the_origin() {
+        let corner = Shape::Path(vec![PathSegment::LineTo(Point::relative(1.,0.)),PathSegment::LineTo(Point::relative(1.,1.)),PathSegment::Close]);
+        let p = filled(&corner);
+        assert_eq!(alpha(&p,20,3),128);
+        assert_eq!(alpha(&p,3,20),0);
+        let curve = Shape::Path(vec![PathSegment::QuadTo(Point::relative(1.,0.),Point::relative(1.,1.)),PathSegment::Close]);
+        let p = filled(&curve);
+        assert_eq!(alpha(&p,20,12),128);
+        assert_eq!(alpha(&p,20,3),0);
+        assert_eq!(alpha(&p,3,20),0);
+        let cubic = Shape::Path(vec![PathSegment::CubicTo(Point::relative(0.5,0.),Point::relative(1.,0.),Point::relative(1.,1.)),PathSegment::Close]);
+        assert_eq!(alpha(&filled(&cubic),20,16),128);
+        // strokes too
+        let top = Shape::Path(vec![PathSegment::LineTo(Point::relative(0.5,0.))]);
+        let p = stroked(&top,4.);
+        assert_eq!(alpha(&p,5,1),128);
+        assert_eq!(alpha(&p,5,3),0);
+        assert_eq!(alpha(&p,13,1),0);
+    }
+}
@@ -0,0 +1,156 @@
+use std::sync::OnceLock;
+
+use fontdue::{Font, FontSettings, LineMetrics};
+use types::render::{Point, Rect, Text, TextAlign, TextPrimitive};
+
+use crate::{Color, Primitive};
+
+static REGULAR: &[u8] = include_bytes!("../assets/DejaVuSans.ttf");
+static BOLD: &[u8] = include_bytes!("../assets/DejaVuSans-Bold.ttf");
+
+/// Styles starting from this weight are drawn with the bold face
+pub const BOLD_WEIGHT: u16 = 600;
+
+/// Bundled face for a weight, fonts are parsed on first use
+fn font(weight: u16) -> &'static Font {
+    static FONTS: OnceLock<(Font,Font)> = OnceLock::new();
+    let parse = |data: &'static [u8]| Font::from_bytes(data,FontSettings::default()).expect("bundled font is valid");
+    let (regular,bold) = FONTS.get_or_init(|| (parse(REGULAR),parse(BOLD)));
+    if weight >= BOLD_WEIGHT { bold } else { regular }
+}
+
+/// Width of a line in pixels, kerning included
+fn line_width(font: &Font, line: &str, px: f32) -> f32 {
+    let mut width = 0.;
+    let mut prev = None;
+    for ch in line.chars() {
+        if let Some(prev) = prev {
+            width += font.horizontal_kern(prev,ch,px).unwrap_or(0.);
+        }
+        width += font.metrics(ch,px).advance_width;
+        prev = Some(ch);
+    }
+    width
+}
+
+impl TextPrimitive for Primitive {
+    fn draw_text(&mut self, place: Rect<f32,f32>, text: &Text, color: Color, weight: u16) {
+        let px = text.size;
+        if px <= 0. || color.a == 0 {
+            return;
+        }
+        let font = font(weight);
+        let (x0,y0,x1,y1) = self.absolute_bounds(place);
+        // glyphs are clipped by the rect and by the primitive
+        let clip = (x0.max(0.), y0.max(0.), x1.min(self.width as f32), y1.min(self.height as f32));
+
+        let metrics = font.horizontal_line_metrics(px).unwrap_or(LineMetrics {
+            ascent: px * 0.8,
+            descent: -px * 0.2,
+            line_gap: 0.,
+            new_line_size: px,
+        });
+        let lines: Vec<&str> = text.content.split('\n').collect();
+        let block_height = metrics.new_line_size * lines.len() as f32 - metrics.line_gap;
+        let mut baseline = y0 + ((y1 - y0) - block_height) / 2. + metrics.ascent;
+
+        for line in lines {
+            let width = line_width(font,line,px);
+            let mut pen = match text.align {
+                TextAlign::Left => x0,
+                TextAlign::Center => x0 + ((x1 - x0) - width) / 2.,
+                TextAlign::Right => x1 - width,
+            };
+            let mut prev = None;
+            for ch in line.chars() {
+                if let Some(prev) = prev {
+                    pen += font.horizontal_kern(prev,ch,px).unwrap_or(0.);
+                }
+                let (m,coverage) = font.rasterize(ch,px);
+                // upper left corner of the glyph bitmap
+                let gx = pen.round() + m.xmin as f32;
+                let gy = baseline.round() - m.ymin as f32 - m.height as f32;
+                for row in 0..m.height {
+                    let y = gy + row as f32;
+                    if y < clip.1 || y >= clip.3 {
+                        continue;
+                    }
+                    for col in 0..m.width {
+                        let x = gx + col as f32;
+                        let cov = coverage[row * m.width + col];
+                        if cov == 0 || x < clip.0 || x >= clip.2 {
+                            continue;
+                        }
+                        let a = (color.a as u32 * cov as u32 / 255) as u8;
+                        self.blend(Point::absolute(x as u32,y as u32),Color { a, ..color });
+                    }
+                }
+                pen += m.advance_width;
+                prev = Some(ch);
+            }
+            baseline += metrics.new_line_size;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use types::render::Viewport;
+
+    use super::*;
+
+    fn drawn(text: &Text, place: Rect<f32,f32>) -> Primitive {
+        let mut p = Primitive::filled(Viewport { width: 60, height: 20 },Color::TRANSPARENT);
+        p.draw_text(place,text,Color::BLACK,400);
+        p
+    }
+
+    fn rect(x0: f32, x1: f32) -> Rect<f32,f32> {
+        Rect::<(),()>::zero().upper_left_relative(Point::relative(x0,0.)).down_right_relative(Point::relative(x1,1.))
+    }
+
+    /// Pixels touched by the text, as (x0,y0,x1,y1)
+    fn ink(p: &Primitive) -> Option<(u32,u32,u32,u32)> {
+        let (w,h) = (p.width(),p.height());
+        let inked: Vec<_> = (0..h).flat_map(|y| (0..w).map(move |x| (x,y)))
+            .filter(|(x,y)| p.get(Point::absolute(*x,*y)).unwrap().a > 0)
+            .collect();
+        let xs = || inked.iter().map(|(x,_)| *x);
+        let ys = || inked.iter().map(|(_,y)| *y);
+        Some((xs().min()?,ys().min()?,xs().max()? + 1,ys().max()? + 1))
+    }
+
+    #[test]
+    fn lines_are_aligned_within_their_place() {
+        let label = |align| Text::new("Hello",12.).align(align);
+        let (l0,top,l1,bottom) = ink(&drawn(&label(TextAlign::Left),Rect::full_box())).unwrap();
+        let (c0,_,c1,_) = ink(&drawn(&label(TextAlign::Center),Rect::full_box())).unwrap();
+        let (r0,_,r1,_) = ink(&drawn(&label(TextAlign::Right),Rect::full_box())).unwrap();
+        // the same line, only moved
+        assert!(l1 - l0 == c1 - c0 && c1 - c0 == r1 - r0);
+        assert!(l0 <= 2);
+        assert!((58..=60).contains(&r1));
+        assert!(((c0 + c1) as i32 - 60).abs() <= 2);
+        // vertically centered
+        assert!(((top + bottom) as i32 - 20).abs() <= 3);
+    }
+
+    #[test]
+    fn glyphs_stay_in_their_place() {
+        let text = Text::new("Hello world",12.);
+        let place = rect(0.25,0.5);
+        let (x0,_,x1,_) = ink(&drawn(&text,place)).unwrap();
+        assert!(x0 >= 15 && x1 <= 30);
+    }
+
+    #[test]
+    fn nothing_to_draw() {
+        let blank = Primitive::filled(Viewport { width: 60, height: 20 },Color::TRANSPARENT);
+        assert_eq!(drawn(&Text::new("",12.),Rect::full_box()),blank);
+        assert_eq!(drawn(&Text::new("Hello",0.),Rect::full_box()),blank);
+        assert_eq!(drawn(&Text::new("Hello",12.),rect(0.5,0.5)),blank);
+        let mut p = blank.clone();
+        p.draw_text(Rect::full_box(),&Text::new("Hello",12.),Color::TRANSPARENT,400);
+        assert_eq!(p,blank);
+    }
+}
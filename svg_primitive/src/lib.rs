@@ -0,0 +1,285 @@
+//! A primitive recording what is drawn into it instead of pixels, to be exported as an SVG document.
+//! Primitives copied into each other become nested `<svg>` elements, components are additionally
+//! wrapped into `<g>` groups naming the entity they were rendered from.
+extern crate types;
+extern crate raster_primitive;
+extern crate png;
+
+mod write;
+
+use std::sync::Arc;
+
+use raster_primitive::Color;
+use types::render::{self, Compositing, Paint, Rect, Shape, ShapePrimitive, Text, TextPrimitive, Transform, Viewport};
+
+/// A drawing operation, `place` is relative to the primitive
+#[derive(Clone,Debug)]
+enum Op {
+    Shape { place: Rect<f32,f32>, shape: Shape, paint: Paint<Color> },
+    Text { place: Rect<f32,f32>, text: Text, color: Color, weight: u16 },
+    /// another primitive stretched over `place`
+    Embed { place: Rect<f32,f32>, src: Arc<Primitive>, params: Compositing },
+    /// pixels, PNG encoded
+    Raster { place: Rect<f32,f32>, png: Arc<Vec<u8>> },
+}
+
+impl Op {
+    fn place(&self) -> Rect<f32,f32> {
+        match self {
+            Op::Shape { place, .. } | Op::Text { place, .. } | Op::Embed { place, .. } | Op::Raster { place, .. } => *place,
+        }
+    }
+
+    /// Area the operation may draw to, if it's known not to spill out of its place
+    fn extent(&self) -> Option<Rect<f32,f32>> {
+        match self {
+            Op::Raster { place, .. } => Some(*place),
+            Op::Embed { place, params, .. } if params.transform.is_none_or(|t| t.is_identity()) => Some(*place),
+            _ => None,
+        }
+    }
+}
+
+/// Recorded drawing of a `size` sized area
+#[derive(Clone,Debug)]
+pub struct Primitive {
+    size: Viewport,
+    /// operations with the scissor they were recorded under
+    ops: Vec<(Option<Rect>,Op)>,
+    scissor: Option<Rect>,
+    /// entity and portal the drawing is a component of
+    component: Option<(String,usize)>,
+}
+
+impl Primitive {
+    /// Note the drawing as the view of a component, it becomes a group with the entity and portal as attributes
+    pub fn set_component<I: std::fmt::Display>(&mut self, entity: I, portal: usize) {
+        self.component = Some((entity.to_string(),portal));
+    }
+
+    /// Entity and portal of the component the drawing is a view of
+    pub fn component(&self) -> Option<(&str,usize)> {
+        self.component.as_ref().map(|(e,p)| (e.as_str(),*p))
+    }
+
+    /// Pixels as an embedded PNG image
+    pub fn from_raster(raster: &raster_primitive::Primitive) -> Self {
+        let size = raster.viewport();
+        let mut p = <Self as render::Primitive>::blank(size);
+        if size.width > 0 && size.height > 0 {
+            let mut png = vec![];
+            let mut encoder = png::Encoder::new(&mut png,size.width,size.height);
+            encoder.set_color(png::ColorType::Rgba);
+            encoder.set_depth(png::BitDepth::Eight);
+            encoder.write_header()
+                .and_then(|mut w| w.write_image_data(&raster.to_rgba8()))
+                .expect("encoding into memory doesn't fail");
+            p.push(Op::Raster { place: Rect::full_box(), png: Arc::new(png) });
+        }
+        p
+    }
+
+    /// Fill the whole area with a color
+    pub fn filled(size: Viewport, color: Color) -> Self {
+        let mut p = <Self as render::Primitive>::blank(size);
+        p.fill_shape(Rect::full_box(),&Shape::rect(),color);
+        p
+    }
+
+    /// Whether nothing was drawn
+    pub fn is_empty(&self) -> bool {
+        self.ops.is_empty()
+    }
+
+    /// Serialize into a standalone SVG document
+    pub fn to_svg(&self) -> String {
+        write::document(self)
+    }
+
+    /// Mix `src` into `place` as `params` say, sharing it rather than copying
+    pub fn embed(&mut self, place: Rect<f32,f32>, src: Arc<Primitive>, params: Compositing) {
+        if src.is_empty() && src.component.is_none() {
+            return;
+        }
+        self.push(Op::Embed { place, src, params })
+    }
+
+    fn push(&mut self, op: Op) {
+        self.ops.push((self.scissor,op));
+    }
+}
+
+impl render::Primitive for Primitive {
+    type Color = Color;
+
+    fn copy_from(&mut self, place: Rect<f32,f32>, src: &Self) {
+        self.blend_from(place,src,Compositing::default())
+    }
+
+    /// Operations which may draw into the part, clipped to it
+    fn cut(&self, part: Rect) -> Self {
+        let (ul,dr) = (part.upper_left(),part.down_right());
+        let size = Viewport { width: dr.x().saturating_sub(ul.x()), height: dr.y().saturating_sub(ul.y()) };
+        let mut p = <Self as render::Primitive>::blank(size);
+        let area = self.size.relative_box(part);
+        let touches = |r: Rect<f32,f32>| r.intersection(area).is_some();
+        let ops = self.ops.iter()
+            .filter(|(scissor,op)| scissor.is_none_or(|s| touches(self.size.relative_box(s))) && op.extent().is_none_or(touches))
+            .cloned()
+            .collect();
+        let src = Primitive { size: self.size, ops, scissor: None, component: self.component.clone() };
+        // `self` shifted so that the part is at the origin
+        let (w,h) = (size.width.max(1) as f32, size.height.max(1) as f32);
+        let place = Rect::<(),()>::zero()
+            .upper_left_relative(render::Point::relative(-(ul.x() as f32) / w,-(ul.y() as f32) / h))
+            .down_right_relative(render::Point::relative(
+                (self.size.width as f32 - ul.x() as f32) / w,
+                (self.size.height as f32 - ul.y() as f32) / h,
+            ));
+        p.set_scissor(Some(Rect::<(),()>::zero().upper_left_absolute(render::Point::absolute(0,0)).down_right_absolute(size.as_point())));
+        p.embed(place,Arc::new(src),Compositing::default());
+        p.set_scissor(None);
+        p
+    }
+
+    fn resize(&self, scale: (f32,f32)) -> Self {
+        let size = Viewport {
+            width: (self.size.width as f32 * scale.0).round() as u32,
+            height: (self.size.height as f32 * scale.1).round() as u32,
+        };
+        let mut p = <Self as render::Primitive>::blank(size);
+        p.copy_from(Rect::full_box(),self);
+        p
+    }
+
+    fn blank(size: Viewport) -> Self {
+        Self { size, ops: vec![], scissor: None, component: None }
+    }
+
+    /// Recorded operations can't be partially erased, the ones entirely inside of `part` are dropped
+    fn clear(&mut self, part: Rect) {
+        let part = self.size.relative_box(part);
+        self.ops.retain(|(_,op)| !part.contains(op.place()));
+    }
+
+    fn size(&self) -> Viewport {
+        self.size
+    }
+
+    fn set_scissor(&mut self, part: Option<Rect>) {
+        self.scissor = part;
+    }
+
+    fn copy_transformed(&mut self, place: Rect<f32,f32>, src: &Self, transform: Transform) {
+        self.blend_from(place,src,Compositing { transform: Some(transform), ..Compositing::default() })
+    }
+
+    /// A borrowed source has to be copied, `embed` takes one without
+    fn blend_from(&mut self, place: Rect<f32,f32>, src: &Self, params: Compositing) {
+        if src.is_empty() && src.component.is_none() {
+            return;
+        }
+        self.embed(place,Arc::new(src.clone()),params)
+    }
+
+    fn footprint(&self) -> usize {
+        std::mem::size_of::<Self>() + self.ops.len() * std::mem::size_of::<(Option<Rect>,Op)>()
+    }
+}
+
+impl ShapePrimitive for Primitive {
+    fn fill_shape(&mut self, place: Rect<f32,f32>, shape: &Shape, color: Color) {
+        self.push(Op::Shape { place, shape: shape.clone(), paint: Paint::fill(color) })
+    }
+
+    fn stroke_shape(&mut self, place: Rect<f32,f32>, shape: &Shape, color: Color, width: f32) {
+        self.push(Op::Shape { place, shape: shape.clone(), paint: Paint::stroke(color,width) })
+    }
+
+    /// Fill and stroke go into one element
+    fn draw_shape(&mut self, place: Rect<f32,f32>, shape: &Shape, paint: &Paint<Color>) {
+        if paint.fill.is_some() || paint.stroke.is_some() {
+            self.push(Op::Shape { place, shape: shape.clone(), paint: *paint })
+        }
+    }
+}
+
+impl TextPrimitive for Primitive {
+    fn draw_text(&mut self, place: Rect<f32,f32>, text: &Text, color: Color, weight: u16) {
+        self.push(Op::Text { place, text: text.clone(), color, weight })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use render::{Point, Primitive as _};
+
+    use super::*;
+
+    const GOLDEN: &str = concat!(env!("CARGO_MANIFEST_DIR"),"/golden/export.svg");
+
+    fn rect(x0: f32, y0: f32, x1: f32, y1: f32) -> Rect<f32,f32> {
+        Rect::<(),()>::zero().upper_left_relative(Point::relative(x0,y0)).down_right_relative(Point::relative(x1,y1))
+    }
+
+    fn pixels(x0: u32, y0: u32, x1: u32, y1: u32) -> Rect {
+        Rect::<(),()>::zero().upper_left_absolute(Point::absolute(x0,y0)).down_right_absolute(Point::absolute(x1,y1))
+    }
+
+    /// A root with a run of clipped shapes, nesting a translucent component which nests another one showing pixels
+    fn export() -> Primitive {
+        let raster = raster_primitive::Primitive::from_pixels(Viewport { width: 2, height: 1 },vec![Color::rgb(255,0,0),Color::rgba(0,0,255,128)]).unwrap();
+        let mut grand = Primitive::from_raster(&raster);
+        grand.set_component(2,0);
+        let mut child = Primitive::filled(Viewport { width: 20, height: 10 },Color::rgb(0,128,0));
+        child.draw_text(rect(0.,0.,1.,0.5),&Text::new("a < b",4.),Color::WHITE,700);
+        child.embed(rect(0.5,0.5,1.,1.),Arc::new(grand),Compositing::default());
+        child.set_component(1,0);
+        let mut root = Primitive::filled(Viewport { width: 40, height: 20 },Color::WHITE);
+        root.set_scissor(Some(pixels(0,0,20,20)));
+        root.fill_shape(rect(0.,0.,1.,1.),&Shape::ellipse(),Color::rgba(255,0,0,128));
+        root.stroke_shape(rect(0.,0.,0.5,0.5),&Shape::rounded_rect(2.),Color::BLACK,1.5);
+        root.set_scissor(Some(pixels(20,0,40,20)));
+        root.blend_from(rect(0.5,0.,1.,1.),&child,Compositing { opacity: 0.5, ..Compositing::default() });
+        root.set_scissor(None);
+        root
+    }
+
+    #[test]
+    fn golden_export() {
+        let svg = export().to_svg();
+        if std::env::var_os("UPDATE_GOLDEN").is_some() {
+            std::fs::create_dir_all(std::path::Path::new(GOLDEN).parent().unwrap()).unwrap();
+            std::fs::write(GOLDEN,&svg).unwrap();
+        }
+        assert_eq!(svg,std::fs::read_to_string(GOLDEN).unwrap());
+    }
+
+    #[test]
+    fn embedded_sources_are_shared() {
+        let src = Arc::new(Primitive::filled(Viewport { width: 2, height: 2 },Color::BLACK));
+        let mut p = Primitive::blank(Viewport { width: 4, height: 4 });
+        p.embed(Rect::full_box(),src.clone(),Compositing::default());
+        let Op::Embed { src: embedded, .. } = &p.ops[0].1 else { panic!("{:?}",p.ops) };
+        assert!(Arc::ptr_eq(embedded,&src));
+    }
+
+    #[test]
+    fn cut_keeps_what_touches_the_part() {
+        let mut p = Primitive::blank(Viewport { width: 40, height: 20 });
+        p.copy_from(rect(0.,0.,0.25,1.),&Primitive::from_raster(&raster_primitive::Primitive::filled(Viewport { width: 1, height: 1 },Color::BLACK)));
+        p.set_scissor(Some(pixels(0,0,10,20)));
+        p.fill_shape(rect(0.,0.,1.,1.),&Shape::ellipse(),Color::BLACK);
+        p.set_scissor(None);
+        p.fill_shape(rect(0.5,0.,0.75,1.),&Shape::ellipse(),Color::WHITE);
+        let cut = p.cut(pixels(20,5,40,15));
+        assert_eq!(cut.size(),Viewport { width: 20, height: 10 });
+        // the cut is clipped to its size, only the white ellipse may show in it
+        assert_eq!(cut.ops.len(),1);
+        let (scissor,Op::Embed { src, place, .. }) = &cut.ops[0] else { panic!("{:?}",cut.ops) };
+        assert_eq!(*scissor,Some(pixels(0,0,20,10)));
+        assert_eq!(*place,rect(-1.,-0.5,1.,1.5));
+        assert_eq!(src.ops.len(),1);
+        assert!(matches!(src.ops[0].1,Op::Shape { paint, .. } if paint.fill == Some(Color::WHITE)));
+    }
+}
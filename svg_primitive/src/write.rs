@@ -0,0 +1,329 @@
+//! Serialization of recorded primitives.
+//! Elements are indented by nesting and numbers are rounded to two decimals, so that documents diff well.
+use std::fmt::Write;
+
+use raster_primitive::Color;
+use types::render::{BlendMode, Compositing, Paint, PathSegment, Point, Rect, Shape, Text, TextAlign, Viewport};
+
+use crate::{Op, Primitive};
+
+/// Font size to line height, and to ascent, of the generic font text is set in
+const LINE_HEIGHT: f32 = 1.2;
+const ASCENT: f32 = 0.8;
+
+struct Writer {
+    out: String,
+    depth: usize,
+    /// clip paths written so far, their ids have to be unique in the document
+    clips: usize,
+}
+
+impl Writer {
+    fn line(&mut self, s: &str) {
+        for _ in 0..self.depth {
+            self.out.push_str("  ");
+        }
+        self.out.push_str(s);
+        self.out.push('\n');
+    }
+
+    fn open(&mut self, s: &str) {
+        self.line(s);
+        self.depth += 1;
+    }
+
+    fn close(&mut self, s: &str) {
+        self.depth -= 1;
+        self.line(s);
+    }
+}
+
+pub(crate) fn document(p: &Primitive) -> String {
+    let mut w = Writer { out: String::new(), depth: 0, clips: 0 };
+    let (width,height) = (p.size.width,p.size.height);
+    w.open(&format!(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink" width="{width}" height="{height}" viewBox="0 0 {width} {height}">"#
+    ));
+    body(&mut w,p);
+    w.close("</svg>");
+    w.out
+}
+
+/// A number with at most two decimals
+fn num(v: f32) -> String {
+    let s = format!("{:.2}",v);
+    let s = s.trim_end_matches('0').trim_end_matches('.');
+    if s == "-0" { "0".to_string() } else { s.to_string() }
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&',"&amp;").replace('<',"&lt;").replace('>',"&gt;").replace('"',"&quot;")
+}
+
+fn rgb(c: Color) -> String {
+    format!("rgb({},{},{})",c.r,c.g,c.b)
+}
+
+/// Pixel corners of a place: (x0,y0,x1,y1)
+fn pixels(place: Rect<f32,f32>, vp: Viewport) -> (f32,f32,f32,f32) {
+    let (ul,dr) = (place.upper_left(),place.down_right());
+    let (w,h) = (vp.width as f32, vp.height as f32);
+    (ul.x() * w,ul.y() * h,dr.x() * w,dr.y() * h)
+}
+
+fn body(w: &mut Writer, p: &Primitive) {
+    if let Some((entity,portal)) = &p.component {
+        w.open(&format!(r#"<g data-entity="{}" data-portal="{}">"#,escape(entity),portal));
+    }
+    let mut i = 0;
+    while i < p.ops.len() {
+        // consecutive operations under the same scissor share a clip path
+        let scissor = p.ops[i].0;
+        let run = p.ops[i..].iter().take_while(|(s,_)| *s == scissor).count();
+        if let Some(rect) = scissor {
+            let (ul,dr) = (rect.upper_left(),rect.down_right());
+            let id = w.clips;
+            w.clips += 1;
+            w.line(&format!(
+                r#"<clipPath id="clip{}"><rect x="{}" y="{}" width="{}" height="{}"/></clipPath>"#,
+                id,ul.x(),ul.y(),dr.x().saturating_sub(ul.x()),dr.y().saturating_sub(ul.y())
+            ));
+            w.open(&format!(r#"<g clip-path="url(#clip{})">"#,id));
+        }
+        for (_,op) in &p.ops[i..i + run] {
+            self::op(w,op,p.size);
+        }
+        if scissor.is_some() {
+            w.close("</g>");
+        }
+        i += run;
+    }
+    if p.component.is_some() {
+        w.close("</g>");
+    }
+}
+
+fn op(w: &mut Writer, op: &Op, vp: Viewport) {
+    match op {
+        Op::Shape { place, shape, paint } => self::shape(w,*place,shape,paint,vp),
+        Op::Text { place, text, color, weight } => self::text(w,*place,text,*color,*weight,vp),
+        Op::Embed { place, src, params } => {
+            let (x0,y0,x1,y1) = pixels(*place,vp);
+            let attrs = compositing(params,(x0 + x1) / 2.,(y0 + y1) / 2.);
+            if !attrs.is_empty() {
+                w.open(&format!("<g{}>",attrs));
+            }
+            w.open(&format!(
+                r#"<svg x="{}" y="{}" width="{}" height="{}" viewBox="0 0 {} {}" preserveAspectRatio="none" overflow="hidden">"#,
+                num(x0),num(y0),num(x1 - x0),num(y1 - y0),src.size.width,src.size.height
+            ));
+            body(w,src);
+            w.close("</svg>");
+            if !attrs.is_empty() {
+                w.close("</g>");
+            }
+        }
+        Op::Raster { place, png } => {
+            let (x0,y0,x1,y1) = pixels(*place,vp);
+            w.line(&format!(
+                r#"<image x="{}" y="{}" width="{}" height="{}" preserveAspectRatio="none" xlink:href="data:image/png;base64,{}"/>"#,
+                num(x0),num(y0),num(x1 - x0),num(y1 - y0),base64(png)
+            ));
+        }
+    }
+}
+
+/// Attributes of a group mixing its content in as `params` say; transforms go around (cx,cy)
+fn compositing(params: &Compositing, cx: f32, cy: f32) -> String {
+    let mut attrs = String::new();
+    if let Some(t) = params.transform.filter(|t| !t.is_identity()) {
+        write!(
+            attrs,r#" transform="translate({} {}) matrix({} {} {} {} {} {}) translate({} {})""#,
+            num(cx),num(cy),t.a,t.b,t.c,t.d,num(t.e),num(t.f),num(-cx),num(-cy)
+        ).unwrap();
+    }
+    if params.opacity < 1. {
+        write!(attrs,r#" opacity="{}""#,num(params.opacity.max(0.))).unwrap();
+    }
+    let blend = match params.blend {
+        BlendMode::Normal => None,
+        BlendMode::Multiply => Some("multiply"),
+        BlendMode::Screen => Some("screen"),
+        BlendMode::Additive => Some("plus-lighter"),
+    };
+    if let Some(blend) = blend {
+        write!(attrs,r#" style="mix-blend-mode:{}""#,blend).unwrap();
+    }
+    attrs
+}
+
+fn paint(paint: &Paint<Color>) -> String {
+    let mut attrs = String::new();
+    match paint.fill {
+        Some(c) => {
+            write!(attrs,r#" fill="{}""#,rgb(c)).unwrap();
+            if c.a < 255 {
+                write!(attrs,r#" fill-opacity="{}""#,num(c.a as f32 / 255.)).unwrap();
+            }
+        }
+        None => attrs.push_str(r#" fill="none""#),
+    }
+    if let Some((c,width)) = paint.stroke {
+        write!(attrs,r#" stroke="{}" stroke-width="{}""#,rgb(c),num(width)).unwrap();
+        if c.a < 255 {
+            write!(attrs,r#" stroke-opacity="{}""#,num(c.a as f32 / 255.)).unwrap();
+        }
+    }
+    attrs
+}
+
+fn shape(w: &mut Writer, place: Rect<f32,f32>, shape: &Shape, p: &Paint<Color>, vp: Viewport) {
+    let (x0,y0,x1,y1) = pixels(place,vp);
+    // a point relative to the place, in pixels
+    let at = |p: Point<f32>| (x0 + p.x() * (x1 - x0),y0 + p.y() * (y1 - y0));
+    let paint = paint(p);
+    match shape {
+        Shape::Rect { rect, radii } => {
+            let ((ax,ay),(bx,by)) = (at(rect.upper_left()),at(rect.down_right()));
+            let (ax,bx,ay,by) = (ax.min(bx),ax.max(bx),ay.min(by),ay.max(by));
+            let max = ((bx - ax).min(by - ay) / 2.).max(0.);
+            let r = radii.map(|r| r.clamp(0.,max));
+            if r.iter().all(|v| *v == r[0]) {
+                let rounded = if r[0] > 0. { format!(r#" rx="{}""#,num(r[0])) } else { String::new() };
+                w.line(&format!(
+                    r#"<rect x="{}" y="{}" width="{}" height="{}"{}{}/>"#,
+                    num(ax),num(ay),num(bx - ax),num(by - ay),rounded,paint
+                ));
+            } else {
+                // clockwise from the upper left corner
+                let arc = |r: f32, x: f32, y: f32| format!("A{} {} 0 0 1 {} {}",num(r),num(r),num(x),num(y));
+                let d = [
+                    format!("M{} {}",num(ax + r[0]),num(ay)),
+                    format!("H{}",num(bx - r[1])),
+                    arc(r[1],bx,ay + r[1]),
+                    format!("V{}",num(by - r[2])),
+                    arc(r[2],bx - r[2],by),
+                    format!("H{}",num(ax + r[3])),
+                    arc(r[3],ax,by - r[3]),
+                    format!("V{}",num(ay + r[0])),
+                    arc(r[0],ax + r[0],ay),
+                    "Z".to_string(),
+                ].join(" ");
+                w.line(&format!(r#"<path d="{}"{}/>"#,d,paint));
+            }
+        }
+        Shape::Line { from, to } => {
+            let ((ax,ay),(bx,by)) = (at(*from),at(*to));
+            w.line(&format!(r#"<line x1="{}" y1="{}" x2="{}" y2="{}"{}/>"#,num(ax),num(ay),num(bx),num(by),paint));
+        }
+        Shape::Ellipse { bounds } => {
+            let ((ax,ay),(bx,by)) = (at(bounds.upper_left()),at(bounds.down_right()));
+            w.line(&format!(
+                r#"<ellipse cx="{}" cy="{}" rx="{}" ry="{}"{}/>"#,
+                num((ax + bx) / 2.),num((ay + by) / 2.),num((bx - ax).abs() / 2.),num((by - ay).abs() / 2.),paint
+            ));
+        }
+        Shape::Path(segments) => {
+            let pt = |p: Point<f32>| { let (x,y) = at(p); format!("{} {}",num(x),num(y)) };
+            let mut d: Vec<String> = segments.iter().map(|s| match s {
+                PathSegment::MoveTo(p) => format!("M{}",pt(*p)),
+                PathSegment::LineTo(p) => format!("L{}",pt(*p)),
+                PathSegment::QuadTo(c,p) => format!("Q{} {}",pt(*c),pt(*p)),
+                PathSegment::CubicTo(c1,c2,p) => format!("C{} {} {}",pt(*c1),pt(*c2),pt(*p)),
+                PathSegment::Close => "Z".to_string(),
+            }).collect();
+            // SVG paths have to start with a moveto, ours start at the origin of their place
+            if !matches!(segments.first(),None | Some(PathSegment::MoveTo(_))) {
+                d.insert(0,format!("M{}",pt(Point::relative(0.,0.))));
+            }
+            w.line(&format!(r#"<path d="{}" fill-rule="nonzero"{}/>"#,d.join(" "),paint));
+        }
+    }
+}
+
+fn text(w: &mut Writer, place: Rect<f32,f32>, text: &Text, color: Color, weight: u16, vp: Viewport) {
+    if text.size <= 0. || color.a == 0 {
+        return;
+    }
+    let (x0,y0,x1,y1) = pixels(place,vp);
+    let (x,anchor) = match text.align {
+        TextAlign::Left => (x0,"start"),
+        TextAlign::Center => ((x0 + x1) / 2.,"middle"),
+        TextAlign::Right => (x1,"end"),
+    };
+    let lines: Vec<&str> = text.content.split('\n').collect();
+    // the block of lines is centered vertically, as raster text is
+    let line = text.size * LINE_HEIGHT;
+    let block = line * (lines.len() - 1) as f32 + text.size;
+    let baseline = y0 + ((y1 - y0) - block) / 2. + text.size * ASCENT;
+    let mut attrs = format!(
+        r#"x="{}" y="{}" font-family="sans-serif" font-size="{}" font-weight="{}" text-anchor="{}" fill="{}""#,
+        num(x),num(baseline),num(text.size),weight,anchor,rgb(color)
+    );
+    if color.a < 255 {
+        write!(attrs,r#" fill-opacity="{}""#,num(color.a as f32 / 255.)).unwrap();
+    }
+    if lines.len() == 1 {
+        w.line(&format!("<text {}>{}</text>",attrs,escape(lines[0])));
+    } else {
+        w.open(&format!("<text {}>",attrs));
+        for (i,l) in lines.iter().enumerate() {
+            let dy = if i == 0 { 0. } else { line };
+            w.line(&format!(r#"<tspan x="{}" dy="{}">{}</tspan>"#,num(x),num(dy),escape(l)));
+        }
+        w.close("</text>");
+    }
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let b = [chunk[0],*chunk.get(1).unwrap_or(&0),*chunk.get(2).unwrap_or(&0)];
+        let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn base64_padding() {
+        // test vectors of RFC 4648
+        for (bytes,encoded) in [("",""),("f","Zg=="),("fo","Zm8="),("foo","Zm9v"),("foob","Zm9vYg=="),("fooba","Zm9vYmE="),("foobar","Zm9vYmFy")] {
+            assert_eq!(base64(bytes.as_bytes()),encoded);
+        }
+        assert_eq!(base64(&[0xfb,0xff,0xbf]),"+/+/");
+    }
+
+    #[test]
+    fn paths_start_with_a_move() {
+        let mut w = Writer { out: String::new(), depth: 0, clips: 0 };
+        let place = Rect::full_box();
+        let vp = Viewport { width: 10, height: 20 };
+        let path = Shape::Path(vec![PathSegment::LineTo(Point::relative(1.,1.)),PathSegment::Close]);
+        shape(&mut w,place,&path,&Paint::fill(Color::BLACK),vp);
+        let moved = Shape::Path(vec![PathSegment::MoveTo(Point::relative(0.5,0.)),PathSegment::LineTo(Point::relative(1.,1.))]);
+        shape(&mut w,place,&moved,&Paint::fill(Color::BLACK),vp);
+        assert_eq!(w.out,concat!(
+            r##"<path d="M0 0 L10 20 Z" fill-rule="nonzero" fill="rgb(0,0,0)"/>"##,"\n",
+            r##"<path d="M5 0 L10 20" fill-rule="nonzero" fill="rgb(0,0,0)"/>"##,"\n",
+        ));
+    }
+
+    #[test]
+    fn numbers() {
+        for (v,s) in [(1.,"1"),(0.5,"0.5"),(1.005,"1"),(-0.001,"0"),(12.3456,"12.35")] {
+            assert_eq!(num(v),s);
+        }
+    }
+}
@@ -0,0 +1,142 @@
+use std::time::Duration;
+
+use crossterm::event::{self as term, KeyCode, KeyModifiers, MouseEventKind};
+use types::app::{Event, EventSource};
+use winit::dpi::{PhysicalPosition, PhysicalSize};
+use winit::event::{
+    DeviceId, ElementState, KeyboardInput, ModifiersState, MouseButton, MouseScrollDelta, TouchPhase, VirtualKeyCode,
+    WindowEvent,
+};
+
+fn modifiers(m: KeyModifiers) -> ModifiersState {
+    let mut state = ModifiersState::empty();
+    state.set(ModifiersState::SHIFT,m.contains(KeyModifiers::SHIFT));
+    state.set(ModifiersState::CTRL,m.contains(KeyModifiers::CONTROL));
+    state.set(ModifiersState::ALT,m.contains(KeyModifiers::ALT));
+    state
+}
+
+fn key_code(code: KeyCode) -> Option<VirtualKeyCode> {
+    use VirtualKeyCode as V;
+    const LETTERS: [V; 26] = [
+        V::A, V::B, V::C, V::D, V::E, V::F, V::G, V::H, V::I, V::J, V::K, V::L, V::M,
+        V::N, V::O, V::P, V::Q, V::R, V::S, V::T, V::U, V::V, V::W, V::X, V::Y, V::Z,
+    ];
+    const DIGITS: [V; 10] = [V::Key0, V::Key1, V::Key2, V::Key3, V::Key4, V::Key5, V::Key6, V::Key7, V::Key8, V::Key9];
+    const FUNCTIONS: [V; 12] = [V::F1, V::F2, V::F3, V::F4, V::F5, V::F6, V::F7, V::F8, V::F9, V::F10, V::F11, V::F12];
+    Some(match code {
+        KeyCode::Char(c) if c.is_ascii_alphabetic() => LETTERS[(c.to_ascii_lowercase() as u8 - b'a') as usize],
+        KeyCode::Char(c) if c.is_ascii_digit() => DIGITS[(c as u8 - b'0') as usize],
+        KeyCode::Char(' ') => V::Space,
+        KeyCode::Char(_) => return None,
+        KeyCode::F(n @ 1..=12) => FUNCTIONS[n as usize - 1],
+        KeyCode::Enter => V::Return,
+        KeyCode::Esc => V::Escape,
+        KeyCode::Backspace => V::Back,
+        KeyCode::Tab | KeyCode::BackTab => V::Tab,
+        KeyCode::Left => V::Left,
+        KeyCode::Right => V::Right,
+        KeyCode::Up => V::Up,
+        KeyCode::Down => V::Down,
+        KeyCode::Home => V::Home,
+        KeyCode::End => V::End,
+        KeyCode::PageUp => V::PageUp,
+        KeyCode::PageDown => V::PageDown,
+        KeyCode::Delete => V::Delete,
+        KeyCode::Insert => V::Insert,
+        _ => return None,
+    })
+}
+
+fn mouse_button(b: term::MouseButton) -> MouseButton {
+    match b {
+        term::MouseButton::Left => MouseButton::Left,
+        term::MouseButton::Right => MouseButton::Right,
+        term::MouseButton::Middle => MouseButton::Middle,
+    }
+}
+
+/// Translate a terminal event into events of the window showing root portal `window`.
+/// Terminals don't report key releases, so every key press is followed by a release.
+/// Positions and sizes are in pixels of `Primitive`, two per cell vertically
+#[allow(deprecated)]
+pub fn translate(event: term::Event, window: usize) -> Vec<Event> {
+    // terminal input doesn't come from any device winit knows of
+    let device_id = unsafe { DeviceId::dummy() };
+    let events = match event {
+        term::Event::Key(key) => {
+            let mods = modifiers(key.modifiers);
+            let input = |state| WindowEvent::KeyboardInput {
+                device_id,
+                input: KeyboardInput { scancode: 0, state, virtual_keycode: key_code(key.code), modifiers: mods },
+                is_synthetic: false,
+            };
+            let mut events = vec![WindowEvent::ModifiersChanged(mods),input(ElementState::Pressed)];
+            match key.code {
+                KeyCode::Char(c) => events.push(WindowEvent::ReceivedCharacter(c)),
+                KeyCode::Enter => events.push(WindowEvent::ReceivedCharacter('\r')),
+                KeyCode::Tab => events.push(WindowEvent::ReceivedCharacter('\t')),
+                _ => {}
+            }
+            events.push(input(ElementState::Released));
+            events
+        }
+        term::Event::Mouse(mouse) => {
+            let modifiers = modifiers(mouse.modifiers);
+            let position = PhysicalPosition::new(mouse.column as f64 + 0.5,mouse.row as f64 * 2. + 1.);
+            let moved = WindowEvent::CursorMoved { device_id, position, modifiers };
+            let wheel = |lines| WindowEvent::MouseWheel {
+                device_id,
+                delta: MouseScrollDelta::LineDelta(0.,lines),
+                phase: TouchPhase::Moved,
+                modifiers,
+            };
+            match mouse.kind {
+                MouseEventKind::Down(b) => vec![moved,WindowEvent::MouseInput { device_id, state: ElementState::Pressed, button: mouse_button(b), modifiers }],
+                MouseEventKind::Up(b) => vec![moved,WindowEvent::MouseInput { device_id, state: ElementState::Released, button: mouse_button(b), modifiers }],
+                MouseEventKind::Drag(_) | MouseEventKind::Moved => vec![moved],
+                MouseEventKind::ScrollUp => vec![moved,wheel(1.)],
+                MouseEventKind::ScrollDown => vec![moved,wheel(-1.)],
+            }
+        }
+        term::Event::Resize(cols,rows) => {
+            let vp = crate::Primitive::cells(cols,rows);
+            vec![WindowEvent::Resized(PhysicalSize::new(vp.width,vp.height))]
+        }
+    };
+    events.into_iter().map(|event| Event { window, event }).collect()
+}
+
+/// Events of the terminal, as a source for `Application::run_headless_with`.
+/// Each batch holds whatever arrived within a frame; Ctrl+C ends the run.
+/// The terminal is expected to be in raw mode, with mouse capture enabled if mouse events are wanted
+pub struct TerminalEvents {
+    window: usize,
+    frame: Duration,
+}
+
+impl TerminalEvents {
+    /// Events for root portal `window`, batched every `frame`
+    pub fn new(window: usize, frame: Duration) -> Self {
+        Self { window, frame }
+    }
+}
+
+impl EventSource<Event> for TerminalEvents {
+    fn next_batch(&mut self) -> Option<Vec<Event>> {
+        let mut batch = vec![];
+        let mut timeout = self.frame;
+        while term::poll(timeout).ok()? {
+            let event = term::read().ok()?;
+            if let term::Event::Key(key) = event {
+                if key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL) {
+                    return None;
+                }
+            }
+            batch.extend(translate(event,self.window));
+            // drain what's already there, without waiting any longer
+            timeout = Duration::ZERO;
+        }
+        Some(batch)
+    }
+}
@@ -0,0 +1,204 @@
+//! A primitive for terminals: every cell shows two pixels stacked on top of each other,
+//! drawn as an upper half block with the upper pixel as the foreground color and the lower one as the background.
+//! Drawing itself is done by `raster_primitive`, frames are written out as 24-bit ANSI escape sequences.
+extern crate types;
+extern crate raster_primitive;
+extern crate crossterm;
+extern crate winit;
+
+mod input;
+
+pub use input::{translate, TerminalEvents};
+
+use std::io::{self, Write};
+
+use raster_primitive::Color;
+use types::render::{self, Compositing, Fit, Paint, Rect, Shape, ShapePrimitive, Text, TextPrimitive, Transform, Viewport};
+
+/// The upper half block, its foreground is the upper pixel of a cell
+const UPPER_HALF: char = '\u{2580}';
+
+/// Pixels of terminal cells, two per cell vertically
+#[derive(Clone,Debug,PartialEq)]
+pub struct Primitive(raster_primitive::Primitive);
+
+impl Primitive {
+    /// Viewport covering `cols` by `rows` cells
+    pub fn cells(cols: u16, rows: u16) -> Viewport {
+        Viewport { width: cols as u32, height: rows as u32 * 2 }
+    }
+
+    /// Number of (columns,rows) of cells the primitive takes
+    pub fn cell_size(&self) -> (u16,u16) {
+        let vp = self.0.viewport();
+        (vp.width as u16, vp.height.div_ceil(2) as u16)
+    }
+
+    pub fn raster(&self) -> &raster_primitive::Primitive {
+        &self.0
+    }
+
+    pub fn into_raster(self) -> raster_primitive::Primitive {
+        self.0
+    }
+
+    /// Write the cells as lines of half blocks, every pixel is put over `background`.
+    /// Colors are only emitted when they change, every line ends with a reset
+    pub fn write_ansi<W: Write>(&self, w: &mut W, background: Color) -> io::Result<()> {
+        let (cols,rows) = self.cell_size();
+        let pixel = |x: u32, y: u32| {
+            self.0.get(render::Point::absolute(x,y)).map_or(background,|c| c.over(background))
+        };
+        for row in 0..rows as u32 {
+            let mut last = None;
+            for x in 0..cols as u32 {
+                let (fg,bg) = (pixel(x,row * 2),pixel(x,row * 2 + 1));
+                if last != Some((fg,bg)) {
+                    write!(w,"\x1b[38;2;{};{};{};48;2;{};{};{}m",fg.r,fg.g,fg.b,bg.r,bg.g,bg.b)?;
+                    last = Some((fg,bg));
+                }
+                write!(w,"{}",UPPER_HALF)?;
+            }
+            write!(w,"\x1b[0m")?;
+            if row + 1 < rows as u32 {
+                write!(w,"\r\n")?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Same as `write_ansi` over black, collected into a string
+    pub fn to_ansi(&self) -> String {
+        let mut out = vec![];
+        self.write_ansi(&mut out,Color::BLACK).expect("writing into memory doesn't fail");
+        String::from_utf8(out).expect("escape sequences and blocks are utf-8")
+    }
+
+    /// Redraw the whole terminal screen with the frame, starting at its upper left corner
+    pub fn present<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        write!(w,"\x1b[H")?;
+        self.write_ansi(w,Color::BLACK)?;
+        w.flush()
+    }
+}
+
+impl From<raster_primitive::Primitive> for Primitive {
+    fn from(p: raster_primitive::Primitive) -> Self {
+        Self(p)
+    }
+}
+
+impl render::Primitive for Primitive {
+    type Color = Color;
+
+    fn copy_from(&mut self, place: Rect<f32,f32>, src: &Self) {
+        self.0.copy_from(place,&src.0)
+    }
+
+    fn cut(&self, part: Rect) -> Self {
+        Self(self.0.cut(part))
+    }
+
+    fn resize(&self, scale: (f32,f32)) -> Self {
+        Self(self.0.resize(scale))
+    }
+
+    fn blank(size: Viewport) -> Self {
+        Self(raster_primitive::Primitive::blank(size))
+    }
+
+    fn clear(&mut self, part: Rect) {
+        self.0.clear(part)
+    }
+
+    fn size(&self) -> Viewport {
+        self.0.size()
+    }
+
+    fn set_scissor(&mut self, part: Option<Rect>) {
+        self.0.set_scissor(part)
+    }
+
+    fn copy_transformed(&mut self, place: Rect<f32,f32>, src: &Self, transform: Transform) {
+        self.0.copy_transformed(place,&src.0,transform)
+    }
+
+    fn blend_from(&mut self, place: Rect<f32,f32>, src: &Self, params: Compositing) {
+        self.0.blend_from(place,&src.0,params)
+    }
+
+    fn footprint(&self) -> usize {
+        self.0.footprint()
+    }
+
+    fn draw_image(&mut self, place: Rect<f32,f32>, image: &Self, fit: Fit) {
+        self.0.draw_image(place,&image.0,fit)
+    }
+}
+
+impl ShapePrimitive for Primitive {
+    fn fill_shape(&mut self, place: Rect<f32,f32>, shape: &Shape, color: Color) {
+        self.0.fill_shape(place,shape,color)
+    }
+
+    fn stroke_shape(&mut self, place: Rect<f32,f32>, shape: &Shape, color: Color, width: f32) {
+        self.0.stroke_shape(place,shape,color,width)
+    }
+
+    fn draw_shape(&mut self, place: Rect<f32,f32>, shape: &Shape, paint: &Paint<Color>) {
+        self.0.draw_shape(place,shape,paint)
+    }
+}
+
+impl TextPrimitive for Primitive {
+    fn draw_text(&mut self, place: Rect<f32,f32>, text: &Text, color: Color, weight: u16) {
+        self.0.draw_text(place,text,color,weight)
+    }
+}
+
+impl types::app::Present for Primitive {
+    fn rgba8(&self) -> Vec<u8> {
+        self.0.to_rgba8()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use types::render::Point;
+    use winit::dpi::PhysicalSize;
+    use winit::event::WindowEvent;
+
+    use super::*;
+
+    fn image(width: u32, height: u32, pixels: &[Color]) -> Primitive {
+        Primitive(raster_primitive::Primitive::from_pixels(Viewport { width, height },pixels.to_vec()).unwrap())
+    }
+
+    #[test]
+    fn half_blocks() {
+        let p = image(2,2,&[Color::rgb(255,0,0),Color::rgb(0,255,0),Color::rgb(0,0,255),Color::rgba(255,255,255,128)]);
+        assert_eq!(p.cell_size(),(2,1));
+        // upper pixels are foregrounds, lower ones backgrounds, translucent ones are put over black
+        assert_eq!(p.to_ansi(),"\x1b[38;2;255;0;0;48;2;0;0;255m\u{2580}\x1b[38;2;0;255;0;48;2;128;128;128m\u{2580}\x1b[0m");
+    }
+
+    #[test]
+    fn repeated_cells_and_odd_heights() {
+        let mut p = image(2,3,&[Color::WHITE; 6]);
+        p.0.set(Point::absolute(1,2),Color::rgb(1,2,3));
+        let mut out = vec![];
+        p.write_ansi(&mut out,Color::rgb(9,9,9)).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(),concat!(
+            "\x1b[38;2;255;255;255;48;2;255;255;255m\u{2580}\u{2580}\x1b[0m\r\n",
+            "\x1b[38;2;255;255;255;48;2;9;9;9m\u{2580}\x1b[38;2;1;2;3;48;2;9;9;9m\u{2580}\x1b[0m",
+        ));
+    }
+
+    #[test]
+    fn resizes_count_two_pixels_per_row() {
+        let events = translate(crossterm::event::Event::Resize(80,24),1);
+        assert_eq!(events.len(),1);
+        assert_eq!(events[0].window,1);
+        assert_eq!(events[0].event,WindowEvent::Resized(PhysicalSize::new(80,48)));
+    }
+}
@@ -0,0 +1,183 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use types::anim::{Animation, AnimationId, Lerp, Target, Tween};
+use types::render::{Rect, StyleChange};
+
+use crate::{layout_area, EntityViews, Host, ViewData};
+
+/// A value of a target
+#[derive(Clone,Copy,PartialEq)]
+enum Value {
+    Color(raster_primitive::Color),
+    Weight(u16),
+    Rect(Rect<f32,f32>),
+}
+
+impl Value {
+    /// The value `target` is animated to
+    fn of(target: &Target<Host>) -> Self {
+        match target {
+            Target::StyleColor(_,color) => Value::Color(*color),
+            Target::StyleWeight(_,weight) => Value::Weight(*weight),
+            Target::Dims(_,rect) | Target::PartRect(_,_,rect) => Value::Rect(*rect),
+        }
+    }
+
+    fn lerp(&self, to: &Self, t: f32) -> Self {
+        match (self,to) {
+            (Value::Color(a),Value::Color(b)) => Value::Color(a.lerp(b,t)),
+            (Value::Weight(a),Value::Weight(b)) => Value::Weight(a.lerp(b,t)),
+            (Value::Rect(a),Value::Rect(b)) => Value::Rect(a.lerp(b,t)),
+            _ => *to,
+        }
+    }
+}
+
+/// Sends the completion message of an animation
+pub(crate) type Done = Box<dyn FnOnce(&mut Host)>;
+
+struct Running {
+    id: AnimationId,
+    entity: usize,
+    steps: VecDeque<Tween<Host>>,
+    /// when the current step started
+    started: Duration,
+    /// value the current step started from, read on its first frame; `None` if the target wasn't found
+    from: Option<Value>,
+    /// sends the completion message
+    done: Option<Done>,
+}
+
+/// Animations being run, in the order they were started: of two animating the same value, the latter wins
+#[derive(Default)]
+pub(crate) struct Animations {
+    next_id: u64,
+    running: Vec<Running>,
+}
+
+impl Animations {
+    pub(crate) fn start(&mut self, entity: usize, animation: Animation<Host>, now: Duration, done: Option<Done>) -> AnimationId {
+        let id = AnimationId(self.next_id);
+        self.next_id += 1;
+        self.running.push(Running { id, entity, steps: animation.steps.into(), started: now, from: None, done });
+        id
+    }
+
+    pub(crate) fn cancel(&mut self, id: AnimationId) -> bool {
+        let before = self.running.len();
+        self.running.retain(|a| a.id != id);
+        self.running.len() != before
+    }
+
+    /// Forget animations of a dropped entity
+    pub(crate) fn drop_entity(&mut self, entity: usize) {
+        self.running.retain(|a| a.entity != entity);
+    }
+}
+
+impl ViewData<Host> {
+    fn read(&self, target: &Target<Host>) -> Option<Value> {
+        match target {
+            Target::StyleColor(path,_) => self.styles.get(path).map(|s| Value::Color(s.color)),
+            Target::StyleWeight(path,_) => self.styles.get(path).map(|s| Value::Weight(s.weight)),
+            Target::Dims(anchor,_) => {
+                self.layouts.iter().find(|(a,_)| a.0 == *anchor).map(|(_,(l,_))| Value::Rect(l.dims))
+            }
+            Target::PartRect(anchor,part,_) => {
+                let (_,(layout,_)) = self.layouts.iter().find(|(a,_)| a.0 == *anchor)?;
+                layout.parts.get(*part).map(|p| Value::Rect(p.rect))
+            }
+        }
+    }
+
+    /// Set a value, damaging what it changes
+    fn write(&mut self, target: &Target<Host>, value: Value) {
+        if self.read(target).is_none_or(|old| old == value) {
+            return;
+        }
+        match (target,value) {
+            (Target::StyleColor(path,_),Value::Color(color)) => {
+                self.styles.update(StyleChange::OverwriteColor { what: path, color });
+                self.touch_style(target,value);
+            }
+            (Target::StyleWeight(path,_),Value::Weight(weight)) => {
+                self.styles.update(StyleChange::OverwriteWeight { what: path, new_weight: weight });
+                self.touch_style(target,value);
+            }
+            (Target::Dims(anchor,_),Value::Rect(rect)) => {
+                let Some((_,(layout,_))) = self.layouts.iter_mut().find(|(a,_)| a.0 == *anchor) else { return };
+                let old = layout_area(layout);
+                layout.dims = rect;
+                let new = layout_area(layout);
+                self.touch(old);
+                self.touch(new);
+            }
+            (Target::PartRect(anchor,part,_),Value::Rect(rect)) => {
+                let Some((_,(layout,_))) = self.layouts.iter_mut().find(|(a,_)| a.0 == *anchor) else { return };
+                let Some(p) = layout.parts.get_mut(*part) else { return };
+                p.rect = rect;
+                let area = layout_area(layout);
+                self.touch(area);
+            }
+            _ => {}
+        }
+    }
+
+    /// Damage the view if an overwritten style took the value; tables may leave styles they inherit alone
+    fn touch_style(&mut self, target: &Target<Host>, value: Value) {
+        if self.read(target) == Some(value) {
+            self.touch(None);
+        }
+    }
+}
+
+/// Value of a target in the first portal of `entity` having it
+fn read(views: &EntityViews, entity: usize, target: &Target<Host>) -> Option<Value> {
+    views.get(&entity)?.iter().find_map(|(_,view)| view.read(target))
+}
+
+fn write(views: &mut EntityViews, entity: usize, target: &Target<Host>, value: Value) {
+    for (_,view) in views.get_mut(&entity).into_iter().flatten() {
+        view.write(target,value);
+    }
+}
+
+impl Host {
+    /// Bring animated values to `now`, steps ending before it are finished exactly at their target values.
+    /// Animations done send their messages, to be processed in the same update round
+    pub(crate) fn advance_animations(&mut self, now: Duration) {
+        let mut running = std::mem::take(&mut self.animations.running);
+        let mut finished = vec![];
+        running.retain_mut(|anim| loop {
+            let Some(step) = anim.steps.front() else {
+                finished.extend(anim.done.take());
+                return false;
+            };
+            let elapsed = now.saturating_sub(anim.started);
+            if elapsed < step.delay {
+                return true;
+            }
+            if anim.from.is_none() {
+                anim.from = read(&self.data_view,anim.entity,&step.target);
+            }
+            let to = Value::of(&step.target);
+            if elapsed >= step.total() {
+                write(&mut self.data_view,anim.entity,&step.target,to);
+                // the next step starts where this one ended, not at this frame
+                anim.started += step.total();
+                anim.from = None;
+                anim.steps.pop_front();
+                continue;
+            }
+            if let Some(from) = anim.from {
+                write(&mut self.data_view,anim.entity,&step.target,from.lerp(&to,step.progress(elapsed)));
+            }
+            return true;
+        });
+        self.animations.running = running;
+        for done in finished {
+            done(self);
+        }
+    }
+}
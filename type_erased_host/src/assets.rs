@@ -0,0 +1,99 @@
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use futures::executor::ThreadPool;
+use futures::future::{RemoteHandle, Shared};
+use futures::task::SpawnExt;
+use futures::FutureExt;
+use types::assets::{Asset, Handle, WeakHandle};
+use types::errors::assets::AssetError;
+
+/// Resolves once an asset has finished loading, may be awaited by any number of requesters
+pub type Loaded = Shared<RemoteHandle<Result<(),AssetError>>>;
+
+/// Type erased bookkeeping of a requested asset
+trait Tracked: Any {
+    /// Whether anyone still holds a handle to the asset
+    fn is_alive(&self) -> bool;
+    fn as_any(&self) -> &dyn Any;
+}
+
+struct Entry<A> {
+    handle: WeakHandle<A>,
+    loaded: Loaded,
+}
+
+impl<A: Asset> Tracked for Entry<A> {
+    fn is_alive(&self) -> bool {
+        self.handle.is_alive()
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+/// Loads assets on a thread pool and deduplicates requests for the same (type, path) pair.
+/// The server only keeps weak references, an asset lives as long as its handles do.
+pub struct AssetServer {
+    /// directory relative paths are resolved against
+    root: PathBuf,
+    entries: HashMap<(TypeId,PathBuf), Box<dyn Tracked>>,
+}
+
+impl AssetServer {
+    pub fn new<P: Into<PathBuf>>(root: P) -> Self {
+        Self {
+            root: root.into(),
+            entries: HashMap::new(),
+        }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn set_root<P: Into<PathBuf>>(&mut self, root: P) {
+        self.root = root.into();
+    }
+
+    /// Number of assets which are alive or being loaded
+    pub fn len(&self) -> usize {
+        self.entries.values().filter(|e| e.is_alive()).count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// A handle to the asset at `path`, starting to load it unless someone already did
+    pub fn load<A: Asset>(&mut self, runtime: &ThreadPool, path: &Path) -> (Handle<A>,Loaded) {
+        let key = (TypeId::of::<A>(),path.to_path_buf());
+        if let Some(e) = self.entries.get(&key).and_then(|e| e.as_any().downcast_ref::<Entry<A>>()) {
+            if let Some(handle) = e.handle.upgrade() {
+                return (handle,e.loaded.clone());
+            }
+        }
+
+        let handle = Handle::<A>::loading(path);
+        let full = self.root.join(path);
+        let slot = handle.clone();
+        let job = async move {
+            let result = std::fs::read(&full)
+                .map_err(AssetError::from)
+                .and_then(|bytes| A::from_bytes(bytes,slot.path()));
+            let outcome = result.as_ref().map(|_| ()).map_err(Clone::clone);
+            slot.complete(result);
+            outcome
+        };
+        let loaded = runtime.spawn_with_handle(job).expect("failed to spawn an asset load").shared();
+        self.entries.insert(key,Box::new(Entry { handle: handle.downgrade(), loaded: loaded.clone() }));
+        (handle,loaded)
+    }
+
+    /// Forget assets nobody holds a handle to
+    pub fn purge(&mut self) {
+        self.entries.retain(|_,e| e.is_alive());
+    }
+}
@@ -0,0 +1,159 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use types::render::{Primitive, Viewport};
+
+/// Rendered versions of a view are keyed by the viewport and the content generation they were rendered at
+pub type CacheKey = (Viewport, u64);
+
+/// Default memory budget of a view cache, in bytes
+pub const DEFAULT_VIEW_CACHE_BUDGET: usize = 8 << 20;
+
+struct CacheEntry<P> {
+    primitive: Arc<P>,
+    uses: usize,
+    size: usize,
+    /// when the entry was inserted, the oldest of equally used entries is evicted first
+    inserted: u64,
+}
+
+/// A least frequently used cache bounded by memory taken by its primitives.
+/// A view is rendered at few sizes, so entries are few and looking for a victim goes through all of them
+pub struct ViewCache<P: Primitive> {
+    entries: HashMap<CacheKey, CacheEntry<P>>,
+    /// bytes we are allowed to hold
+    budget: usize,
+    /// bytes we are holding now
+    used: usize,
+    /// number of insertions so far
+    inserted: u64,
+}
+
+impl<P: Primitive> ViewCache<P> {
+    pub fn new(budget: usize) -> Self {
+        Self {
+            entries: HashMap::new(),
+            budget,
+            used: 0,
+            inserted: 0,
+        }
+    }
+
+    pub fn set_budget(&mut self, budget: usize) {
+        self.budget = budget;
+        self.evict(0);
+    }
+
+    pub fn get(&mut self, key: &CacheKey) -> Option<&Arc<P>> {
+        self.entries.get_mut(key).map(|e| {
+            e.uses += 1;
+            &e.primitive
+        })
+    }
+
+    /// Primitives larger than the whole budget are not cached
+    pub fn insert(&mut self, key: CacheKey, primitive: Arc<P>) {
+        let size = primitive.footprint();
+        self.remove(&key);
+        if size > self.budget {
+            return;
+        }
+        self.evict(size);
+        self.used += size;
+        self.inserted += 1;
+        self.entries.insert(key, CacheEntry { primitive, uses: 1, size, inserted: self.inserted });
+    }
+
+    /// Drop everything rendered at a content generation other than `generation`
+    pub fn retain_generation(&mut self, generation: u64) {
+        let used = &mut self.used;
+        self.entries.retain(|(_,g),e| {
+            if *g != generation {
+                *used -= e.size;
+            }
+            *g == generation
+        });
+    }
+
+    fn remove(&mut self, key: &CacheKey) {
+        if let Some(e) = self.entries.remove(key) {
+            self.used -= e.size;
+        }
+    }
+
+    /// Evict least used entries until `incoming` bytes fit into the budget
+    fn evict(&mut self, incoming: usize) {
+        while self.used + incoming > self.budget {
+            let victim = self.entries.iter().min_by_key(|(_,e)| (e.uses,e.inserted)).map(|(k,_)| *k);
+            match victim {
+                Some(k) => self.remove(&k),
+                None => break,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use raster_primitive::{Color, Primitive as P};
+
+    use super::*;
+
+    fn key(width: u32, generation: u64) -> CacheKey {
+        (Viewport { width, height: 1 },generation)
+    }
+
+    fn image(width: u32) -> Arc<P> {
+        Arc::new(P::filled(Viewport { width, height: 1 },Color::WHITE))
+    }
+
+    #[test]
+    fn stays_within_budget() {
+        let size = image(4).footprint();
+        let mut cache = ViewCache::new(size * 2 + size / 2);
+        for g in 0..3 {
+            cache.insert(key(4,g),image(4));
+            assert!(cache.used <= cache.budget);
+        }
+        assert_eq!((cache.entries.len(),cache.used),(2,size * 2));
+        // too large to be cached at all, nothing is evicted for it
+        cache.insert(key(64,0),image(64));
+        assert_eq!((cache.entries.len(),cache.used),(2,size * 2));
+        // replacing an entry doesn't count it twice
+        cache.insert(key(4,2),image(4));
+        assert_eq!(cache.used,size * 2);
+        cache.set_budget(size);
+        assert_eq!((cache.entries.len(),cache.used),(1,size));
+        cache.retain_generation(1);
+        assert_eq!((cache.entries.len(),cache.used),(0,0));
+    }
+
+    #[test]
+    fn evicts_least_used_then_oldest() {
+        let size = image(4).footprint();
+        let mut cache = ViewCache::new(size * 3);
+        for g in 0..3 {
+            cache.insert(key(4,g),image(4));
+        }
+        cache.get(&key(4,0));
+        cache.get(&key(4,2));
+        cache.insert(key(4,3),image(4));
+        assert!(cache.get(&key(4,1)).is_none());
+        // all are used twice, the oldest goes
+        cache.get(&key(4,3));
+        cache.insert(key(4,4),image(4));
+        assert!(cache.get(&key(4,0)).is_none());
+        // a fresh entry is the least used
+        cache.insert(key(4,5),image(4));
+        assert!(cache.get(&key(4,4)).is_none());
+        assert!(cache.get(&key(4,2)).is_some() && cache.get(&key(4,3)).is_some() && cache.get(&key(4,5)).is_some());
+    }
+
+    #[test]
+    fn hits_share_the_frame() {
+        let mut cache = ViewCache::new(DEFAULT_VIEW_CACHE_BUDGET);
+        let frame = image(4);
+        cache.insert(key(4,0),frame.clone());
+        assert!(Arc::ptr_eq(cache.get(&key(4,0)).unwrap(),&frame));
+        assert!(cache.get(&key(4,1)).is_none() && cache.get(&key(5,0)).is_none());
+    }
+}
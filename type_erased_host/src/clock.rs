@@ -0,0 +1,199 @@
+use std::any::TypeId;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use types::time::{Clock, SystemClock, Tick, Timestep};
+
+use crate::Host;
+
+/// Delivers a tick to a subscribed component
+pub(crate) type TickDelivery = Arc<dyn Fn(&mut Host, Tick)>;
+
+/// The host's clock, with the ticks given out so far
+pub(crate) struct FrameClock {
+    clock: Box<dyn Clock>,
+    /// reading of `clock` when it was set, and the host's time then
+    origin: Duration,
+    base: Duration,
+    timestep: Timestep,
+    /// clock reading of the current update round
+    now: Duration,
+    /// time of the last tick
+    ticked: Duration,
+    /// time not yet given out in fixed steps
+    carried: Duration,
+    frame: u64,
+    /// components receiving ticks, by entity and system
+    subscribers: BTreeMap<(usize,TypeId),TickDelivery>,
+}
+
+impl Default for FrameClock {
+    fn default() -> Self {
+        Self {
+            clock: Box::new(SystemClock::new()),
+            origin: Duration::ZERO,
+            base: Duration::ZERO,
+            timestep: Timestep::default(),
+            now: Duration::ZERO,
+            ticked: Duration::ZERO,
+            carried: Duration::ZERO,
+            frame: 0,
+            subscribers: BTreeMap::new(),
+        }
+    }
+}
+
+impl FrameClock {
+    /// Replace the time source; time goes on from the current round, as the new clock moves from its present reading,
+    /// so running animations and fixed steps aren't disturbed
+    pub(crate) fn set_clock(&mut self, mut clock: Box<dyn Clock>) {
+        self.origin = clock.now();
+        self.base = self.now;
+        self.clock = clock;
+    }
+
+    pub(crate) fn set_timestep(&mut self, timestep: Timestep) {
+        self.timestep = timestep;
+        self.ticked = self.now;
+        self.carried = Duration::ZERO;
+    }
+
+    pub(crate) fn now(&self) -> Duration {
+        self.now
+    }
+
+    pub(crate) fn subscribe(&mut self, entity: usize, system: TypeId, delivery: TickDelivery) {
+        self.subscribers.insert((entity,system),delivery);
+    }
+
+    pub(crate) fn unsubscribe(&mut self, entity: usize, system: TypeId) {
+        self.subscribers.remove(&(entity,system));
+    }
+
+    /// Forget subscriptions of a dropped entity
+    pub(crate) fn drop_entity(&mut self, entity: usize) {
+        self.subscribers.retain(|(e,_),_| *e != entity);
+    }
+
+    pub(crate) fn subscribers(&self) -> Vec<TickDelivery> {
+        self.subscribers.values().cloned().collect()
+    }
+
+    /// Read the clock for a new update round, returns the ticks it makes
+    pub(crate) fn advance(&mut self) -> Vec<Tick> {
+        // a clock going backwards is treated as standing still
+        let now = (self.base + self.clock.now().saturating_sub(self.origin)).max(self.now);
+        let passed = now - self.now;
+        self.now = now;
+        match self.timestep {
+            Timestep::Variable => {
+                let tick = self.tick(now - self.ticked);
+                vec![tick]
+            }
+            Timestep::Fixed { step, .. } if step.is_zero() => vec![],
+            Timestep::Fixed { step, max_steps } => {
+                self.carried += passed;
+                let due = (self.carried.as_nanos() / step.as_nanos()).min(u32::MAX as u128) as u32;
+                let steps = due.min(max_steps);
+                self.carried = if due > max_steps { Duration::ZERO } else { self.carried - step * steps };
+                (0..steps).map(|_| self.tick(step)).collect()
+            }
+        }
+    }
+
+    fn tick(&mut self, delta: Duration) -> Tick {
+        self.ticked += delta;
+        let tick = Tick { delta, now: self.ticked, frame: self.frame };
+        self.frame += 1;
+        tick
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use types::time::ManualClock;
+
+    use super::*;
+
+    fn ms(ms: u64) -> Duration {
+        Duration::from_millis(ms)
+    }
+
+    /// A clock giving the readings it's made of, then standing at the last one
+    struct Readings(Vec<Duration>);
+
+    impl Clock for Readings {
+        fn now(&mut self) -> Duration {
+            if self.0.len() > 1 { self.0.remove(0) } else { self.0[0] }
+        }
+    }
+
+    fn manual(timestep: Timestep) -> (FrameClock,ManualClock) {
+        let mut frames = FrameClock::default();
+        let clock = ManualClock::new();
+        frames.set_clock(Box::new(clock.clone()));
+        frames.set_timestep(timestep);
+        (frames,clock)
+    }
+
+    #[test]
+    fn variable_ticks() {
+        let (mut frames,clock) = manual(Timestep::Variable);
+        clock.advance(ms(16));
+        assert_eq!(frames.advance(),vec![Tick { delta: ms(16), now: ms(16), frame: 0 }]);
+        assert_eq!(frames.advance(),vec![Tick { delta: ms(0), now: ms(16), frame: 1 }]);
+    }
+
+    #[test]
+    fn fixed_steps_carry_the_remainder() {
+        let (mut frames,clock) = manual(Timestep::fixed(ms(10)));
+        clock.advance(ms(25));
+        let ticks = frames.advance();
+        assert_eq!(ticks.iter().map(|t| (t.delta,t.now)).collect::<Vec<_>>(),vec![(ms(10),ms(10)),(ms(10),ms(20))]);
+        // 5ms were carried, 4 more don't make a step
+        clock.advance(ms(4));
+        assert!(frames.advance().is_empty());
+        clock.advance(ms(1));
+        assert_eq!(frames.advance(),vec![Tick { delta: ms(10), now: ms(30), frame: 2 }]);
+        assert_eq!(frames.now(),ms(30));
+    }
+
+    #[test]
+    fn steps_beyond_the_limit_are_dropped() {
+        let (mut frames,clock) = manual(Timestep::Fixed { step: ms(10), max_steps: 3 });
+        clock.advance(ms(105));
+        assert_eq!(frames.advance().len(),3);
+        // nothing is carried after a drop, not even the remainder
+        clock.advance(ms(9));
+        assert!(frames.advance().is_empty());
+        clock.advance(ms(1));
+        let ticks = frames.advance();
+        assert_eq!(ticks.len(),1);
+        // ticks keep their own time, behind the clock by what was dropped
+        assert_eq!((ticks[0].now,frames.now()),(ms(40),ms(115)));
+    }
+
+    #[test]
+    fn backwards_clock_stands_still() {
+        let mut frames = FrameClock::default();
+        frames.set_clock(Box::new(Readings(vec![ms(0),ms(50),ms(30),ms(60)])));
+        assert_eq!(frames.advance()[0].now,ms(50));
+        let tick = frames.advance()[0];
+        assert_eq!((tick.delta,tick.now),(ms(0),ms(50)));
+        let tick = frames.advance()[0];
+        assert_eq!((tick.delta,tick.now),(ms(10),ms(60)));
+    }
+
+    #[test]
+    fn replaced_clocks_go_on_from_the_current_time() {
+        let (mut frames,clock) = manual(Timestep::Variable);
+        clock.advance(ms(100));
+        frames.advance();
+        let replacement = ManualClock::new();
+        replacement.advance(ms(500));
+        frames.set_clock(Box::new(replacement.clone()));
+        replacement.advance(ms(20));
+        assert_eq!(frames.advance(),vec![Tick { delta: ms(20), now: ms(120), frame: 1 }]);
+    }
+}
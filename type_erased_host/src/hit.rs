@@ -0,0 +1,109 @@
+use types::render::{self, Anchor, Filling, Layout, Point, Rect, Viewport};
+
+use crate::overlay::Placed;
+use crate::{lookup_view, part_viewport, EntityViews, Host, ViewData};
+
+/// A layout under a point
+#[derive(Clone,Debug,PartialEq)]
+pub struct Hit {
+    pub entity: usize,
+    pub portal: usize,
+    /// anchor the layout is attached at; for overlays, their origin anchor
+    pub anchor: Anchor,
+    /// the part under the point, `None` if it's the background of the layout
+    pub part: Option<usize>,
+    /// the point, relative to the view of the entity
+    pub at: Point<f32>,
+    /// whether the layout is an overlay of the entity
+    pub overlay: bool,
+}
+
+/// `p` relative to `area`, both relative to the same view
+fn relative_point(p: Point<f32>, area: Rect<f32,f32>) -> Point<f32> {
+    let ul = area.upper_left();
+    Point::relative((p.x() - ul.x()) / area.width(),(p.y() - ul.y()) / area.height())
+}
+
+/// Whether a raster filling is opaque at `p`, relative to it
+fn opaque_at(primitive: &<Host as types::traits::Host>::Primitive, p: Point<f32>) -> bool {
+    let vp = render::Primitive::size(primitive);
+    let (x,y) = ((p.x() * vp.width as f32) as u32,(p.y() * vp.height as f32) as u32);
+    primitive.get(Point::absolute(x.min(vp.width.saturating_sub(1)),y.min(vp.height.saturating_sub(1)))).is_some_and(|c| c.a > 0)
+}
+
+impl ViewData<Host> {
+    /// Hits under `p`, relative to the view `(ind,portal)` shown at `vp`: the view first, components nested in it after.
+    /// Empty if everything at `p` is transparent
+    fn hit(&self, views: &EntityViews, ind: usize, portal: usize, vp: Viewport, p: Point<f32>) -> Vec<Hit> {
+        self.sorted_layouts().into_iter().rev()
+            .find_map(|(anchor,layout)| self.hit_layout(views,(ind,portal,anchor),layout,vp,p,false))
+            .unwrap_or_default()
+    }
+
+    /// Hits under `p` in one of the view's layouts, `None` if it's transparent there.
+    /// Parts are tested top down, invisible ones and those whose nested component or raster is transparent at `p` are skipped
+    fn hit_layout(&self, views: &EntityViews, (ind,portal,anchor): (usize,usize,&Anchor), layout: &Layout<Host>, vp: Viewport,
+                  p: Point<f32>, overlay: bool) -> Option<Vec<Hit>> {
+        if layout.opacity <= 0. {
+            return None;
+        }
+        let local = layout.to_local(p,vp)?;
+        let hit = |part| Hit { entity: ind, portal, anchor: anchor.clone(), part, at: p, overlay };
+        let layout_vp = part_viewport(vp,layout.dims,Rect::full_box());
+        for (i,part) in layout.parts.iter().enumerate().rev() {
+            if part.opacity <= 0. {
+                continue;
+            }
+            let Some(at) = part.to_local(local,layout_vp) else { continue };
+            let part_vp = part_viewport(vp,layout.dims,part.rect);
+            match &part.filling {
+                Filling::Component(child,child_portal) => {
+                    let nested = lookup_view(views,child,child_portal)
+                        .hit(views,*child,*child_portal,part_vp,at);
+                    if !nested.is_empty() {
+                        return Some(std::iter::once(hit(Some(i))).chain(nested).collect());
+                    }
+                }
+                Filling::Data(primitive) if !opaque_at(primitive,at) => {}
+                // letterboxes of fitted images are transparent too
+                Filling::Image(image,fit) if fit.image_point(at,part_vp,render::Primitive::size(&**image)).is_none_or(|at| !opaque_at(image,at)) => {}
+                _ => return Some(vec![hit(Some(i))]),
+            }
+        }
+        (layout.bgc.a > 0).then(|| vec![hit(None)])
+    }
+}
+
+impl Host {
+    /// What is under a pixel of root portal `screen_idx`, as it was last rendered: the root first, then the components
+    /// nested down to the innermost one, each with the anchor of its layout and the part under the pixel.
+    /// Layouts are tested from the top, transparent backgrounds and parts let lower ones be hit.
+    /// Overlays are tested before anything else; their hits start with the components their owner is nested in.
+    /// Transforms of layouts and parts are respected, except for positioning overlays
+    pub fn hit_test(&self, screen_idx: usize, at: Point<u32>) -> Vec<Hit> {
+        let Some(root) = self.root else { return vec![] };
+        let Some((_,view)) = self.data_view.get(&root).and_then(|v| v.iter().find(|(i,_)| *i == screen_idx)) else { return vec![] };
+        let vp = view.vp;
+        // the center of the pixel
+        let p = Point::relative((at.x() as f32 + 0.5) / vp.width.max(1) as f32,(at.y() as f32 + 0.5) / vp.height.max(1) as f32);
+
+        for Placed { key: (ind,portal,origin), path, area } in self.placed_overlays.get(&screen_idx).into_iter().flatten().rev() {
+            let Some((_,owner)) = self.data_view.get(ind).and_then(|v| v.iter().find(|(i,_)| i == portal)) else { continue };
+            let Some(overlay) = owner.overlays.get(origin) else { continue };
+            let Some(hits) = owner.hit_layout(&self.data_view,(*ind,*portal,origin),&overlay.layout,vp,p,true) else { continue };
+            let path = path.iter().map(|(ind,portal,anchor,part,area,overlay)| Hit {
+                entity: *ind,
+                portal: *portal,
+                anchor: anchor.clone(),
+                part: Some(*part),
+                at: relative_point(p,*area),
+                overlay: *overlay,
+            });
+            // the overlay was tested at the scale of the root, the point is made relative to its owner
+            let mut hits = hits.into_iter();
+            let owner = hits.next().map(|h| Hit { at: relative_point(p,*area), ..h });
+            return path.chain(owner).chain(hits).collect();
+        }
+        view.hit(&self.data_view,root,screen_idx,vp,p)
+    }
+}
@@ -1,34 +1,75 @@
 extern crate typemap;
 extern crate types;
 extern crate futures;
+extern crate raster_primitive;
+extern crate svg_primitive;
+
+mod animation;
+mod assets;
+mod cache;
+mod clock;
+mod hit;
+mod overlay;
+mod pointer;
+#[cfg(test)]
+mod tests;
+
+pub use assets::AssetServer;
+pub use cache::DEFAULT_VIEW_CACHE_BUDGET;
+pub use hit::Hit;
 
 use std::any::{Any, TypeId};
 use std::collections::{BTreeMap, HashMap};
 use std::future::Future;
-use types::traits::{System, Hosts, Host as HostTrait, Context, GlobalState, View};
-use types::errors::traits::{AllocError, ReduceError};
+use types::traits::{GlobalState, System, Hosts, View};
+use types::errors::traits::AllocError;
 use futures::task::SpawnExt;
-use futures::{FutureExt, TryFutureExt, StreamExt};
+use futures::FutureExt;
+use futures::executor::ThreadPool;
+use futures::future::{BoxFuture, Shared};
 use std::marker::PhantomData;
 use typemap::{Entry, TypeMap};
 use std::convert::TryFrom;
-use bitmaps::Bitmap;
 use std::collections::btree_map::Entry as BEntry;
 use std::collections::hash_map::Entry as HEntry;
-use winit::event::WindowEvent;
 use std::task::{Poll, Waker, RawWaker, RawWakerVTable};
 use std::pin::Pin;
-use winit::event::VirtualKeyCode::Wake;
 use std::sync::Arc;
-use std::path::Path;
-use types::render::{Anchor, Viewport, StyleTable, self, Visitor, Primitive, ZIndex, Layout, Filling};
+use std::path::{Path, PathBuf};
+use types::anim::{Animation, AnimationId};
+use types::time::{Clock, Tick, Timestep};
+use types::pointer::{Phase, Propagation};
+use types::assets::{Asset, Handle};
+use types::errors::assets::AssetError;
+use types::render::{Anchor, Viewport, StyleTable, self, Primitive, ZIndex, Layout, Part, Filling, TextPrimitive, ShapePrimitive};
+use types::render::constraints::Constraints;
+use types::render::display_list::{DisplayList, Draw};
 use std::cell::RefCell;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+use cache::ViewCache;
+use animation::Animations;
+use clock::FrameClock;
+use overlay::Overlays;
+use pointer::PointerListeners;
 
 /// A map from entities to their components data
 type EntityStorage = BTreeMap<usize, (typemap::TypeMap, ProcessingFunctionsEntity)>;
 /// A map from entities to their view's data
 /// index -> set of portal's view data.
 type EntityViews = BTreeMap<usize, Vec<(usize, ViewData<Host>)>>;
+/// A primitive entities are rendered into
+type HostPrimitive = <Host as types::traits::Host>::Primitive;
+/// Reduces queued messages of a system
+type MsgReducer = Arc<dyn Fn(&mut Host)>;
+/// Delivers resolved futures of a system
+type FutureDelivery = Arc<dyn Fn(&mut TypeMap, &mut EntityStorage)>;
+/// Produces views of an entity
+type ViewFunction = Arc<dyn Fn(&mut EntityViews,&mut EntityStorage)>;
+/// Passes an event to the event filters of an entity
+type EventDispatch = Box<dyn for<'s> Fn(&'s <Host as types::traits::Host>::Event, &'s mut typemap::TypeMap)>;
+/// A display list, with the viewport and content generation it was built for
+type BuiltList<P> = ((render::Viewport,u64),Arc<DisplayList<P>>);
 
 pub struct Host {
     /// free ids
@@ -42,13 +83,41 @@ pub struct Host {
     /// a map from entities to their views
     data_view: EntityViews,
     /// collection of reducer functions, one for each system
-    msg_reducers: HashMap<TypeId, std::sync::Arc<dyn Fn(&mut Self)>>,
+    msg_reducers: HashMap<TypeId, MsgReducer>,
     /// collection of Future resolvers
-    future_delivery: HashMap<TypeId, std::sync::Arc<dyn Fn(&mut TypeMap, &mut EntityStorage)>>,
+    future_delivery: HashMap<TypeId, FutureDelivery>,
     /// a code for producing a views
-    views: BTreeMap<usize,std::sync::Arc<dyn Fn(&mut EntityViews,&mut EntityStorage)>>,
+    views: BTreeMap<usize,ViewFunction>,
     /// a futures runtime.
     runtime: futures::executor::ThreadPool,
+    /// loaded and loading assets
+    assets: AssetServer,
+    /// scale factors of windows, by the root portal they show
+    scale_factors: HashMap<usize,render::ScaleFactor>,
+    render_mode: RenderMode,
+    /// animations started by systems
+    animations: Animations,
+    /// frame clock, read at the start of every update round
+    clock: FrameClock,
+    /// overlays placed at the last render of each root portal, in the order they were drawn
+    placed_overlays: HashMap<usize,Vec<overlay::Placed>>,
+    /// the last cursor position in each window, by root portal
+    cursors: HashMap<usize,render::Point<u32>>,
+    /// what the cursor was last moved over in each window, told when it leaves the window
+    hovered: HashMap<usize,Vec<Hit>>,
+}
+
+/// How root portals are rendered
+#[derive(Clone,Copy,Debug,Default,Eq,PartialEq)]
+pub enum RenderMode {
+    /// every view is composited into a buffer of its own, which is cached and repaired where damaged
+    #[default]
+    Immediate,
+    /// layouts of all views are flattened into a display list, replayed into the frame at once
+    Retained,
+    /// like `Immediate`, but nested components and layouts are rasterised in parallel on the host's thread pool,
+    /// then mixed in z-order; frames are the same as in `Immediate` mode
+    Parallel,
 }
 
 pub struct ViewData<H: types::traits::Host> {
@@ -61,79 +130,751 @@ pub struct ViewData<H: types::traits::Host> {
     vp: render::Viewport,
     /// a table of styles
     styles: Box<dyn StyleTable<H>>,
-    /// a cache for rendered versions of self todo: clean it somewhere + pick right capacity
-    view_cache: RefCell<lfu::LFUCache<render::Viewport,Self::Primitive>>,
+    /// a cache for rendered versions of self
+    view_cache: RefCell<ViewCache<H::Primitive>>,
+    /// changes each time layouts or styles of this view change
+    generation: u64,
+    /// what has changed since the last render
+    damage: RefCell<Damage>,
+    /// the last rendered frame, undamaged parts are taken from it
+    last_frame: RefCell<Option<(render::Viewport,Arc<H::Primitive>)>>,
+    /// positions of constrained anchors, layouts set at them are placed by the solver
+    constraints: Option<Constraints>,
+    /// scale factor of the window the view is shown in
+    scale: render::ScaleFactor,
+    /// the last display list built
+    display_list: RefCell<Option<BuiltList<H::Primitive>>>,
+    /// layouts teleported above the whole tree
+    overlays: Overlays<H>,
+    /// what was found walking nested components, cleared before each render
+    memo: RefCell<Memo>,
 }
 
+/// Generations are unique across all views
+static NEXT_GENERATION: AtomicU64 = AtomicU64::new(0);
 
-impl<'a> types::render::Visitor<Host::Primitive> for ViewData<Host>
-    where Host: 'a
-{
-    type Ctx = (&'a mut EntityViews,render::Viewport);
+impl<H: types::traits::Host> ViewData<H> {
+    pub fn new(anchors: Vec<render::Anchor>, vp: render::Viewport, styles: Box<dyn StyleTable<H>>) -> Self {
+        Self {
+            anchors,
+            z_index_range: 0..1,
+            layouts: HashMap::new(),
+            vp,
+            styles,
+            view_cache: RefCell::new(ViewCache::new(DEFAULT_VIEW_CACHE_BUDGET)),
+            generation: NEXT_GENERATION.fetch_add(1,Ordering::Relaxed),
+            damage: RefCell::new(Damage { full: true, rects: vec![] }),
+            last_frame: RefCell::new(None),
+            constraints: None,
+            scale: render::ScaleFactor::default(),
+            display_list: RefCell::new(None),
+            overlays: HashMap::new(),
+            memo: RefCell::new(Memo::default()),
+        }
+    }
+
+    /// Scale factor of the window the view is shown in
+    pub fn scale_factor(&self) -> render::ScaleFactor {
+        self.scale
+    }
+
+    /// Place anchors by constraints, solved for the size of the view
+    pub fn with_constraints(mut self, mut constraints: Constraints) -> Self {
+        constraints.resize(self.vp);
+        self.constraints = Some(constraints);
+        self
+    }
+
+    /// Change the size of the view, re-solving its constraints
+    pub fn resize(&mut self, vp: render::Viewport) {
+        self.vp = vp;
+        let Some(constraints) = self.constraints.as_mut() else { return };
+        if !constraints.resize(vp) {
+            return;
+        }
+        let mut moved = false;
+        for (anc,(layout,_)) in self.layouts.iter_mut() {
+            if let Some(rect) = constraints.rect(&anc.0) {
+                moved |= layout.dims != rect;
+                layout.dims = rect;
+            }
+        }
+        if moved {
+            self.touch(None);
+        }
+    }
+
+    /// A layout at a constrained anchor takes the solved rect of the anchor
+    fn place(&self, anc: &Anchor, layout: &mut Layout<H>) {
+        if let Some(rect) = self.constraints.as_ref().and_then(|c| c.rect(&anc.0)) {
+            layout.dims = rect;
+        }
+    }
+
+    /// Layouts of the view with their anchors, in the order they are drawn
+    fn sorted_layouts(&self) -> Vec<(&Anchor,&Layout<H>)> {
+        // sorting by anchor name as well keeps the order of a layer stable between frames
+        let mut layouts_sorted: Vec<_> = self.layouts.iter().map(|(a,(l,i))| (*i,a,l)).collect();
+        layouts_sorted.sort_unstable_by(|el,el2| el.0.cmp(&el2.0).then_with(|| el.1.0.cmp(&el2.1.0)));
+        layouts_sorted.into_iter().map(|(_,a,l)| (a,l)).collect()
+    }
+
+    /// Set memory budget for cached rendered versions of the view, in bytes
+    pub fn with_cache_budget(self, budget: usize) -> Self {
+        self.view_cache.borrow_mut().set_budget(budget);
+        self
+    }
+
+    /// Record a change of the view: `Some` rect is damaged, `None` means everything is.
+    /// Cached renders become stale, they are dropped when the view is rendered at its new generation.
+    fn touch(&mut self, rect: Option<render::Rect<f32,f32>>) {
+        let damage = self.damage.get_mut();
+        match rect {
+            Some(rect) => damage.add(rect),
+            None => damage.full = true,
+        }
+        self.generation = NEXT_GENERATION.fetch_add(1,Ordering::Relaxed);
+        *self.memo.get_mut() = Memo::default();
+    }
+}
+
+/// Color and weight of text which has no style in the table
+const DEFAULT_TEXT_STYLE: (raster_primitive::Color, u16) = (raster_primitive::Color::BLACK, 400);
+
+/// Past this amount, damaged rects are merged into their bounding box
+const MAX_DAMAGE_RECTS: usize = 8;
+
+/// Results of walks over the components nested in a view, so that each is walked once per render
+#[derive(Default)]
+struct Memo {
+    /// see `content_generation`
+    generation: Option<u64>,
+    /// see `pending_damage`, with the size it's for
+    damage: Option<(render::Viewport,Option<Damage>)>,
+}
+
+/// Parts of a view changed since its last render
+#[derive(Default,Clone)]
+struct Damage {
+    /// everything has to be redrawn
+    full: bool,
+    /// rects relative to the view
+    rects: Vec<render::Rect<f32,f32>>,
+}
+
+impl Damage {
+    fn add(&mut self, rect: render::Rect<f32,f32>) {
+        if !self.full && !self.rects.contains(&rect) {
+            self.rects.push(rect);
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        !self.full && self.rects.is_empty()
+    }
+
+    fn simplify(&mut self) {
+        if self.rects.len() > MAX_DAMAGE_RECTS {
+            let bbox = self.rects.iter().skip(1).fold(self.rects[0],|acc,r| acc.union(*r));
+            self.rects = vec![bbox];
+        }
+    }
+}
+
+fn lookup_view<'v>(views: &'v EntityViews, ind: &usize, portal: &usize) -> &'v ViewData<Host> {
+    views.get(ind)
+        .and_then(|v| v.iter().find(|(i,_)| i == portal))
+        .map(|(_,d)| d)
+        .unwrap_or_else(|| panic!("No view data for required index {ind:?}"))
+}
+
+/// Debug builds warn about layouts sticking out of their view and about overlapping or sticking out parts.
+/// Only issues the previous layout of the anchor didn't have are reported, so a view redrawn every round doesn't flood the output.
+#[cfg(debug_assertions)]
+fn report_layout_issues<H: types::traits::Host + ?Sized>(anc: &Anchor, layout: &Layout<H>, old: Option<&Layout<H>>) {
+    let full = render::Rect::full_box();
+    if !full.contains(layout.dims) && old.is_none_or(|o| full.contains(o.dims)) {
+        eprintln!("warning: layout at anchor `{}` sticks out of its view: {:?}",anc.0,layout.dims);
+    }
+    let known = old.map(Layout::validate).unwrap_or_default();
+    for issue in layout.validate().into_iter().filter(|i| !known.contains(i)) {
+        match issue {
+            render::LayoutIssue::OutOfBounds(i) => eprintln!(
+                "warning: part #{} of layout at anchor `{}` sticks out of the layout: {:?}",i,anc.0,layout.parts[i].rect
+            ),
+            render::LayoutIssue::Overlap(i,j) => eprintln!(
+                "warning: parts #{} and #{} of layout at anchor `{}` overlap",i,j,anc.0
+            ),
+        }
+    }
+}
+
+/// Area of a view a layout may draw to; `None` if that depends on the size the view is rendered at
+fn layout_area<H: types::traits::Host + ?Sized>(layout: &Layout<H>) -> Option<render::Rect<f32,f32>> {
+    match layout.transform {
+        Some(_) => None,
+        None => Some(layout.dims),
+    }
+}
+
+/// Give a view and everything nested in it a scale factor
+fn propagate_scale(views: &mut EntityViews, ind: usize, portal: usize, scale: render::ScaleFactor) {
+    let mut stack = vec![(ind,portal)];
+    let mut seen = std::collections::HashSet::new();
+    while let Some((ind,portal)) = stack.pop() {
+        if !seen.insert((ind,portal)) {
+            continue;
+        }
+        let Some((_,view)) = views.get_mut(&ind).and_then(|v| v.iter_mut().find(|(i,_)| *i == portal)) else { continue };
+        view.scale = scale;
+        for layout in view.layouts.values().map(|(l,_)| l).chain(view.overlays.values().map(|o| &o.layout)) {
+            for part in layout.parts.iter() {
+                if let Filling::Component(ind,portal) = part.filling {
+                    stack.push((ind,portal));
+                }
+            }
+        }
+    }
+}
+
+/// Size a view and everything nested in it as they are composited at `vp`, nested constraints are solved for their parts.
+/// Components in overlays are sized by the rect the overlay was last placed at in the root portal
+fn propagate_size(views: &mut EntityViews, ind: usize, portal: usize, vp: render::Viewport) {
+    let root_vp = vp;
+    let mut stack = vec![(ind,portal,vp)];
+    let mut seen = std::collections::HashSet::new();
+    while let Some((ind,portal,vp)) = stack.pop() {
+        if !seen.insert((ind,portal)) {
+            continue;
+        }
+        let Some((_,view)) = views.get_mut(&ind).and_then(|v| v.iter_mut().find(|(i,_)| *i == portal)) else { continue };
+        view.resize(vp);
+        for (layout,_) in view.layouts.values() {
+            for part in layout.parts.iter() {
+                if let Filling::Component(ind,portal) = part.filling {
+                    stack.push((ind,portal,part_viewport(vp,layout.dims,part.rect)));
+                }
+            }
+        }
+        for layout in view.overlays.values().map(|o| &o.layout) {
+            for part in layout.parts.iter() {
+                if let Filling::Component(ind,portal) = part.filling {
+                    stack.push((ind,portal,part_viewport(root_vp,layout.dims,part.rect)));
+                }
+            }
+        }
+    }
+}
+
+/// What views are composited into: the host's own primitive, or a recording for exports
+trait Canvas: Primitive<Color = raster_primitive::Color> + TextPrimitive + ShapePrimitive + Clone {
+    fn filled(vp: Viewport, color: Self::Color) -> Self;
+    /// Component `(ind,portal)` viewed by `view`, rendered at `vp`
+    fn nested(view: &ViewData<Host>, views: &EntityViews, vp: Viewport, ind: usize, portal: usize) -> Arc<Self>;
+    /// Pixels handed over in fillings
+    fn raster(p: &HostPrimitive) -> std::borrow::Cow<'_,Self>;
+    /// Mix `src` into `place`; canvases keeping what is drawn into them take it without a copy
+    fn embed(&mut self, place: render::Rect<f32,f32>, src: Arc<Self>, params: render::Compositing) {
+        self.blend_from(place,&src,params)
+    }
+}
+
+/// A part of a planned layout, showing `fill`
+struct PlannedPart<S> {
+    rect: render::Rect<f32,f32>,
+    /// the (transformed) rect of the part, nothing is drawn outside of it
+    bounds: render::Rect<f32,f32>,
+    params: render::Compositing,
+    /// size of the part in pixels
+    vp: Viewport,
+    fill: S,
+}
+
+/// A layout of a view, with what its parts show resolved to `S`
+struct PlannedLayout<S> {
+    /// where the layout goes, relative to the area being composited
+    place: render::Rect<f32,f32>,
+    params: render::Compositing,
+    vp: Viewport,
+    bgc: raster_primitive::Color,
+    parts: Vec<PlannedPart<S>>,
+}
+
+impl<S> PlannedLayout<S> {
+    /// Draw the layout into a buffer of its own; `draw` puts what a part shows into a place of a target,
+    /// the size of the place in pixels is given along
+    fn rasterise<C: Canvas>(self, mut draw: impl FnMut(&mut C, render::Rect<f32,f32>, Viewport, &S)) -> C {
+        let mut l_primitive = C::filled(self.vp,self.bgc);
+        for part in self.parts {
+            // a part never draws outside of its (transformed) rect
+            l_primitive.set_scissor(Some(self.vp.scissor_box(part.bounds)));
+            if part.params.is_plain() {
+                draw(&mut l_primitive,part.rect,part.vp,&part.fill);
+            } else {
+                let mut offscreen = C::blank(part.vp);
+                draw(&mut offscreen,render::Rect::full_box(),part.vp,&part.fill);
+                l_primitive.embed(part.rect,Arc::new(offscreen),part.params);
+            }
+        }
+        l_primitive.set_scissor(None);
+        l_primitive
+    }
+}
+
+/// Draw a part resolved for compositing on the thread pool
+fn draw<C: Canvas>(target: &mut C, place: render::Rect<f32,f32>, draw: &Draw<C>) {
+    match draw {
+        Draw::Fill(color) => target.fill_shape(place,&render::Shape::rect(),*color),
+        Draw::Shape(shape,paint) => target.draw_shape(place,shape,paint),
+        Draw::Text(text,color,weight) => target.draw_text(place,text,*color,*weight),
+        Draw::Image(image,fit) => target.draw_image(place,image,*fit),
+    }
+}
+
+/// A frame being composited on the thread pool
+type PendingFrame = Shared<BoxFuture<'static,Arc<HostPrimitive>>>;
+/// Frames being composited, by view, viewport and content generation
+type FramesInFlight = Vec<((usize,usize,Viewport,u64),PendingFrame)>;
+
+/// What a part shows, once the nested component it may be is composited
+enum Pending {
+    Ready(Draw<HostPrimitive>),
+    Frame(PendingFrame),
+}
+
+impl Canvas for HostPrimitive {
+    fn filled(vp: Viewport, color: Self::Color) -> Self {
+        HostPrimitive::filled(vp,color)
+    }
+
+    fn nested(view: &ViewData<Host>, views: &EntityViews, vp: Viewport, _: usize, _: usize) -> Arc<Self> {
+        view.frame(views,vp)
+    }
+
+    fn raster(p: &HostPrimitive) -> std::borrow::Cow<'_,Self> {
+        std::borrow::Cow::Borrowed(p)
+    }
+}
+
+/// Exports aren't cached, every component is recorded anew as a group of its own
+impl Canvas for svg_primitive::Primitive {
+    fn filled(vp: Viewport, color: Self::Color) -> Self {
+        svg_primitive::Primitive::filled(vp,color)
+    }
+
+    fn nested(view: &ViewData<Host>, views: &EntityViews, vp: Viewport, ind: usize, portal: usize) -> Arc<Self> {
+        let mut p: Self = view.composite(views,vp,render::Rect::full_box(),vp);
+        p.set_component(ind,portal);
+        Arc::new(p)
+    }
+
+    fn raster(p: &HostPrimitive) -> std::borrow::Cow<'_,Self> {
+        std::borrow::Cow::Owned(svg_primitive::Primitive::from_raster(p))
+    }
+
+    fn embed(&mut self, place: render::Rect<f32,f32>, src: Arc<Self>, params: render::Compositing) {
+        svg_primitive::Primitive::embed(self,place,src,params)
+    }
+}
+
+/// Viewport of a part placed in a layout of a `vp` sized view
+fn part_viewport(vp: render::Viewport, dims: render::Rect<f32,f32>, part: render::Rect<f32,f32>) -> render::Viewport {
+    let layout_vp = render::Rect::<(),()>::zero().down_right_absolute(vp.as_point()).get_absolute_rect(dims).get_viewport();
+    render::Rect::<(),()>::zero().down_right_absolute(layout_vp.as_point()).get_absolute_rect(part).get_viewport()
+}
+
+impl ViewData<Host> {
+    /// Generation of what the view shows: changes whenever the view or any of its nested components change.
+    /// Remembered until the view changes or the next render starts
+    fn content_generation(&self, views: &EntityViews) -> u64 {
+        if let Some(generation) = self.memo.borrow().generation {
+            return generation;
+        }
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        self.generation.hash(&mut hasher);
+        for (layout,_) in self.layouts.values() {
+            for part in layout.parts.iter() {
+                if let Filling::Component(ind, portal) = &part.filling {
+                    lookup_view(views,ind,portal).content_generation(views).hash(&mut hasher);
+                }
+            }
+        }
+        let generation = hasher.finish();
+        self.memo.borrow_mut().generation = Some(generation);
+        generation
+    }
+
+    /// Damage to be repaired by rendering at `vp`, including damage of nested components; `None` if the last frame is up to date.
+    /// Remembered like `content_generation`, and forgotten once the view is rendered
+    fn pending_damage(&self, views: &EntityViews, vp: render::Viewport) -> Option<Damage> {
+        if let Some((memo_vp,damage)) = &self.memo.borrow().damage {
+            if *memo_vp == vp {
+                return damage.clone();
+            }
+        }
+        let damage = self.collect_damage(views,vp);
+        self.memo.borrow_mut().damage = Some((vp,damage.clone()));
+        damage
+    }
+
+    fn collect_damage(&self, views: &EntityViews, vp: render::Viewport) -> Option<Damage> {
+        let fresh = matches!(&*self.last_frame.borrow(), Some((last_vp,_)) if *last_vp == vp);
+        let mut damage = self.damage.borrow().clone();
+        if !fresh {
+            damage.full = true;
+        }
+        if damage.full {
+            return Some(damage);
+        }
+        for (layout,_) in self.layouts.values() {
+            for Part { rect, filling, transform, .. } in layout.parts.iter() {
+                if let Filling::Component(ind, portal) = filling {
+                    let child = lookup_view(views,ind,portal);
+                    let child_vp = part_viewport(vp,layout.dims,*rect);
+                    if let Some(child_damage) = child.pending_damage(views,child_vp) {
+                        let part = rect.map_into(layout.dims);
+                        if layout.transform.is_some() {
+                            damage.add(layout.bounds(vp));
+                        } else if let Some(t) = transform {
+                            let layout_vp = part_viewport(vp,layout.dims,render::Rect::full_box());
+                            damage.add(rect.transformed_bounds(t,layout_vp).map_into(layout.dims));
+                        } else if child_damage.full {
+                            damage.add(part);
+                        } else {
+                            damage.rects.extend(child_damage.rects.iter().map(|r| r.map_into(part)));
+                        }
+                    }
+                }
+            }
+        }
+        if damage.is_empty() {
+            None
+        } else {
+            damage.simplify();
+            Some(damage)
+        }
+    }
+
+    /// Layouts of the view touching `clip`, bottom up; `resolve` tells what a filling shows in a part of `part_vp` size
+    fn plan<'v,S>(&'v self, vp: render::Viewport, clip: render::Rect<f32,f32>, resolve: impl FnMut(&'v Filling<Host>, render::Viewport) -> S) -> Vec<PlannedLayout<S>> {
+        self.plan_layouts(self.sorted_layouts().into_iter().map(|(_,l)| l),vp,clip,resolve)
+    }
+
+    /// Like `plan`, for given layouts of the view, in the order they are drawn
+    fn plan_layouts<'l,S>(&self, layouts: impl IntoIterator<Item = &'l Layout<Host>>, vp: render::Viewport, clip: render::Rect<f32,f32>,
+                          mut resolve: impl FnMut(&'l Filling<Host>, render::Viewport) -> S) -> Vec<PlannedLayout<S>> {
+        let screen_rect_absolute = render::Rect::<(),()>::zero().down_right_absolute(vp.as_point());
+
+        let mut planned = vec![];
+        for layout in layouts {
+            if layout.bounds(vp).intersection(clip).is_none() {
+                continue;
+            }
+            // viewport of the layout
+            let sub_vp = screen_rect_absolute.get_absolute_rect(layout.dims).get_viewport();
+            let sub_rect_absolute = render::Rect::<(),()>::zero().down_right_absolute(sub_vp.as_point());
+            let mut parts = vec![];
+            for part in layout.parts.iter() {
+                let bounds = part.bounds(sub_vp);
+                // transformed layouts are drawn as a whole
+                if layout.transform.is_none() && bounds.map_into(layout.dims).intersection(clip).is_none() {
+                    continue;
+                }
+                let part_vp = sub_rect_absolute.get_absolute_rect(part.rect).get_viewport();
+                parts.push(PlannedPart {
+                    rect: part.rect,
+                    bounds,
+                    params: part.compositing(),
+                    vp: part_vp,
+                    fill: resolve(&part.filling,part_vp),
+                });
+            }
+            planned.push(PlannedLayout {
+                place: layout.dims.relative_to(clip),
+                params: layout.compositing(),
+                vp: sub_vp,
+                bgc: layout.bgc,
+                parts,
+            });
+        }
+        planned
+    }
+
+    /// Color and weight of a label
+    fn text_style(&self, text: &render::Text) -> (raster_primitive::Color, u16) {
+        text.resolve_style(&*self.styles).map_or(DEFAULT_TEXT_STYLE,|s| (s.color,s.weight))
+    }
+
+    /// Draw a filling into `place` of `target`; `part_vp` is the size of the place in pixels.
+    /// Nested components are rendered right away, one at a time
+    fn draw_part<C: Canvas>(&self, views: &EntityViews, target: &mut C, place: render::Rect<f32,f32>, filling: &Filling<Host>, part_vp: render::Viewport) {
+        match filling {
+            Filling::Component(ind, portal) => {
+                let data = lookup_view(views,ind,portal);
+                target.embed(place,C::nested(data,views,part_vp,*ind,*portal),render::Compositing::default());
+            }
+            Filling::Data(primitive) => target.copy_from(place,&C::raster(primitive)),
+            Filling::Text(text) => {
+                let (color,weight) = self.text_style(text);
+                target.draw_text(place,text,color,weight)
+            }
+            Filling::Shape(shape,paint) => target.draw_shape(place,shape,paint),
+            Filling::Image(image,fit) => target.draw_image(place,&C::raster(image),*fit),
+        }
+    }
+
+    /// Composite the part of the view under `clip` (relative to the view) into a primitive of `clip_vp` size
+    fn composite<C: Canvas>(&self, views: &EntityViews, vp: render::Viewport, clip: render::Rect<f32,f32>, clip_vp: render::Viewport) -> C {
+        let mut primitive_ret = C::blank(clip_vp);
+        // layouts are flattened from the bottom up, each mixed with everything beneath it
+        for layout in self.plan(vp,clip,|filling,_| filling) {
+            let (place,params) = (layout.place,layout.params);
+            let l_primitive = layout.rasterise(|target,place,part_vp,filling| self.draw_part(views,target,place,filling,part_vp));
+            primitive_ret.embed(place,Arc::new(l_primitive),params);
+        }
+        //result
+        primitive_ret
+    }
+
+    /// What a filling shows in a part of `part_vp` size, once composited on `pool`; pixels are shared with the filling
+    fn resolve(&self, views: &EntityViews, filling: &Filling<Host>, part_vp: render::Viewport, pool: &ThreadPool, frames: &mut FramesInFlight) -> Pending {
+        Pending::Ready(match filling {
+            Filling::Component(ind, portal) => {
+                return Pending::Frame(lookup_view(views,ind,portal).visit_parallel(views,part_vp,*ind,*portal,pool,frames));
+            }
+            Filling::Data(primitive) => Draw::Image(primitive.clone(),render::Fit::Stretch),
+            Filling::Text(text) => {
+                let (color,weight) = self.text_style(text);
+                Draw::Text(text.clone(),color,weight)
+            }
+            Filling::Shape(shape,paint) => Draw::Shape(shape.clone(),*paint),
+            Filling::Image(image,fit) => Draw::Image(image.clone(),*fit),
+        })
+    }
 
-    //todo: implement render logic (caching?)
-    fn visit(&self, ctx: Self::Ctx) -> Host::Primitive {
+    /// Like `composite`, but every layout is rasterised on `pool` as soon as the components nested in it are,
+    /// and layouts are mixed together in a task of their own
+    fn composite_parallel(&self, views: &EntityViews, vp: render::Viewport, clip: render::Rect<f32,f32>, clip_vp: render::Viewport,
+                          pool: &ThreadPool, frames: &mut FramesInFlight) -> PendingFrame {
+        let planned = self.plan(vp,clip,|filling,part_vp| self.resolve(views,filling,part_vp,pool,frames));
+        let layouts: Vec<_> = planned.into_iter().map(|layout| {
+            let (place,params) = (layout.place,layout.params);
+            let buffer = pool.spawn_with_handle(async move {
+                let mut parts = Vec::with_capacity(layout.parts.len());
+                for part in layout.parts {
+                    let fill = match part.fill {
+                        Pending::Ready(draw) => draw,
+                        Pending::Frame(frame) => Draw::Image(frame.await,render::Fit::Stretch),
+                    };
+                    parts.push(PlannedPart { rect: part.rect, bounds: part.bounds, params: part.params, vp: part.vp, fill });
+                }
+                PlannedLayout { place, params, vp: layout.vp, bgc: layout.bgc, parts }.rasterise(|target,place,_,fill| draw(target,place,fill))
+            }).expect("failed to spawn compositing");
+            (place,params,buffer)
+        }).collect();
+        pool.spawn_with_handle(async move {
+            let mut primitive_ret = HostPrimitive::blank(clip_vp);
+            // mixed in z-order, whichever layout was ready first
+            for (place,params,buffer) in layouts {
+                primitive_ret.blend_from(place,&buffer.await,params);
+            }
+            Arc::new(primitive_ret)
+        }).expect("failed to spawn compositing").boxed().shared()
+    }
 
-        if let Some(val) = self.view_cache.borrow_mut().get(&ctx.1) {
-            // return Clone of the primitive todo: maybe optimize
-            let mut frame = Host::Primitive::blank(ctx.1);
-            frame.copy_from(Rect::full_box(),val);
-            return frame;
+    /// Like `visit`, with compositing done on `pool`; `(ind,portal)` is the view itself.
+    /// Frames started are noted in `frames`, so that each is made once, and cached after they are all done
+    fn visit_parallel(&self, views: &EntityViews, vp: render::Viewport, ind: usize, portal: usize,
+                      pool: &ThreadPool, frames: &mut FramesInFlight) -> PendingFrame {
+        let generation = self.content_generation(views);
+        if let Some((_,frame)) = frames.iter().find(|(key,_)| *key == (ind,portal,vp,generation)) {
+            return frame.clone();
+        }
+        let ready = |frame: Arc<HostPrimitive>| futures::future::ready(frame).boxed().shared();
+        {
+            let mut cache = self.view_cache.borrow_mut();
+            cache.retain_generation(generation);
+            if let Some(val) = cache.get(&(vp,generation)) {
+                return ready(val.clone());
+            }
+        }
+
+        let damage = match self.pending_damage(views,vp) {
+            Some(damage) => damage,
+            None => {
+                let last = self.last_frame.borrow();
+                return ready(last.as_ref().map(|(_,frame)| frame.clone()).expect("undamaged view has a frame"));
+            }
+        };
+
+        let last = self.last_frame.borrow_mut().take();
+        let frame = match last {
+            Some((_,frame)) if !damage.full => {
+                // stale versions were dropped from the cache, so the frame is usually ours alone
+                let mut frame = Arc::unwrap_or_clone(frame);
+                let mut patches = vec![];
+                for rect in damage.rects {
+                    let pixels = vp.pixel_box(rect);
+                    let clip = vp.relative_box(pixels);
+                    if clip.width() <= 0. || clip.height() <= 0. {
+                        continue;
+                    }
+                    patches.push((pixels,clip,self.composite_parallel(views,vp,clip,pixels.get_viewport(),pool,frames)));
+                }
+                pool.spawn_with_handle(async move {
+                    for (pixels,clip,patch) in patches {
+                        let patch = patch.await;
+                        frame.clear(pixels);
+                        frame.copy_from(clip,&patch);
+                    }
+                    Arc::new(frame)
+                }).expect("failed to spawn compositing").boxed().shared()
+            }
+            _ => self.composite_parallel(views,vp,render::Rect::full_box(),vp,pool,frames),
         };
 
-        let screen_rect = render::Rect::zero()
-            .upper_left_relative(render::Point::relative(0.,0.))
-            .down_right_relative(render::Point::relative(100.,100.));
-        let screen_rect_absolute = render::Rect::zero().down_right_absolute(ctx.vp.as_point());
-        let mut primitive_ret = Host::Primitive::blank(ctx.vp);
-
-        let mut layouts_sorted: Vec<_> = self.layouts.values().map(|(l,i)| (*i,l)).collect();
-        layouts_sorted.sort_unstable_by(|el,el2| el.0.cmp(&el2.0));
-
-        //previous z-index
-        let mut prev_layer = layouts_sorted[0].0;
-        //primitive of current z-index
-        let mut prim = Host::Primitive::blank(ctx.vp);
-        //sanity?
-        let mut layouts_sorted = layouts_sorted.into_iter().peekable();
-
-        for (layer,layout) in layouts_sorted {
-            // if z-index of the current layout is bigger than one from previous iterations
-            // we dump the layer data onto current entity texture and clear the current layers buffer
-            if *layer > prev_layer {
-                prev_layer +=1;
-                primitive_ret.copy_from(screen_rect,&prim);
-                prim = Host::Primitive::blank(ctx.vp);
+        *self.damage.borrow_mut() = Damage::default();
+        self.memo.borrow_mut().damage = None;
+        frames.push(((ind,portal,vp,generation),frame.clone()));
+        frame
+    }
+
+    /// Keep frames made by `visit_parallel`, once they are done
+    fn store_frames(views: &EntityViews, frames: FramesInFlight) {
+        for ((ind,portal,vp,generation),frame) in frames {
+            let frame = futures::executor::block_on(frame);
+            let view = lookup_view(views,&ind,&portal);
+            *view.last_frame.borrow_mut() = Some((vp,frame.clone()));
+            view.view_cache.borrow_mut().insert((vp,generation),frame);
+        }
+    }
+
+    /// What the view draws at `vp`; lists of nested components are spliced in, and reused while they don't change
+    fn display_list(&self, views: &EntityViews, vp: render::Viewport) -> Arc<DisplayList<HostPrimitive>> {
+        let key = (vp,self.content_generation(views));
+        if let Some((built,list)) = &*self.display_list.borrow() {
+            if *built == key {
+                return list.clone();
+            }
+        }
+        let mut list = DisplayList::new(vp);
+
+        for (_,layout) in self.sorted_layouts() {
+            // commands of a layout are relative to `outer`, and never leave it
+            let layered = !layout.compositing().is_plain();
+            let (outer,clip) = if layered {
+                list.begin(layout.dims,None,layout.compositing());
+                (render::Rect::full_box(),None)
+            } else {
+                (layout.dims,Some(layout.dims))
             };
-            // rect for current layout
-            let dest = screen_rect_absolute.get_absolute_rect(layout.dims);
-            // its viewport
-            let sub_vp = dest.get_viewport();
-            // primitive for current layer
-            let mut l_rimitive = Host::Primitive::blank(sub_vp);
-
-            //todo: implement overlapping check
-            for (rect, data) in layout.parts {
-                match data {
+            list.draw(outer,clip,Draw::Fill(layout.bgc));
+            let layout_vp = part_viewport(vp,layout.dims,render::Rect::full_box());
+
+            for part in layout.parts.iter() {
+                // a part never draws outside of its (transformed) rect
+                let bounds = part.bounds(layout_vp).map_into(outer);
+                let Some(bounds) = clip.map_or(Some(bounds),|c| bounds.intersection(c)) else { continue };
+                let params = part.compositing();
+                let (place,clip) = if params.is_plain() {
+                    (part.rect.map_into(outer),Some(bounds))
+                } else {
+                    list.begin(part.rect.map_into(outer),Some(bounds),params);
+                    (render::Rect::full_box(),None)
+                };
+                match &part.filling {
                     Filling::Component(ind, portal) => {
-                        let handle: Option<&ViewData<Host>> = ctx.0.get(&ind).iter().find(|(i,_)| **i == portal).map(|(_,d)|d );
-                        let data = handle.expect("No view data for required index {ind:?}");
-                        //get primitive
-                        let primitive = data.visit((ctx.0,sub_vp));
-                        l_primitive.copy_from(rect,&primitive);
+                        let child = lookup_view(views,ind,portal);
+                        list.append(place,clip,&child.display_list(views,part_viewport(vp,layout.dims,part.rect)));
                     }
                     Filling::Data(primitive) => {
-                        l_rimitive.copy_from(rect,&primitive)
+                        list.draw(place,clip,Draw::Image(primitive.clone(),render::Fit::Stretch))
+                    }
+                    Filling::Text(text) => {
+                        let (color,weight) = text.resolve_style(&*self.styles)
+                            .map_or(DEFAULT_TEXT_STYLE,|s| (s.color,s.weight));
+                        list.draw(place,clip,Draw::Text(text.clone(),color,weight))
+                    }
+                    Filling::Shape(shape,paint) => {
+                        list.draw(place,clip,Draw::Shape(shape.clone(),*paint))
                     }
+                    Filling::Image(image,fit) => {
+                        list.draw(place,clip,Draw::Image(image.clone(),*fit))
+                    }
+                }
+                if !params.is_plain() {
+                    list.end();
                 }
             }
-            if layouts_sorted.peek().is_none() {
-                primitive_ret.copy_from(screen_rect,&prim);
+            if layered {
+                list.end();
             }
+        }
+
+        let list = Arc::new(list);
+        *self.display_list.borrow_mut() = Some((key,list.clone()));
+        list
+    }
+}
 
+impl<'a> types::render::Visitor<'a, HostPrimitive> for ViewData<Host> {
+    type Ctx = (&'a EntityViews,render::Viewport);
+
+    fn visit(&self, ctx: Self::Ctx) -> HostPrimitive {
+        let (views,vp) = ctx;
+        Arc::unwrap_or_clone(self.frame(views,vp))
+    }
+}
+
+impl ViewData<Host> {
+    /// The view rendered at `vp`, shared with its cache
+    fn frame(&self, views: &EntityViews, vp: render::Viewport) -> Arc<HostPrimitive> {
+        let generation = self.content_generation(views);
+        {
+            let mut cache = self.view_cache.borrow_mut();
+            // something nested has changed
+            cache.retain_generation(generation);
+            if let Some(val) = cache.get(&(vp,generation)) {
+                return val.clone();
+            }
+        }
+
+        let damage = match self.pending_damage(views,vp) {
+            Some(damage) => damage,
+            None => {
+                let last = self.last_frame.borrow();
+                return last.as_ref().map(|(_,frame)| frame.clone()).expect("undamaged view has a frame");
+            }
         };
+
+        let last = self.last_frame.borrow_mut().take();
+        let frame = match last {
+            Some((_,frame)) if !damage.full => {
+                // stale versions were dropped from the cache, so the frame is usually ours alone
+                let mut frame = Arc::unwrap_or_clone(frame);
+                // recomposite only damaged rects, the rest stays from the previous frame
+                for rect in damage.rects {
+                    let pixels = vp.pixel_box(rect);
+                    let clip = vp.relative_box(pixels);
+                    if clip.width() <= 0. || clip.height() <= 0. {
+                        continue;
+                    }
+                    let patch = self.composite(views,vp,clip,pixels.get_viewport());
+                    frame.clear(pixels);
+                    frame.copy_from(clip,&patch);
+                }
+                frame
+            }
+            _ => self.composite(views,vp,render::Rect::full_box(),vp),
+        };
+
+        let frame = Arc::new(frame);
+        *self.damage.borrow_mut() = Damage::default();
+        self.memo.borrow_mut().damage = None;
+        *self.last_frame.borrow_mut() = Some((vp,frame.clone()));
+        self.view_cache.borrow_mut().insert((vp,generation),frame.clone());
         //result
-        primitive_ret
+        frame
     }
 }
 
@@ -144,32 +885,56 @@ impl<H: types::traits::Host + 'static> types::traits::View<H> for ViewData<H> {
 
     fn set_layout(&mut self, anc: Anchor, filling: Option<render::Layout<H>>, z_index: render::ZIndex) {
         if self.anchors.iter().find(|&i| i.0 == anc.0).is_some() {
-            if let Some(filling) = filling {
+            if let Some(mut filling) = filling {
+                self.place(&anc,&mut filling);
+                let area = layout_area(&filling);
+                #[cfg(debug_assertions)]
+                report_layout_issues(&anc,&filling,self.layouts.get(&anc).map(|(l,_)| l));
                 //todo: Process the error here properly
-                self.layouts.insert(anc, (filling, z_index.normalize(&mut self.z_index_range).expect("Bad z-index chosen")));
-            } else {
-                self.layouts.remove(&anc);
+                let old = self.layouts.insert(anc, (filling, z_index.normalize(&mut self.z_index_range).expect("Bad z-index chosen")));
+                self.touch(area);
+                if let Some((old,_)) = old {
+                    self.touch(layout_area(&old));
+                }
+            } else if let Some((old,_)) = self.layouts.remove(&anc) {
+                self.touch(layout_area(&old));
             }
         }
         //Do nothing if smth. tries to fill non existent anchor
     }
 
+    /// Overlays don't change the frame of the view, they are drawn anew over every frame of the root
+    fn set_overlay(&mut self, origin: Anchor, overlay: Option<render::Layout<H>>, layer: isize) {
+        let known = self.anchors.iter().any(|a| a.0 == origin.0) || self.layouts.keys().any(|a| a.0 == origin.0);
+        match overlay {
+            Some(layout) if known => {
+                self.overlays.insert(origin,overlay::Overlay { rect: layout.dims, layer, layout });
+            }
+            _ => {
+                self.overlays.remove(&origin);
+            }
+        }
+    }
+
     fn viewport(&self) -> Viewport {
         self.vp
     }
 
     fn get_style_table(&self) -> &dyn StyleTable<H> {
-        &self.styles as &dyn StyleTable<H>
+        &*self.styles
     }
 
     fn get_style_table_mut(&mut self) -> &mut dyn StyleTable<H> {
-        &mut self.styles as &mut dyn StyleTable<H>
+        // any style may change, so everything is to be redrawn
+        self.touch(None);
+        &mut *self.styles
     }
 }
 
 /// the functions to interact with systems in type erased setting
 struct ProcessingFunctionsEntity {
-    event_dispatch: Box<dyn for<'s> Fn(&'s <Host as types::traits::Host>::Event, &'s mut typemap::TypeMap)>,
+    event_dispatch: EventDispatch,
+    pointer_dispatch: PointerListeners,
     // poll_fn: Box<dyn for<'s> Fn(&'s mut typemap::TypeMap)>,
 }
 
@@ -198,13 +963,16 @@ impl<S: System<Host>> EntityData<S> {
     }
 }
 
+/// A future of a message for an entity
+type PendingMessage<M> = (usize, Pin<Box<dyn Future<Output=M>>>);
+
 pub struct SystemData<S: System<Host>> {
     state: S::State,
-    future_handles: Vec<(usize, Pin<Box<dyn Future<Output=S::Message>>>)>,
+    future_handles: Vec<PendingMessage<S::Message>>,
 }
 
 /// This does nothing
-static R_W_VTable: RawWakerVTable = RawWakerVTable::new(
+static R_W_VTABLE: RawWakerVTable = RawWakerVTable::new(
     |waker_ptr| unsafe { core::ptr::read(waker_ptr as *const RawWaker) },
     |_p| {},
     |_p| {},
@@ -215,30 +983,28 @@ static R_W_VTable: RawWakerVTable = RawWakerVTable::new(
 impl<S: System<Host>> SystemData<S> {
     fn poll(&mut self, host: &mut EntityStorage) {
         //init
-        let mut rw: RawWaker = RawWaker::new(core::ptr::null(), &R_W_VTable);
+        let mut rw: RawWaker = RawWaker::new(core::ptr::null(), &R_W_VTABLE);
         //patch in correct reference
-        rw = RawWaker::new(&rw as *const _ as *const (), &R_W_VTable);
+        rw = RawWaker::new(&rw as *const _ as *const (), &R_W_VTABLE);
 
         // SAFETY the only usage of this waker is `wake`, which does nothing.
         // todo: revisit it later (20.03.22)
         let waker = unsafe { Waker::from_raw(rw) };
 
-        for (to, fut) in self.future_handles.iter_mut() {
+        // finished futures are dropped, they must not be polled again
+        self.future_handles.retain_mut(|(to, fut)| {
             match fut.as_mut().poll(&mut core::task::Context::from_waker(&waker)) {
                 Poll::Ready(msg) => {
-                    match host.entry(*to) {
-                        BEntry::Occupied(mut e) => {
-                            match e.get_mut().0.entry::<EntityHolder<S>>() {
-                                Entry::Occupied(mut e) => e.get_mut().push(msg),
-                                Entry::Vacant(_) => continue,
-                            }
+                    if let BEntry::Occupied(mut e) = host.entry(*to) {
+                        if let Entry::Occupied(mut e) = e.get_mut().0.entry::<EntityHolder<S>>() {
+                            e.get_mut().push(msg);
                         }
-                        BEntry::Vacant(_) => continue,
                     }
+                    false
                 }
-                Poll::Pending => continue,
+                Poll::Pending => true,
             }
-        }
+        });
     }
 }
 
@@ -257,10 +1023,18 @@ impl<S: System<Host> + Any> typemap::Key for EntityHolder<S>
     type Value = EntityData<S>;
 }
 
+impl Default for Host {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Host {
     pub fn new() -> Self {
+        // compositing scales with the number of cores
+        let threads = std::thread::available_parallelism().map_or(4,|n| n.get());
         let runtime = futures::executor::ThreadPoolBuilder::new()
-            .pool_size(4)
+            .pool_size(threads)
             .create().expect("failed to create a thread pool");
         Self {
             ids: BTreeMap::new(),
@@ -272,8 +1046,81 @@ impl Host {
             future_delivery: HashMap::new(),
             views: Default::default(),
             runtime,
+            assets: AssetServer::new("assets"),
+            scale_factors: HashMap::new(),
+            render_mode: RenderMode::default(),
+            animations: Animations::default(),
+            clock: FrameClock::default(),
+            placed_overlays: HashMap::new(),
+            cursors: HashMap::new(),
+            hovered: HashMap::new(),
         }
     }
+
+    pub fn assets(&self) -> &AssetServer {
+        &self.assets
+    }
+
+    /// Directory asset paths are resolved against, `assets` by default
+    pub fn set_asset_root<P: Into<PathBuf>>(&mut self, root: P) {
+        self.assets.set_root(root);
+    }
+
+    pub fn set_render_mode(&mut self, mode: RenderMode) {
+        self.render_mode = mode;
+    }
+
+    /// Replace the wall clock, e.g. with a `ManualClock` in tests; the host's time goes on from where it is
+    pub fn set_clock<C: Clock + 'static>(&mut self, clock: C) {
+        self.clock.set_clock(Box::new(clock));
+    }
+
+    /// `Timestep::Variable` by default
+    pub fn set_timestep(&mut self, timestep: Timestep) {
+        self.clock.set_timestep(timestep);
+    }
+
+    /// Record root portal `screen_idx` rendered at `vp`, nested components become groups naming their entity.
+    /// Serialize the result with `to_svg`
+    pub fn render_svg(&mut self, screen_idx: usize, vp: render::Viewport) -> svg_primitive::Primitive {
+        let (root,placed) = self.prepare_root(screen_idx,vp);
+        let view = lookup_view(&self.data_view,&root,&screen_idx);
+        let mut frame = Arc::unwrap_or_clone(Canvas::nested(view,&self.data_view,vp,root,screen_idx));
+        overlay::draw(&self.data_view,&placed,vp,&mut frame);
+        self.placed_overlays.insert(screen_idx,placed);
+        frame
+    }
+
+    /// Get root portal `screen_idx` ready to be rendered at `vp`, returns the root entity and the overlays placed in it
+    fn prepare_root(&mut self, screen_idx: usize, vp: render::Viewport) -> (usize,Vec<overlay::Placed>) {
+        let root = self.root.expect("No root entity set before render");
+        // components may have been nested since the last frame
+        if let Some(scale) = self.scale_factors.get(&screen_idx) {
+            propagate_scale(&mut self.data_view,root,screen_idx,*scale);
+        }
+        // nested components may have changed since the last render
+        for (_,view) in self.data_view.values_mut().flatten() {
+            *view.memo.get_mut() = Memo::default();
+        }
+        // the root portal takes the size of its window, nested views that of their parts
+        propagate_size(&mut self.data_view,root,screen_idx,vp);
+        // overlays are placed by their solved origins, then what they show is sized by where they went
+        let placed = overlay::place(&mut self.data_view,root,screen_idx);
+        if !placed.is_empty() {
+            propagate_size(&mut self.data_view,root,screen_idx,vp);
+        }
+        (root,placed)
+    }
+
+    pub(crate) fn load_asset<A: Asset, F, S: System<Self>>(&mut self, path: &Path, f: F, whom: usize) -> Handle<A>
+        where F: FnOnce(Result<Handle<A>, AssetError>) -> S::Message + 'static, Self: Hosts<S>
+    {
+        let (handle, loaded) = self.assets.load::<A>(&self.runtime, path);
+        let delivered = handle.clone();
+        self.spawn_fut::<_, _, _, S>(loaded, move |res| f(res.map(|_| delivered)), whom);
+        handle
+    }
+
     pub(crate) fn spawn_fut<T: 'static + Send, F: FnOnce(T) -> S::Message + 'static, Fut: Future<Output=T> + Send + 'static, S: System<Self>>(&mut self, fut: Fut, f: F, whom: usize) -> bool
         where Self: Hosts<S>,
     {
@@ -287,7 +1134,7 @@ impl Host {
         };
         match self.future_delivery.entry(TypeId::of::<S>()) {
             HEntry::Occupied(_) => {}
-            HEntry::Vacant(mut e) => {
+            HEntry::Vacant(e) => {
                 //insert the processing function
                 e.insert(Arc::new(clo));
             }
@@ -305,14 +1152,14 @@ impl Host {
 
     pub(crate) fn with_entity_data<S: System<Self>, T, F: FnOnce(&mut EntityData<S>) -> T>(&mut self, which: usize, f: F) -> Option<T> where Self: Hosts<S>
     {
-        self.data.get_mut(&which).map(|(m, _)| match m.entry::<EntityHolder<S>>() {
+        self.data.get_mut(&which).and_then(|(m, _)| match m.entry::<EntityHolder<S>>() {
             Entry::Occupied(mut e) => {
                 Some(f(e.get_mut()))
             }
             _ => {
                 None
             }
-        }).flatten()
+        })
     }
     pub(crate) fn with_system_data<S: System<Self>, T, F: FnOnce(&mut SystemData<S>) -> T>(&mut self, f: F) -> Option<T> where Self: Hosts<S> {
         match self.states.entry::<SystemHolder<S>>() {
@@ -322,6 +1169,7 @@ impl Host {
             Entry::Vacant(_) => None,
         }
     }
+    #[allow(dead_code)]
     pub(crate) fn with_system_and_entity_data<S: System<Host>, T, F: FnOnce(&mut SystemData<S>, &mut EntityData<S>) -> T>(&mut self, which: usize, f: F) -> Option<T> where Self: Hosts<S> {
         let (data, states) = (&mut self.data, &mut self.states);
         match (data.get_mut(&which), states) {
@@ -340,44 +1188,14 @@ impl Host {
     }
 }
 
-//todo: remove
-mod stub {
-    use types::render::Rect;
-
-    #[derive(Clone, Copy)]
-    pub struct Color;
-
-    pub struct Primitive;
-
-    impl super::render::Primitive for Primitive {
-        type Color = Color;
-
-        fn copy_from(&mut self, place: Rect<f32,f32>, src: &Self) {
-            unimplemented!()
-        }
-
-        fn cut(&self, part: Rect) -> Self {
-            unimplemented!()
-        }
-
-        fn resize(&self, scale: (f32, f32)) -> Self {
-            unimplemented!()
-        }
-
-        fn blank(size: super::render::Viewport) -> Self {
-            unimplemented!()
-        }
-    }
-}
-
 impl types::traits::Host for Host {
     type Index = usize;
 
-    type Event = winit::event::WindowEvent<'static>;
+    type Event = types::app::Event;
 
     type EntityData = ViewData<Host>;
-    //todo: implement
-    type Primitive = stub::Primitive;
+
+    type Primitive = raster_primitive::Primitive;
 
     fn allocate_entity(&mut self) -> Result<Self::Index, types::errors::traits::AllocError> {
         const HALFWORD: u8 = (usize::BITS / 2) as u8;
@@ -392,7 +1210,7 @@ impl types::traits::Host for Host {
                     None => unreachable!(),
                     Some(idx) => {
                         let bit = v.set(idx, false);
-                        assert_eq!(bit, true);
+                        assert!(bit);
                         //idx here is the position in bitmap.
                         //res = half a word bits of `k` left and half a word bits of idx right
                         res = Some((((*k as usize & !MASK) << HALFWORD) & !MASK) | (idx & MASK));
@@ -404,7 +1222,8 @@ impl types::traits::Host for Host {
         res.ok_or(AllocError)
     }
 
-    fn set_entity_data(&mut self, which: Self::Index, data: ViewData<Self>, portal: usize) {
+    fn set_entity_data(&mut self, which: Self::Index, data: impl Into<ViewData<Self>>, portal: usize) {
+        let data = data.into();
         match self.data_view.entry(which) {
             BEntry::Occupied(mut e) => {
                 let e = e.get_mut();
@@ -414,7 +1233,7 @@ impl types::traits::Host for Host {
                     e.push((portal, data));
                 }
             }
-            BEntry::Vacant(mut e) => {
+            BEntry::Vacant(e) => {
                 e.insert(vec![(portal, data)]);
             }
         }
@@ -435,45 +1254,86 @@ impl types::traits::Host for Host {
             BEntry::Occupied(mut e) => {
                 let bm = e.get_mut();
                 bm.set(right, false);
-                /// clean up
+                // clean up
                 self.data.remove(&which);
                 self.data_view.remove(&which);
+                self.animations.drop_entity(which);
+                self.clock.drop_entity(which);
             }
         }
     }
 
+    fn get_root_entity(&self) -> Option<Self::Index> {
+        self.root
+    }
+
     fn get_root_portal_count(&self) -> usize {
         let root = self.root.unwrap();
         self.data_view[&root].len()
     }
 
-    fn render(&mut self, screen_idx: usize,vp: render::Viewport, by: impl FnOnce(Self::Primitive)) {
-        let view: &[(usize,ViewData<_>)] = &self.data_view[self.root.expect("No root entity set before render")];
-        let view = &view.iter().find(|(idx,_)| idx == screen_idx).expect("No such portal of root entity").1;
-
-        let mut primitive = Self::Primitive::blank();
-        let mut ctx = (&mut self.data_view,vp);
-        let sc = Visitor::visit(view,ctx);
-        let copy_rect = render::Rect::zero()
-            .upper_left_relative(render::Point::relative(0.0,0.0))
-            .down_right_relative(render::Point::relative(100.0,100.0));
+    fn set_scale_factor(&mut self, screen_idx: usize, scale: render::ScaleFactor) {
+        self.scale_factors.insert(screen_idx,scale);
+        // views see it from the next update round on, components nested later get it when rendered
+        if let Some(root) = self.root {
+            propagate_scale(&mut self.data_view,root,screen_idx,scale);
+        }
+    }
 
-        pritimive.copy_from(copy_rect,&sc);
-        by(primitive);
+    fn render(&mut self, screen_idx: usize,vp: render::Viewport, by: impl FnOnce(Self::Primitive)) {
+        let (root,placed) = self.prepare_root(screen_idx,vp);
+        let views = &self.data_view;
+        let view: &[(usize,ViewData<_>)] = &views[&root];
+        let view = &view.iter().find(|(idx,_)| *idx == screen_idx).expect("No such portal of root entity").1;
+
+        let mut frame = match self.render_mode {
+            // the frame stays cached, overlays are drawn over a copy
+            RenderMode::Immediate => Arc::unwrap_or_clone(view.frame(views,vp)),
+            RenderMode::Retained => {
+                let mut frame = HostPrimitive::blank(vp);
+                view.display_list(views,vp).replay(&mut frame);
+                frame
+            }
+            RenderMode::Parallel => {
+                let mut frames = vec![];
+                let frame = view.visit_parallel(views,vp,root,screen_idx,&self.runtime,&mut frames);
+                let frame = futures::executor::block_on(frame);
+                ViewData::store_frames(views,frames);
+                Arc::unwrap_or_clone(frame)
+            }
+        };
+        // overlays go over everything, they aren't part of any cached frame
+        overlay::draw(views,&placed,vp,&mut frame);
+        self.placed_overlays.insert(screen_idx,placed);
+        by(frame)
     }
 
-    //TODO: think of dispatch between currently rendered components
-    fn receive_events(&mut self, events: impl Iterator<Item = Self::Event>) {
-        for (_, (tm, f)) in self.data.iter_mut() {
-            let mut f = |ev| (f.event_dispatch)(ev, tm);
-            for ev in events.iter() {
-                // here must go filter for mouse events
-                f(ev);
+    /// Pointer events are routed to the entities under the pointer, as last rendered; the rest go to every entity
+    fn receive_events<'a>(&'a mut self, events: impl Iterator<Item = &'a Self::Event>) {
+        for ev in events {
+            match self.pointer_position(ev) {
+                Some(Some(at)) => self.route_pointer(ev, at),
+                // nothing is under a pointer outside of the window, but what it was over learns that it left
+                Some(None) => self.leave_pointer(ev),
+                None => {
+                    for (_, (tm, f)) in self.data.iter_mut() {
+                        (f.event_dispatch)(ev, tm);
+                    }
+                }
             }
         }
     }
 
     fn update_round(&mut self) {
+        let ticks = self.clock.advance();
+        self.advance_animations(self.clock.now());
+        // ticks become messages handled in this round
+        let subscribers = self.clock.subscribers();
+        for tick in ticks {
+            for deliver in &subscribers {
+                deliver(self, tick);
+            }
+        }
         let reducers: Vec<_> = self.msg_reducers.values().cloned().collect();
         for red in reducers {
             red(self)
@@ -486,18 +1346,25 @@ impl types::traits::Host for Host {
         for viewer in views {
             viewer(&mut self.data_view,&mut self.data)
         }
+        self.assets.purge();
     }
 }
 
 impl<S: types::traits::System<Self>> Hosts<S> for Host
 {
     fn get_state(&mut self, which: Self::Index) -> Option<&mut S> {
-        self.data.get_mut(&which).map(|(tm, _)| {
+        self.data.get_mut(&which).and_then(|(tm, _)| {
             tm.get_mut::<EntityHolder<S>>().map(|data| &mut data.data)
-        }).flatten()
+        })
     }
     fn subscribe(&mut self, who: Self::Index, with: <S as System<Self>>::Props) {
-        let view_function = move |view: &mut EntityViews,storage: &EntityStorage|{
+        // the global state of a system is made when its first component is
+        if !self.states.contains::<SystemHolder<S>>() {
+            let mut state = S::State::init();
+            state.register(self);
+            self.states.insert::<SystemHolder<S>>(SystemData { state, future_handles: vec![] });
+        }
+        let view_function = move |view: &mut EntityViews,storage: &mut EntityStorage|{
             let view = view.get_mut(&who).expect("ill-fromed entity data");
 
             struct Renderer<'v>(&'v mut ViewData<Host>);
@@ -511,10 +1378,14 @@ impl<S: types::traits::System<Self>> Hosts<S> for Host
                     //trasition logic for anchors
                     // the point is, if anchors of a entity are already attached, we simply don't show them as available to the rest of components, and vise versa
                     match layout {
-                        Some(layout) => {
-                            if let Some(a) = self.0.anchors.iter().enumerate().find(|(a_,a)| a.0 == label.0).map(|(i,_)| i) {
+                        Some(mut layout) => {
+                            self.0.place(&label,&mut layout);
+                            #[cfg(debug_assertions)]
+                            report_layout_issues(&label,&layout,self.0.layouts.get(&label).map(|(l,_)| l));
+                            if let Some(a) = self.0.anchors.iter().enumerate().find(|(_,a)| a.0 == label.0).map(|(i,_)| i) {
                                 let anch = self.0.anchors.swap_remove(a); //should not panic
                                 //todo: finish
+                                self.0.touch(layout_area(&layout));
                                 let it = self.0.layouts.insert(anch,(layout,z_index.normalize(&mut self.0.z_index_range).expect("Msg")));
                                 assert!(it.is_none(),"calling setting of an already set anchors")
                             }
@@ -523,7 +1394,10 @@ impl<S: types::traits::System<Self>> Hosts<S> for Host
                             match self.0.anchors.iter().find(|i| i.0 == label.0) {
                                 None => {
                                     let i = self.0.layouts.remove(&label);
-                                    assert!(i.is_some(),"calling cleaning of un existent anchor")
+                                    assert!(i.is_some(),"calling cleaning of un existent anchor");
+                                    if let Some((old,_)) = i {
+                                        self.0.touch(layout_area(&old));
+                                    }
                                 }
                                 Some(_) => unreachable!(),
                             }
@@ -531,18 +1405,28 @@ impl<S: types::traits::System<Self>> Hosts<S> for Host
                     }
                 }
 
+                fn overlay(&mut self, overlay: Option<Layout<Host>>, origin: Anchor, layer: isize) {
+                    self.0.set_overlay(origin,overlay,layer)
+                }
+
                 fn styles(&self) -> &dyn StyleTable<Host> {
                     self.0.get_style_table()
                 }
 
+                fn scale_factor(&self) -> render::ScaleFactor {
+                    self.0.scale
+                }
+
                 fn patch_style_scope(&mut self, patch: &mut dyn FnMut(&mut dyn StyleTable<Host>)) {
-                    let st_table = self.0.styles.scope();
-                    patch(&mut self.0.styles);
+                    let mut st_table = self.0.styles.scope(&[]);
+                    patch(&mut *st_table);
                     self.0.styles = st_table;
+                    self.0.touch(None);
                 }
             }
 
             for (idx,vd) in view {
+                let vp = vd.vp;
                 let mut renderer = Renderer(vd);
                 match storage.get(&who) {
                     None => {}
@@ -550,7 +1434,7 @@ impl<S: types::traits::System<Self>> Hosts<S> for Host
                         match tm.get::<EntityHolder<S>>() {
                             None => {}
                             Some(sd) => {
-                                S::view(&sd.data,&mut renderer,vd.vp,*idx);
+                                S::view(&sd.data,&mut renderer,vp,*idx);
                             }
                         }
                     }
@@ -559,7 +1443,7 @@ impl<S: types::traits::System<Self>> Hosts<S> for Host
         };
 
         match self.views.entry(who) {
-            BEntry::Vacant(mut e) => {
+            BEntry::Vacant(e) => {
                 let vf = Arc::new(view_function);
                 e.insert(vf);
             }
@@ -575,7 +1459,7 @@ impl<S: types::traits::System<Self>> Hosts<S> for Host
                         BEntry::Occupied(mut e) => {
                             let (e, _) = e.get_mut();
                             match e.entry::<EntityHolder<S>>() {
-                                Entry::Occupied(mut ent) => {
+                                Entry::Occupied(ent) => {
                                     // take out of host our reduced data
                                     Some(ent.remove())
                                 }
@@ -607,7 +1491,7 @@ impl<S: types::traits::System<Self>> Hosts<S> for Host
                                     unreachable!();
                                 }
                                 // and place our entry back
-                                Entry::Vacant(mut e) => {
+                                Entry::Vacant(e) => {
                                     std::mem::swap(&mut e_data.messages, msgs.downcast_mut::<Vec<S::Message>>().unwrap());
                                     e.insert(e_data);
                                 }
@@ -622,27 +1506,28 @@ impl<S: types::traits::System<Self>> Hosts<S> for Host
         //here we accumulated a reducer for subscribers system.
         match self.msg_reducers.entry(TypeId::of::<S>()) {
             HEntry::Occupied(_) => {}
-            HEntry::Vacant(mut e) => {
+            HEntry::Vacant(e) => {
                 e.insert(std::sync::Arc::new(reducer));
             }
         }
 
         match self.data.entry(who) {
-            BEntry::Vacant(mut e) => {
-                let component = EntityData { data: S::changed(None, &with).unwrap(), messages: vec![] };
+            BEntry::Vacant(e) => {
+                let component = EntityData::new(S::init(&with));
                 let mut tm = typemap::TypeMap::new();
                 tm.insert::<EntityHolder<S>>(component);
-                e.insert((tm,ProcessingFunctionsEntity{ event_dispatch: Box::new(|_,_|{}) }));
+                e.insert((tm,ProcessingFunctionsEntity{ event_dispatch: Box::new(|_,_|{}), pointer_dispatch: vec![] }));
             }
             BEntry::Occupied(mut e) => {
-                let (mut tm,_) = e.get_mut();
+                let (tm,_) = e.get_mut();
                 match tm.remove::<EntityHolder<S>>() {
+                    // the component starts over with the new props, messages queued for it are kept
                     Some(mut ed) => {
-                        S::changed(Some(&mut ed.data),&with).unwrap();
+                        ed.data = S::init(&with);
                         tm.insert::<EntityHolder<S>>(ed);
                     },
                     None => {
-                        let component = EntityData { data: S::changed(None, &with).unwrap(), messages: vec![] };
+                        let component = EntityData::new(S::init(&with));
                         tm.insert::<EntityHolder<S>>(component);
                     }
                 }
@@ -654,12 +1539,13 @@ impl<S: types::traits::System<Self>> Hosts<S> for Host
 
     fn unsubscribe(&mut self, who: Self::Index) {
         use std::collections::btree_map::Entry::*;
-        match self.data.entry(who) {
-            Occupied(c) => {
-                c.remove();
-            }
-            _ => {}
-        };
+        if let Occupied(c) = self.data.entry(who) {
+            c.remove();
+        }
+    }
+
+    fn send(&mut self, msg: S::Message, whom: Self::Index) {
+        self.with_entity_data::<S, (), _>(whom, |x| { x.messages.push(msg); });
     }
 }
 
@@ -691,7 +1577,7 @@ impl<'h> types::traits::Context<'h, Host> for HostCtx<'h> {
 
     fn subscribe<S: System<Host>>(&mut self, filter: fn(&<Host as types::traits::Host>::Event) -> Option<<S as System<Host>>::Message>) where Host: Hosts<S> {
         let index = self.cur_index;
-        let reducer = move |ev: &winit::event::WindowEvent<'static>, e_data: &mut typemap::TypeMap| -> () {
+        let reducer = move |ev: &types::app::Event, e_data: &mut typemap::TypeMap| -> () {
             if let Some(m) = filter(ev) {
                 match e_data.entry::<EntityHolder<S>>() {
                     Entry::Occupied(mut e) => {
@@ -710,6 +1596,22 @@ impl<'h> types::traits::Context<'h, Host> for HostCtx<'h> {
         }
     }
 
+    fn subscribe_pointer<S: System<Host>>(&mut self, phase: Phase, listener: fn(&types::app::Event, &types::pointer::Target<Host>) -> Propagation<S::Message>) where Host: Hosts<S> {
+        let reducer = move |ev: &types::app::Event, target: &types::pointer::Target<Host>, e_data: &mut typemap::TypeMap| -> bool {
+            let propagation = listener(ev, target);
+            let stopped = propagation.is_stopped();
+            if let (Some(m), Entry::Occupied(mut e)) = (propagation.into_message(), e_data.entry::<EntityHolder<S>>()) {
+                e.get_mut().push(m);
+            }
+            stopped
+        };
+        if let Some((_, f)) = self.host.data.get_mut(&self.cur_index) {
+            let key = (TypeId::of::<S>(), phase);
+            f.pointer_dispatch.retain(|(k, _)| *k != key);
+            f.pointer_dispatch.push((key, Box::new(reducer)));
+        }
+    }
+
     fn spawn<T: 'static + Send, F, Fut, S: System<Host>>(&mut self, fut: Fut, f: F, whom: usize) -> bool
         where Fut: Future<Output=T> + Send + 'static, F: FnOnce(T) -> S::Message + 'static, Host: Hosts<S>
     {
@@ -721,5 +1623,36 @@ impl<'h> types::traits::Context<'h, Host> for HostCtx<'h> {
             f(&mut s.state)
         })
     }
+
+    fn load<A: Asset, F, S: System<Host>>(&mut self, path: impl AsRef<Path>, f: F) -> Handle<A>
+        where F: FnOnce(Result<Handle<A>, AssetError>) -> S::Message + 'static, Host: Hosts<S>
+    {
+        let whom = self.cur_index;
+        self.host.load_asset::<A, F, S>(path.as_ref(), f, whom)
+    }
+
+    fn animate<S: System<Host>>(&mut self, animation: impl Into<Animation<Host>>, done: Option<S::Message>) -> AnimationId where Host: Hosts<S> {
+        let whom = self.cur_index;
+        let done = done.map(|msg| Box::new(move |host: &mut Host| Hosts::<S>::send(host, msg, whom)) as animation::Done);
+        let now = self.host.clock.now();
+        self.host.animations.start(whom, animation.into(), now, done)
+    }
+
+    fn cancel_animation(&mut self, id: AnimationId) -> bool {
+        self.host.animations.cancel(id)
+    }
+
+    fn now(&mut self) -> std::time::Duration {
+        self.host.clock.now()
+    }
+
+    fn subscribe_ticks<S: System<Host>>(&mut self, f: fn(Tick) -> S::Message) where Host: Hosts<S> {
+        let whom = self.cur_index;
+        self.host.clock.subscribe(whom, TypeId::of::<S>(), Arc::new(move |host: &mut Host, tick| Hosts::<S>::send(host, f(tick), whom)));
+    }
+
+    fn unsubscribe_ticks<S: System<Host>>(&mut self) where Host: Hosts<S> {
+        self.host.clock.unsubscribe(self.cur_index, TypeId::of::<S>());
+    }
 }
 
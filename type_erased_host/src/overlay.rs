@@ -0,0 +1,96 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use types::render::{Anchor, Filling, Layout, Rect, Viewport};
+
+use crate::{lookup_view, Canvas, EntityViews};
+
+/// A layout teleported out of its view
+pub(crate) struct Overlay<H: types::traits::Host> {
+    /// relative to the layout at the origin anchor
+    pub(crate) rect: Rect<f32,f32>,
+    pub(crate) layer: isize,
+    /// dims are relative to the root portal it was last placed in
+    pub(crate) layout: Layout<H>,
+}
+
+/// Overlays of a view, by their origin anchor
+pub(crate) type Overlays<H> = HashMap<Anchor,Overlay<H>>;
+
+/// A component on the way from a root portal to an overlay's owner: the view, the anchor and part showing the next
+/// component, the area of the view relative to the root, and whether the anchor is the origin of an overlay
+pub(crate) type Step = (usize,usize,Anchor,usize,Rect<f32,f32>,bool);
+
+/// An overlay placed in a root portal
+pub(crate) struct Placed {
+    /// `(entity,portal,origin)` of the overlay
+    pub(crate) key: (usize,usize,Anchor),
+    /// components the owner is nested in, the root first
+    pub(crate) path: Vec<Step>,
+    /// area of the owner's view, relative to the root
+    pub(crate) area: Rect<f32,f32>,
+}
+
+/// Position overlays of everything shown in portal `(root,portal)`, components nested in overlays included;
+/// returns them in the order they are drawn: by layer, then by the order their views are drawn in.
+/// Transforms of layouts and parts are ignored, an overlay is placed as if its origin wasn't transformed
+pub(crate) fn place(views: &mut EntityViews, root: usize, portal: usize) -> Vec<Placed> {
+    if views.values().flatten().all(|(_,view)| view.overlays.is_empty()) {
+        return vec![];
+    }
+    let mut found = vec![];
+    // views with where they are, relative to the root
+    let mut stack = vec![(root,portal,Rect::full_box(),vec![])];
+    let mut seen = HashSet::new();
+    while let Some((ind,portal,area,path)) = stack.pop() {
+        if !seen.insert((ind,portal)) {
+            continue;
+        }
+        let Some((_,view)) = views.get(&ind).and_then(|v| v.iter().find(|(i,_)| *i == portal)) else { continue };
+        for (origin,overlay) in view.overlays.iter() {
+            let Some((_,(layout,_))) = view.layouts.iter().find(|(a,_)| a.0 == origin.0) else { continue };
+            let placed = Placed { key: (ind,portal,origin.clone()), path: path.clone(), area };
+            let rect = overlay.rect.map_into(layout.dims).map_into(area);
+            found.push((overlay.layer,placed,rect));
+            // drawn over everything, so popped after the rest of the view
+            for (i,part) in overlay.layout.parts.iter().enumerate().rev() {
+                if let Filling::Component(child,child_portal) = part.filling {
+                    let mut path = path.clone();
+                    path.push((ind,portal,origin.clone(),i,area,true));
+                    stack.push((child,child_portal,part.rect.map_into(rect),path));
+                }
+            }
+        }
+        // pushed top down, so they are popped in the order they are drawn
+        for (anchor,layout) in view.sorted_layouts().into_iter().rev() {
+            for (i,part) in layout.parts.iter().enumerate().rev() {
+                if let Filling::Component(child,child_portal) = part.filling {
+                    let mut path = path.clone();
+                    path.push((ind,portal,anchor.clone(),i,area,false));
+                    stack.push((child,child_portal,part.rect.map_into(layout.dims).map_into(area),path));
+                }
+            }
+        }
+    }
+    found.sort_by_key(|(layer,..)| *layer);
+    found.into_iter().map(|(_,placed,rect)| {
+        let (ind,portal,origin) = &placed.key;
+        if let Some(overlay) = views.get_mut(ind).and_then(|v| v.iter_mut().find(|(i,_)| i == portal)).and_then(|(_,v)| v.overlays.get_mut(origin)) {
+            overlay.layout.dims = rect;
+        }
+        placed
+    }).collect()
+}
+
+/// Draw placed overlays over a `vp` sized frame of the root portal
+pub(crate) fn draw<C: Canvas>(views: &EntityViews, placed: &[Placed], vp: Viewport, frame: &mut C) {
+    for Placed { key: (ind,portal,origin), .. } in placed {
+        let view = lookup_view(views,ind,portal);
+        let Some(overlay) = view.overlays.get(origin) else { continue };
+        for layout in view.plan_layouts([&overlay.layout],vp,Rect::full_box(),|filling,_| filling) {
+            let (place,params) = (layout.place,layout.params);
+            let l_primitive = layout.rasterise(|target,place,part_vp,filling| view.draw_part(views,target,place,filling,part_vp));
+            frame.embed(place,Arc::new(l_primitive),params);
+        }
+    }
+}
@@ -0,0 +1,85 @@
+use std::any::TypeId;
+
+use types::app::Event;
+use types::pointer::{Phase, Target};
+use types::render::Point;
+use winit::event::WindowEvent;
+
+use crate::{Hit, Host};
+
+/// Turns a pointer event into a message for its entity, returns whether propagation is stopped
+pub(crate) type PointerListener = Box<dyn for<'s> Fn(&'s Event, &'s Target<Host>, &'s mut typemap::TypeMap) -> bool>;
+
+/// Pointer listeners of an entity, by system and phase
+pub(crate) type PointerListeners = Vec<((TypeId,Phase),PointerListener)>;
+
+/// A position in physical pixels of a window, `None` if it's outside
+fn pixel(x: f64, y: f64) -> Option<Point<u32>> {
+    (x >= 0. && y >= 0.).then(|| Point::absolute(x as u32,y as u32))
+}
+
+impl Host {
+    /// Where a pointer event points to: `None` if it's not a pointer event,
+    /// `Some(None)` if it is but the pointer isn't over its window. Cursor positions are tracked here
+    pub(crate) fn pointer_position(&mut self, ev: &Event) -> Option<Option<Point<u32>>> {
+        match &ev.event {
+            WindowEvent::CursorMoved { position, .. } => {
+                let at = pixel(position.x,position.y);
+                match at {
+                    Some(at) => self.cursors.insert(ev.window,at),
+                    None => self.cursors.remove(&ev.window),
+                };
+                Some(at)
+            }
+            WindowEvent::CursorLeft { .. } => {
+                self.cursors.remove(&ev.window);
+                Some(None)
+            }
+            WindowEvent::MouseInput { .. } | WindowEvent::MouseWheel { .. } => Some(self.cursors.get(&ev.window).copied()),
+            WindowEvent::Touch(touch) => Some(pixel(touch.location.x,touch.location.y)),
+            _ => None,
+        }
+    }
+
+    /// Deliver a pointer event to the entities under `at`: capture listeners from the root down, then bubble listeners
+    /// and event filters back up. Listeners of an entity all see the event, even if one of them stops it
+    pub(crate) fn route_pointer(&mut self, ev: &Event, at: Point<u32>) {
+        let hits = self.hit_test(ev.window,at);
+        self.route(ev,&hits);
+        if let WindowEvent::CursorMoved { .. } = ev.event {
+            self.hovered.insert(ev.window,hits);
+        }
+    }
+
+    /// Deliver a pointer event happening outside of its window: the cursor leaving it, or moving out of it,
+    /// goes to the entities it was last moved over, as if it was still there; other events go nowhere
+    pub(crate) fn leave_pointer(&mut self, ev: &Event) {
+        if let WindowEvent::CursorLeft { .. } | WindowEvent::CursorMoved { .. } = ev.event {
+            if let Some(hits) = self.hovered.remove(&ev.window) {
+                self.route(ev,&hits);
+            }
+        }
+    }
+
+    fn route(&mut self, ev: &Event, hits: &[Hit]) {
+        let Some(innermost) = hits.last().map(|h| h.entity) else { return };
+        let capture = hits.iter().map(|h| (Phase::Capture,h));
+        let bubble = hits.iter().rev().map(|h| (Phase::Bubble,h));
+        for (phase,hit) in capture.chain(bubble) {
+            let Some((tm,f)) = self.data.get_mut(&hit.entity) else { continue };
+            let target = Target { phase, target: innermost, anchor: hit.anchor.clone(), part: hit.part, at: hit.at };
+            let mut stopped = false;
+            for ((_,listening),listener) in f.pointer_dispatch.iter() {
+                if *listening == phase {
+                    stopped |= listener(ev,&target,tm);
+                }
+            }
+            if phase == Phase::Bubble {
+                (f.event_dispatch)(ev,tm);
+            }
+            if stopped {
+                return;
+            }
+        }
+    }
+}
@@ -0,0 +1,981 @@
+use std::borrow::Cow;
+
+use raster_primitive::{Color, Primitive as P};
+use types::render::{Anchor, Point, Rect, ZIndex};
+use types::traits::{Host as _, View};
+
+use super::*;
+
+struct NoStyles;
+
+impl StyleTable<Host> for NoStyles {
+    fn get(&self, _: &Path) -> Option<render::Style<Host>> {
+        None
+    }
+
+    fn update(&mut self, _: render::StyleChange<Host>) {}
+
+    fn scope(&mut self, _: &[render::StyleShadow]) -> Box<dyn StyleTable<Host>> {
+        Box::new(NoStyles)
+    }
+}
+
+fn rect(x0: f32, y0: f32, x1: f32, y1: f32) -> Rect<f32,f32> {
+    Rect::<(),()>::zero().upper_left_relative(Point::relative(x0,y0)).down_right_relative(Point::relative(x1,y1))
+}
+
+fn vp(width: u32, height: u32) -> Viewport {
+    Viewport { width, height }
+}
+
+fn anchor(name: &'static str) -> Anchor {
+    Anchor(Cow::Borrowed(name),Point::absolute(0,0))
+}
+
+fn view(anchors: &[&'static str], vp: Viewport) -> ViewData<Host> {
+    ViewData::new(anchors.iter().map(|a| anchor(a)).collect(),vp,Box::new(NoStyles))
+}
+
+fn layout(dims: Rect<f32,f32>, bgc: Color, parts: Vec<(Rect<f32,f32>,Filling<Host>)>) -> Layout<Host> {
+    Layout { dims, parts: parts.into_iter().map(Part::from).collect(), bgc, transform: None, opacity: 1., blend: render::BlendMode::Normal }
+}
+
+fn view_mut(h: &mut Host, ind: usize) -> &mut ViewData<Host> {
+    &mut h.data_view.get_mut(&ind).unwrap()[0].1
+}
+
+fn render(h: &mut Host, vp: Viewport) -> P {
+    let mut frame = None;
+    h.render(0,vp,|p| frame = Some(p));
+    frame.unwrap()
+}
+
+fn px(p: &P, x: u32, y: u32) -> Color {
+    p.get(Point::absolute(x,y)).unwrap()
+}
+
+/// Render the tree `build` makes in every mode, all of them must give the same pixels
+fn same_in_all_modes(build: impl Fn(&mut Host), vp: Viewport) -> P {
+    let mut frames = [RenderMode::Immediate,RenderMode::Retained,RenderMode::Parallel].map(|mode| {
+        let mut h = Host::new();
+        build(&mut h);
+        h.set_render_mode(mode);
+        (mode,render(&mut h,vp))
+    }).into_iter();
+    let (_,immediate) = frames.next().unwrap();
+    for (mode,frame) in frames {
+        for (y,x) in (0..vp.height).flat_map(|y| (0..vp.width).map(move |x| (y,x))) {
+            assert_eq!(px(&frame,x,y),px(&immediate,x,y),"{:?} differs from Immediate at {},{}",mode,x,y);
+        }
+    }
+    immediate
+}
+
+/// A root with a translucent layer over a component, which nests another one in a translucent layer of its own
+fn nested_layers(h: &mut Host) {
+    let mut grand = view(&["g"],vp(4,4));
+    grand.set_layout(anchor("g"),Some(layout(rect(0.,0.,1.,1.),Color::rgb(0,255,0),vec![])),ZIndex::Current(0));
+    let mut child = view(&["c","d"],vp(8,8));
+    child.set_layout(anchor("c"),Some(layout(rect(0.,0.,1.,1.),Color::rgb(0,0,255),vec![
+        (rect(0.,0.,0.5,0.5),Filling::Component(2,0)),
+    ])),ZIndex::Current(0));
+    child.set_layout(anchor("d"),Some(layout(rect(0.5,0.5,1.,1.),Color::rgb(255,0,0),vec![]).opacity(0.5)),ZIndex::Top);
+    let mut root = view(&["a","b"],vp(16,16));
+    root.set_layout(anchor("a"),Some(layout(rect(0.,0.,1.,1.),Color::WHITE,vec![
+        (rect(0.5,0.5,1.,1.),Filling::Component(1,0)),
+        (rect(0.,0.,0.5,0.5),Filling::Data(Arc::new(P::filled(vp(2,2),Color::rgb(255,0,255))))),
+    ])),ZIndex::Bottom);
+    root.set_layout(anchor("b"),Some(layout(rect(0.25,0.25,0.75,0.75),Color::rgba(0,0,0,128),vec![])),ZIndex::Top);
+    h.set_entity_data(0,root,0);
+    h.set_entity_data(1,child,0);
+    h.set_entity_data(2,grand,0);
+    h.set_root_entity(0);
+}
+
+#[test]
+fn retained_replays_nested_layers() {
+    let frame = same_in_all_modes(nested_layers,vp(16,16));
+    assert_eq!(px(&frame,1,1),Color::rgb(255,0,255));
+    assert_eq!(px(&frame,15,15),Color::rgb(128,0,127));
+    assert_eq!(px(&frame,9,9),Color::rgb(0,127,0));
+}
+
+#[test]
+fn retained_clips_spliced_lists() {
+    let frame = same_in_all_modes(|h| {
+        // the ellipse spills out of its part, and the child out of the root's layout
+        let mut child = view(&["c"],vp(8,8));
+        child.set_layout(anchor("c"),Some(layout(rect(0.,0.,1.,1.),Color::rgb(0,0,255),vec![
+            (rect(0.25,0.,1.,0.75),Filling::Shape(render::Shape::Ellipse { bounds: rect(-1.,-1.,2.,2.) },render::Paint::fill(Color::rgb(255,0,0)))),
+        ])),ZIndex::Current(0));
+        let mut root = view(&["a"],vp(16,16));
+        root.set_layout(anchor("a"),Some(layout(rect(0.,0.,0.75,0.75),Color::WHITE,vec![
+            (rect(0.5,0.5,1.5,1.5),Filling::Component(1,0)),
+        ])),ZIndex::Current(0));
+        h.set_entity_data(0,root,0);
+        h.set_entity_data(1,child,0);
+        h.set_root_entity(0);
+    },vp(16,16));
+    assert_eq!(px(&frame,6,6),Color::rgb(0,0,255));
+    assert_eq!(px(&frame,10,7),Color::rgb(255,0,0));
+    assert_eq!(px(&frame,13,13).a,0);
+}
+
+#[test]
+fn retained_lists_are_reused() {
+    let mut h = Host::new();
+    nested_layers(&mut h);
+    h.set_render_mode(RenderMode::Retained);
+    let _ = render(&mut h,vp(16,16));
+    let list = |h: &Host,ind| h.data_view[&ind][0].1.display_list.borrow().as_ref().unwrap().1.clone();
+    let (child,grand) = (list(&h,1),list(&h,2));
+    view_mut(&mut h,2).set_layout(anchor("g"),Some(layout(rect(0.,0.,1.,1.),Color::rgb(255,0,0),vec![])),ZIndex::Current(0));
+    let frame = render(&mut h,vp(16,16));
+    assert_eq!(px(&frame,9,9),Color::rgb(127,0,0));
+    assert!(!Arc::ptr_eq(&child,&list(&h,1)) && !Arc::ptr_eq(&grand,&list(&h,2)));
+    // pixels drawn by the root's list are those of its filling
+    let Filling::Data(pixels) = &h.data_view[&0][0].1.layouts[&anchor("a")].0.parts[1].filling else { unreachable!() };
+    assert!(Arc::strong_count(pixels) > 1);
+    let _ = render(&mut h,vp(16,16));
+    let child = list(&h,1);
+    let _ = render(&mut h,vp(16,16));
+    assert!(Arc::ptr_eq(&child,&list(&h,1)));
+}
+
+#[test]
+fn parts_dont_bleed_into_neighbours() {
+    let frame = same_in_all_modes(|h| {
+        let mut root = view(&["a"],vp(10,4));
+        // the split falls inside pixel 5, which belongs to the right part; the ellipse of the left one spills over it
+        root.set_layout(anchor("a"),Some(layout(rect(0.,0.,1.,1.),Color::WHITE,vec![
+            (rect(0.52,0.,1.,1.),Filling::Data(Arc::new(P::filled(vp(1,1),Color::rgb(0,0,255))))),
+            (rect(0.,0.,0.52,1.),Filling::Shape(render::Shape::Ellipse { bounds: rect(-1.,-1.,2.,2.) },render::Paint::fill(Color::rgb(255,0,0)))),
+        ])),ZIndex::Current(0));
+        h.set_entity_data(0,root,0);
+        h.set_root_entity(0);
+    },vp(10,4));
+    assert_eq!(px(&frame,4,2),Color::rgb(255,0,0));
+    assert_eq!(px(&frame,5,2),Color::rgb(0,0,255));
+}
+
+#[test]
+fn translucent_parts_are_mixed_into_the_frame() {
+    let frame = same_in_all_modes(|h| {
+        let mut child = view(&["c"],vp(4,8));
+        child.set_layout(anchor("c"),Some(layout(rect(0.,0.,1.,1.),Color::BLACK,vec![])),ZIndex::Current(0));
+        let mut root = view(&["a"],vp(8,8));
+        let mut parts = layout(rect(0.,0.,1.,1.),Color::WHITE,vec![]);
+        parts.parts = vec![
+            Part::from((rect(0.,0.,0.5,1.),Filling::Component(1,0))).opacity(0.5),
+            Part::from((rect(0.5,0.,1.,1.),Filling::Data(Arc::new(P::filled(vp(1,1),Color::rgb(255,128,0))))))
+                .opacity(0.5).blend(render::BlendMode::Multiply),
+        ];
+        root.set_layout(anchor("a"),Some(parts),ZIndex::Current(0));
+        h.set_entity_data(0,root,0);
+        h.set_entity_data(1,child,0);
+        h.set_root_entity(0);
+    },vp(8,8));
+    assert_eq!(px(&frame,1,4),Color::rgb(127,127,127));
+    assert_eq!(px(&frame,6,4),Color::rgb(255,191,127));
+}
+
+#[test]
+fn rotated_parts_are_turned_around_their_center() {
+    let frame = same_in_all_modes(|h| {
+        let mut root = view(&["a"],vp(8,8));
+        let pixels = P::from_pixels(vp(2,1),vec![Color::rgb(255,0,0),Color::rgb(0,0,255)]).unwrap();
+        let mut parts = layout(rect(0.,0.,1.,1.),Color::WHITE,vec![]);
+        // 4x2 pixels, red on the left and blue on the right, standing upright once turned
+        parts.parts = vec![Part::from((rect(0.25,0.375,0.75,0.625),Filling::Data(Arc::new(pixels))))
+            .transform(render::Transform::rotate(std::f32::consts::FRAC_PI_2))];
+        root.set_layout(anchor("a"),Some(parts),ZIndex::Current(0));
+        h.set_entity_data(0,root,0);
+        h.set_root_entity(0);
+    },vp(8,8));
+    assert_eq!(px(&frame,3,2),Color::rgb(255,0,0));
+    assert_eq!(px(&frame,4,5),Color::rgb(0,0,255));
+    assert_eq!(px(&frame,2,4),Color::WHITE);
+    assert_eq!(px(&frame,5,4),Color::WHITE);
+    assert_eq!(px(&frame,4,1),Color::WHITE);
+}
+
+#[test]
+fn scaled_layouts_shrink_to_their_center() {
+    let frame = same_in_all_modes(|h| {
+        let mut root = view(&["a","b"],vp(8,8));
+        root.set_layout(anchor("a"),Some(layout(rect(0.,0.,1.,1.),Color::WHITE,vec![])),ZIndex::Bottom);
+        root.set_layout(anchor("b"),Some(layout(rect(0.,0.,1.,1.),Color::rgb(255,0,0),vec![])
+            .transform(render::Transform::scale(0.5,0.5))),ZIndex::Top);
+        h.set_entity_data(0,root,0);
+        h.set_root_entity(0);
+    },vp(8,8));
+    for (x,y) in [(2,2),(5,5),(2,5),(4,3)] {
+        assert_eq!(px(&frame,x,y),Color::rgb(255,0,0),"at {},{}",x,y);
+    }
+    for (x,y) in [(1,1),(6,6),(1,4),(4,6)] {
+        assert_eq!(px(&frame,x,y),Color::WHITE,"at {},{}",x,y);
+    }
+}
+
+#[test]
+fn overlays_of_components_in_overlays() {
+    let (blue,green,yellow) = (Color::rgb(0,0,255),Color::rgb(0,255,0),Color::rgb(255,255,0));
+    let build = |h: &mut Host| {
+        // an overlay below its owner shows a component, which has an overlay to its right
+        let mut inner = view(&["g"],vp(32,32));
+        inner.set_layout(anchor("g"),Some(layout(rect(0.,0.,1.,1.),green,vec![])),ZIndex::Current(0));
+        inner.set_overlay(anchor("g"),Some(layout(rect(1.,0.,2.,1.),yellow,vec![])),0);
+        let mut owner = view(&["b"],vp(32,32));
+        owner.set_layout(anchor("b"),Some(layout(rect(0.,0.,1.,1.),Color::rgb(255,0,0),vec![])),ZIndex::Current(0));
+        owner.set_overlay(anchor("b"),Some(layout(rect(0.,1.,1.,2.),blue,vec![(rect(0.,0.,1.,1.),Filling::Component(2,0))])),0);
+        let mut root = view(&["a"],vp(64,64));
+        root.set_layout(anchor("a"),Some(layout(rect(0.,0.,1.,1.),Color::BLACK,vec![
+            (rect(0.,0.,0.5,0.5),Filling::Component(1,0)),
+        ])),ZIndex::Current(0));
+        h.set_entity_data(0,root,0);
+        h.set_entity_data(1,owner,0);
+        h.set_entity_data(2,inner,0);
+        h.set_root_entity(0);
+    };
+    let frame = same_in_all_modes(build,vp(64,64));
+    assert_eq!(px(&frame,10,48),green);
+    assert_eq!(px(&frame,48,48),yellow);
+    let mut h = Host::new();
+    build(&mut h);
+    let _ = render(&mut h,vp(64,64));
+    let hits = h.hit_test(0,Point::absolute(48,48));
+    assert_eq!(hits.iter().map(|h| (h.entity,h.overlay)).collect::<Vec<_>>(),vec![(0,false),(1,true),(2,true)]);
+    let hits = h.hit_test(0,Point::absolute(10,48));
+    assert_eq!(hits.iter().map(|h| (h.entity,h.overlay)).collect::<Vec<_>>(),vec![(0,false),(1,true),(2,false)]);
+}
+
+#[test]
+fn nested_constraints_are_solved_for_their_part() {
+    use types::render::constraints::{Constraints, Edges};
+    let (side,vp_edges) = (Edges::of("side"),Edges::viewport());
+    let constraints = Constraints::new()
+        .with(side.left().eq(0.)).unwrap()
+        .with(side.top().eq(0.)).unwrap()
+        .with(side.width().eq(4.)).unwrap()
+        .with(side.height().eq(vp_edges.height())).unwrap();
+    let mut h = Host::new();
+    // the child is made for 32x32, but shown in an 8x16 part
+    let mut child = view(&["c","side"],vp(32,32)).with_constraints(constraints);
+    child.set_layout(anchor("c"),Some(layout(rect(0.,0.,1.,1.),Color::rgb(0,0,255),vec![])),ZIndex::Bottom);
+    child.set_layout(anchor("side"),Some(layout(rect(0.,0.,1.,1.),Color::rgb(255,0,0),vec![])),ZIndex::Top);
+    let mut root = view(&["a"],vp(16,16));
+    root.set_layout(anchor("a"),Some(layout(rect(0.,0.,1.,1.),Color::WHITE,vec![
+        (rect(0.5,0.,1.,1.),Filling::Component(1,0)),
+    ])),ZIndex::Current(0));
+    h.set_entity_data(0,root,0);
+    h.set_entity_data(1,child,0);
+    h.set_root_entity(0);
+    let frame = render(&mut h,vp(16,16));
+    assert_eq!(h.data_view[&1][0].1.vp,vp(8,16));
+    // four pixels wide whatever the size of the part
+    assert_eq!(px(&frame,8,15),Color::rgb(255,0,0));
+    assert_eq!(px(&frame,11,0),Color::rgb(255,0,0));
+    assert_eq!(px(&frame,12,0),Color::rgb(0,0,255));
+    let frame = render(&mut h,vp(32,8));
+    assert_eq!(px(&frame,19,7),Color::rgb(255,0,0));
+    assert_eq!(px(&frame,20,0),Color::rgb(0,0,255));
+}
+
+#[test]
+fn constrained_components_in_overlays_take_their_part() {
+    use types::render::constraints::{Constraints, Edges};
+    let build = |h: &mut Host| {
+        let (side,vp_edges) = (Edges::of("side"),Edges::viewport());
+        let constraints = Constraints::new()
+            .with(side.left().eq(0.)).unwrap()
+            .with(side.top().eq(0.)).unwrap()
+            .with(side.width().eq(4.)).unwrap()
+            .with(side.height().eq(vp_edges.height())).unwrap();
+        // made for 32x32, shown in the upper half of an 8x8 overlay below its owner
+        let mut inner = view(&["c","side"],vp(32,32)).with_constraints(constraints);
+        inner.set_layout(anchor("c"),Some(layout(rect(0.,0.,1.,1.),Color::rgb(0,0,255),vec![])),ZIndex::Bottom);
+        inner.set_layout(anchor("side"),Some(layout(rect(0.,0.,1.,1.),Color::rgb(255,0,0),vec![])),ZIndex::Top);
+        let mut owner = view(&["b"],vp(8,8));
+        owner.set_layout(anchor("b"),Some(layout(rect(0.,0.,1.,1.),Color::BLACK,vec![])),ZIndex::Current(0));
+        owner.set_overlay(anchor("b"),Some(layout(rect(0.,1.,1.,2.),Color::WHITE,vec![(rect(0.,0.,1.,0.5),Filling::Component(2,0))])),0);
+        let mut root = view(&["a"],vp(16,16));
+        root.set_layout(anchor("a"),Some(layout(rect(0.,0.,1.,1.),Color::BLACK,vec![
+            (rect(0.,0.,0.5,0.5),Filling::Component(1,0)),
+        ])),ZIndex::Current(0));
+        h.set_entity_data(0,root,0);
+        h.set_entity_data(1,owner,0);
+        h.set_entity_data(2,inner,0);
+        h.set_root_entity(0);
+    };
+    let frame = same_in_all_modes(build,vp(16,16));
+    // four pixels wide, as solved for the 8x4 part
+    assert_eq!(px(&frame,3,9),Color::rgb(255,0,0));
+    assert_eq!(px(&frame,4,9),Color::rgb(0,0,255));
+    assert_eq!(px(&frame,4,13),Color::WHITE);
+    let mut h = Host::new();
+    build(&mut h);
+    let _ = render(&mut h,vp(16,16));
+    assert_eq!(h.data_view[&2][0].1.vp,vp(8,4));
+}
+
+/// Sixteen translucent tiles with a rotated layer on top, showing one of them again
+fn dashboard(h: &mut Host) {
+    let mut parts = vec![];
+    for i in 0..4 {
+        for j in 0..4 {
+            let id = 1 + i * 4 + j;
+            let mut tile = view(&["t","o"],vp(16,16));
+            tile.set_layout(anchor("t"),Some(layout(rect(0.,0.,1.,1.),Color::rgb((id * 15) as u8,100,200),vec![
+                (rect(0.1,0.1,0.9,0.5),Filling::Text(render::Text::new(format!("#{id}"),6.))),
+                (rect(0.2,0.5,0.8,0.9),Filling::Shape(render::Shape::ellipse(),render::Paint::fill(Color::rgba(0,255,0,200)))),
+            ])),ZIndex::Current(0));
+            tile.set_layout(anchor("o"),Some(layout(rect(0.5,0.,1.,0.5),Color::rgba(255,0,0,100),vec![]).blend(render::BlendMode::Multiply)),ZIndex::Top);
+            h.set_entity_data(id,tile,0);
+            let (x,y) = (i as f32 / 4.,j as f32 / 4.);
+            parts.push(Part::from((rect(x,y,x + 0.25,y + 0.25),Filling::Component(id,0))).opacity(0.9));
+        }
+    }
+    let mut root = view(&["a","b"],vp(64,64));
+    root.set_layout(anchor("a"),Some(Layout { parts, ..layout(rect(0.,0.,1.,1.),Color::WHITE,vec![]) }),ZIndex::Bottom);
+    root.set_layout(anchor("b"),Some(layout(rect(0.2,0.2,0.6,0.6),Color::rgba(0,0,0,80),vec![
+        (rect(0.,0.,1.,1.),Filling::Component(5,0)),
+    ]).transform(render::Transform::rotate(0.4))),ZIndex::Top);
+    h.set_entity_data(0,root,0);
+    h.set_root_entity(0);
+}
+
+#[test]
+fn parallel_matches_immediate() {
+    let (mut immediate,mut parallel) = (Host::new(),Host::new());
+    dashboard(&mut immediate);
+    dashboard(&mut parallel);
+    parallel.set_render_mode(RenderMode::Parallel);
+    for round in 0..3 {
+        assert_eq!(render(&mut parallel,vp(64,64)),render(&mut immediate,vp(64,64)),"round {}",round);
+        // damage a tile, only its rect is recomposited
+        for h in [&mut immediate,&mut parallel] {
+            view_mut(h,3 + round).set_layout(anchor("o"),Some(layout(rect(0.,0.5,0.5,1.),Color::rgba(0,0,255,150),vec![])),ZIndex::Top);
+        }
+    }
+    let frame = render(&mut parallel,vp(64,64));
+    assert_eq!(render(&mut parallel,vp(64,64)),frame);
+    parallel.set_render_mode(RenderMode::Immediate);
+    assert_eq!(render(&mut parallel,vp(64,64)),frame);
+}
+
+#[test]
+fn repaired_frames_match_full_renders() {
+    // a nested component changes in its middle, and a layer of the root moves
+    let change = |h: &mut Host| {
+        view_mut(h,2).set_layout(anchor("g"),Some(layout(rect(0.25,0.25,0.75,0.75),Color::rgb(255,255,0),vec![])),ZIndex::Current(0));
+        view_mut(h,0).set_layout(anchor("b"),Some(layout(rect(0.1,0.6,0.4,0.9),Color::rgba(0,255,255,200),vec![])),ZIndex::Top);
+    };
+    for mode in [RenderMode::Immediate,RenderMode::Parallel] {
+        let mut h = Host::new();
+        nested_layers(&mut h);
+        h.set_render_mode(mode);
+        let _ = render(&mut h,vp(16,16));
+        change(&mut h);
+        let damage = h.data_view[&0][0].1.pending_damage(&h.data_view,vp(16,16)).unwrap();
+        assert!(!damage.full && !damage.rects.is_empty());
+        let repaired = render(&mut h,vp(16,16));
+        let mut fresh = Host::new();
+        nested_layers(&mut fresh);
+        change(&mut fresh);
+        fresh.set_render_mode(mode);
+        assert_eq!(repaired,render(&mut fresh,vp(16,16)),"{:?}",mode);
+        assert!(h.data_view[&0][0].1.pending_damage(&h.data_view,vp(16,16)).is_none());
+    }
+}
+
+#[test]
+fn cache_keeps_sizes_of_a_generation() {
+    let mut h = Host::new();
+    dashboard(&mut h);
+    let _ = render(&mut h,vp(64,64));
+    let _ = render(&mut h,vp(128,128));
+    let cached = |h: &Host,size,generation| h.data_view[&1][0].1.view_cache.borrow_mut().get(&(vp(size,size),generation)).is_some();
+    let old = h.data_view[&1][0].1.content_generation(&h.data_view);
+    assert!(cached(&h,16,old) && cached(&h,32,old));
+    view_mut(&mut h,1).set_layout(anchor("o"),None,ZIndex::Top);
+    let _ = render(&mut h,vp(64,64));
+    let new = h.data_view[&1][0].1.content_generation(&h.data_view);
+    assert!(cached(&h,16,new) && !cached(&h,16,old) && !cached(&h,32,old));
+}
+
+#[test]
+fn dashboard_snapshot() {
+    let mut h = Host::new();
+    dashboard(&mut h);
+    golden_snapshots::Snapshot::new(h,vp(64,64))
+        .assert_matches(concat!(env!("CARGO_MANIFEST_DIR"),"/golden/dashboard.png"));
+}
+
+#[test]
+fn headless_runs_follow_resizes() {
+    let mut h = Host::new();
+    let mut root = view(&["a"],vp(8,8));
+    root.set_layout(anchor("a"),Some(layout(rect(0.,0.,1.,1.),Color::rgb(0,0,255),vec![])),ZIndex::Current(0));
+    h.set_entity_data(0,root,0);
+    h.set_root_entity(0);
+    let resized = types::app::Event { window: 0, event: winit::event::WindowEvent::Resized(winit::dpi::PhysicalSize::new(6,3)) };
+    let elsewhere = types::app::Event { window: 1, event: winit::event::WindowEvent::Resized(winit::dpi::PhysicalSize::new(2,2)) };
+    let frames = types::app::Application::new(h,vp(8,8)).run_headless(vec![vec![],vec![resized,elsewhere],vec![]].into_iter());
+    let sizes: Vec<_> = frames.iter().map(|f| f.primitive.size()).collect();
+    assert_eq!(sizes,[vp(8,8),vp(8,8),vp(6,3),vp(6,3)]);
+    assert_eq!(px(&frames[3].primitive,5,2),Color::rgb(0,0,255));
+}
+
+#[test]
+fn frames_are_shared_with_the_cache() {
+    for mode in [RenderMode::Immediate,RenderMode::Parallel] {
+        let mut h = Host::new();
+        dashboard(&mut h);
+        h.set_render_mode(mode);
+        let _ = render(&mut h,vp(64,64));
+        let tile = &h.data_view[&1][0].1;
+        let last = tile.last_frame.borrow().as_ref().map(|(_,frame)| frame.clone()).unwrap();
+        let generation = tile.content_generation(&h.data_view);
+        let cached = tile.view_cache.borrow_mut().get(&(vp(16,16),generation)).cloned().unwrap();
+        assert!(Arc::ptr_eq(&last,&cached),"{:?}",mode);
+    }
+}
+
+mod animations {
+    use std::cell::Cell;
+    use std::path::Path;
+    use std::rc::Rc;
+    use std::time::Duration;
+
+    use default_style_table::DefaultStyleTable;
+    use types::anim::{Animation, Easing, Target, Tween};
+    use types::render::{Style, StyleShadow};
+    use types::time::ManualClock;
+
+    use super::*;
+
+    const BODY: &str = "text/body";
+
+    fn body(h: &Host) -> Style<Host> {
+        h.data_view[&0][0].1.styles.get(Path::new(BODY)).unwrap()
+    }
+
+    fn dims(h: &Host) -> Rect<f32,f32> {
+        h.data_view[&0][0].1.layouts[&anchor("a")].0.dims
+    }
+
+    /// A root showing a label, driven by a manual clock
+    fn host(styles: Box<dyn StyleTable<Host>>) -> (Host,ManualClock) {
+        let mut h = Host::new();
+        let clock = ManualClock::new();
+        h.set_clock(clock.clone());
+        let mut root = ViewData::new(vec![anchor("a")],vp(10,10),styles);
+        root.set_layout(anchor("a"),Some(layout(rect(0.,0.,0.5,0.5),Color::WHITE,vec![
+            (rect(0.,0.,1.,1.),Filling::Text(render::Text::new("x",4.))),
+        ])),ZIndex::Current(0));
+        h.set_entity_data(0,root,0);
+        h.set_root_entity(0);
+        (h,clock)
+    }
+
+    fn black_body() -> Box<dyn StyleTable<Host>> {
+        Box::new(DefaultStyleTable::new([(Path::new(BODY),Style { color: Color::BLACK, weight: 400 })].into_iter()))
+    }
+
+    fn step(h: &mut Host, clock: &ManualClock, ms: u64) {
+        clock.advance(Duration::from_millis(ms));
+        h.update_round();
+    }
+
+    #[test]
+    fn steps_chain_after_delays() {
+        let (mut h,clock) = host(black_body());
+        let done = Rc::new(Cell::new(0));
+        let counter = done.clone();
+        let animation = Animation::new(Tween::new(Target::Dims("a".into(),rect(0.,0.,1.,1.)),Duration::from_millis(100)).easing(Easing::Linear))
+            .then(Tween::new(Target::StyleColor(BODY.into(),Color::WHITE),Duration::from_millis(100)).easing(Easing::Linear).delay(Duration::from_millis(50)))
+            .then(Tween::new(Target::StyleWeight(BODY.into(),700),Duration::ZERO));
+        let id = h.animations.start(0,animation,h.clock.now(),Some(Box::new(move |_| counter.set(counter.get() + 1))));
+        step(&mut h,&clock,50);
+        assert_eq!(dims(&h),rect(0.,0.,0.75,0.75));
+        // the first step ends exactly at its target, the second is waiting for its delay
+        step(&mut h,&clock,80);
+        assert_eq!(dims(&h),rect(0.,0.,1.,1.));
+        assert_eq!(body(&h).color,Color::BLACK);
+        // the second step started when the first ended, not at the frame which ended it
+        step(&mut h,&clock,70);
+        assert!((126..=129).contains(&body(&h).color.r),"{:?}",body(&h).color);
+        assert_eq!(done.get(),0);
+        step(&mut h,&clock,1000);
+        assert_eq!((body(&h).color,body(&h).weight),(Color::WHITE,700));
+        assert_eq!(done.get(),1);
+        step(&mut h,&clock,1000);
+        assert_eq!(done.get(),1);
+        assert!(!h.animations.cancel(id));
+    }
+
+    #[test]
+    fn replacing_the_clock_keeps_animations_going() {
+        let (mut h,clock) = host(black_body());
+        h.animations.start(0,Tween::new(Target::Dims("a".into(),rect(0.,0.,1.,1.)),Duration::from_millis(100)).easing(Easing::Linear).into(),h.clock.now(),None);
+        step(&mut h,&clock,50);
+        let clock = ManualClock::new();
+        clock.advance(Duration::from_secs(3));
+        h.set_clock(clock.clone());
+        step(&mut h,&clock,25);
+        assert_eq!(dims(&h),rect(0.,0.,0.875,0.875));
+    }
+
+    #[test]
+    fn inherited_styles_stay_inherited() {
+        let mut parent = DefaultStyleTable::new([(Path::new(BODY),Style { color: Color::BLACK, weight: 400 })].into_iter());
+        let (mut h,clock) = host(parent.scope(&[StyleShadow(Path::new(BODY))]));
+        h.animations.start(0,Tween::new(Target::StyleColor(BODY.into(),Color::WHITE),Duration::from_millis(10)).into(),h.clock.now(),None);
+        step(&mut h,&clock,20);
+        parent.update(render::StyleChange::OverwriteColor { what: Path::new(BODY), color: Color::rgb(255,0,0) });
+        assert_eq!(body(&h).color,Color::rgb(255,0,0));
+    }
+}
+
+mod hits {
+    use super::*;
+
+    /// A root with a component nesting another one, a letterboxed image beside them and a translucent layer below;
+    /// the outer component has a small overlay over the inner one
+    fn scene(h: &mut Host) {
+        let mut inner = view(&["d"],vp(16,16));
+        inner.set_layout(anchor("d"),Some(layout(rect(0.,0.,1.,1.),Color::rgb(0,0,255),vec![])),ZIndex::Current(0));
+        let mut outer = view(&["c"],vp(32,32));
+        outer.set_layout(anchor("c"),Some(layout(rect(0.,0.,1.,1.),Color::TRANSPARENT,vec![
+            (rect(0.,0.,0.5,0.5),Filling::Component(2,0)),
+        ])),ZIndex::Current(0));
+        outer.set_overlay(anchor("c"),Some(layout(rect(0.,0.,0.25,0.25),Color::rgb(255,255,0),vec![])),0);
+        let wide = Arc::new(P::filled(vp(2,1),Color::rgb(255,0,0)));
+        let mut root = view(&["a","b"],vp(64,64));
+        root.set_layout(anchor("a"),Some(layout(rect(0.,0.,1.,1.),Color::WHITE,vec![
+            (rect(0.,0.,0.5,0.5),Filling::Component(1,0)),
+            (rect(0.5,0.,1.,0.5),Filling::Image(wide,render::Fit::Contain)),
+        ])),ZIndex::Bottom);
+        root.set_layout(anchor("b"),Some(layout(rect(0.,0.5,1.,1.),Color::TRANSPARENT,vec![
+            (rect(0.,0.,0.5,1.),Filling::Shape(render::Shape::rect(),render::Paint::fill(Color::BLACK))),
+        ])),ZIndex::Top);
+        h.set_entity_data(0,root,0);
+        h.set_entity_data(1,outer,0);
+        h.set_entity_data(2,inner,0);
+        h.set_root_entity(0);
+    }
+
+    /// Entity, anchor, part and whether it's an overlay, of hits under a pixel
+    fn hits(h: &Host, x: u32, y: u32) -> Vec<(usize,String,Option<usize>,bool)> {
+        h.hit_test(0,Point::absolute(x,y)).into_iter().map(|hit| (hit.entity,hit.anchor.0.into_owned(),hit.part,hit.overlay)).collect()
+    }
+
+    fn hit(entity: usize, anchor: &str, part: Option<usize>, overlay: bool) -> (usize,String,Option<usize>,bool) {
+        (entity,anchor.to_string(),part,overlay)
+    }
+
+    fn host() -> Host {
+        let mut h = Host::new();
+        scene(&mut h);
+        let _ = render(&mut h,vp(64,64));
+        h
+    }
+
+    #[test]
+    fn nested_components() {
+        let h = host();
+        assert_eq!(hits(&h,12,12),vec![hit(0,"a",Some(0),false),hit(1,"c",Some(0),false),hit(2,"d",None,false)]);
+        // relative to the view of each entity
+        let at: Vec<_> = h.hit_test(0,Point::absolute(12,12)).into_iter().map(|h| h.at).collect();
+        assert_eq!(at,vec![Point::relative(12.5 / 64.,12.5 / 64.),Point::relative(12.5 / 32.,12.5 / 32.),Point::relative(12.5 / 16.,12.5 / 16.)]);
+    }
+
+    #[test]
+    fn transparency_falls_through() {
+        let h = host();
+        // the background of the outer component
+        assert_eq!(hits(&h,20,20),vec![hit(0,"a",None,false)]);
+        // the background of the top layer
+        assert_eq!(hits(&h,48,48),vec![hit(0,"a",None,false)]);
+        assert_eq!(hits(&h,10,48),vec![hit(0,"b",Some(0),false)]);
+        // the letterbox of the image, above and below it
+        assert_eq!(hits(&h,48,4),vec![hit(0,"a",None,false)]);
+        assert_eq!(hits(&h,48,28),vec![hit(0,"a",None,false)]);
+        assert_eq!(hits(&h,48,16),vec![hit(0,"a",Some(1),false)]);
+    }
+
+    #[test]
+    fn overlays_come_first() {
+        let h = host();
+        assert_eq!(hits(&h,4,4),vec![hit(0,"a",Some(0),false),hit(1,"c",None,true)]);
+        let hit_test = h.hit_test(0,Point::absolute(4,4));
+        assert_eq!(hit_test[1].at,Point::relative(4.5 / 32.,4.5 / 32.));
+    }
+}
+
+mod pointer {
+    use std::cell::{Cell, RefCell};
+    use std::rc::Rc;
+
+    use types::app::Event;
+    use types::pointer::{Phase, Target};
+    use winit::event::{DeviceId, ElementState, MouseButton, WindowEvent};
+
+    use super::*;
+
+    type Log = Rc<RefCell<Vec<String>>>;
+
+    /// Two sibling components in a root, and an entity outside of the tree; every entity logs the pointer events it sees:
+    /// `c<entity>><target>` from capture listeners, `b<entity>@<part>` from bubble ones and `f<entity>` from event filters.
+    /// A listener stops the event when it's the one in `stop`
+    fn host(stop: Rc<Cell<Option<(usize,Phase)>>>) -> (Host,Log) {
+        let mut h = Host::new();
+        let mut root = view(&["a"],vp(64,64));
+        root.set_layout(anchor("a"),Some(layout(rect(0.,0.,1.,1.),Color::BLACK,vec![
+            (rect(0.,0.,0.5,0.5),Filling::Component(1,0)),
+            (rect(0.5,0.,1.,0.5),Filling::Component(2,0)),
+        ])),ZIndex::Current(0));
+        h.set_entity_data(0,root,0);
+        for e in 1..3 {
+            let mut sibling = view(&["b"],vp(32,32));
+            sibling.set_layout(anchor("b"),Some(layout(rect(0.,0.,1.,1.),Color::WHITE,vec![])),ZIndex::Current(0));
+            h.set_entity_data(e,sibling,0);
+        }
+        h.set_entity_data(3,view(&[],vp(8,8)),0);
+        h.set_root_entity(0);
+        let _ = render(&mut h,vp(64,64));
+        let log: Log = Rc::default();
+        for e in 0..4usize {
+            let (capture,bubble,filter) = (log.clone(),log.clone(),log.clone());
+            let (stop_capture,stop_bubble) = (stop.clone(),stop.clone());
+            let listeners: crate::pointer::PointerListeners = vec![
+                ((std::any::TypeId::of::<u8>(),Phase::Capture),Box::new(move |_,t: &Target<Host>,_| {
+                    capture.borrow_mut().push(format!("c{}>{}",e,t.target));
+                    stop_capture.get() == Some((e,Phase::Capture))
+                })),
+                ((std::any::TypeId::of::<u8>(),Phase::Bubble),Box::new(move |_,t: &Target<Host>,_| {
+                    bubble.borrow_mut().push(format!("b{}@{:?}",e,t.part));
+                    stop_bubble.get() == Some((e,Phase::Bubble))
+                })),
+            ];
+            let event_dispatch = Box::new(move |_: &Event,_: &mut TypeMap| filter.borrow_mut().push(format!("f{}",e)));
+            h.data.insert(e,(TypeMap::new(),ProcessingFunctionsEntity { event_dispatch, pointer_dispatch: listeners }));
+        }
+        (h,log)
+    }
+
+    fn event(event: WindowEvent<'static>) -> Event {
+        Event { window: 0, event }
+    }
+
+    #[allow(deprecated)]
+    fn moved(x: f64, y: f64) -> Event {
+        event(WindowEvent::CursorMoved { device_id: unsafe { DeviceId::dummy() }, position: winit::dpi::PhysicalPosition::new(x,y), modifiers: Default::default() })
+    }
+
+    #[allow(deprecated)]
+    fn click() -> Event {
+        event(WindowEvent::MouseInput { device_id: unsafe { DeviceId::dummy() }, state: ElementState::Pressed, button: MouseButton::Left, modifiers: Default::default() })
+    }
+
+    fn left() -> Event {
+        event(WindowEvent::CursorLeft { device_id: unsafe { DeviceId::dummy() } })
+    }
+
+    fn send(h: &mut Host, log: &Log, events: &[Event]) -> Vec<String> {
+        h.receive_events(events.iter());
+        std::mem::take(&mut *log.borrow_mut())
+    }
+
+    #[test]
+    fn capture_down_then_bubble_up() {
+        let (mut h,log) = host(Rc::default());
+        assert_eq!(send(&mut h,&log,&[moved(10.,10.)]),["c0>1","c1>1","b1@None","f1","b0@Some(0)","f0"]);
+        // the root's own background
+        assert_eq!(send(&mut h,&log,&[moved(10.,50.)]),["c0>0","b0@None","f0"]);
+    }
+
+    #[test]
+    fn only_the_hit_sibling_receives() {
+        let (mut h,log) = host(Rc::default());
+        assert_eq!(send(&mut h,&log,&[moved(48.,10.),click()]),[
+            "c0>2","c2>2","b2@None","f2","b0@Some(1)","f0",
+            "c0>2","c2>2","b2@None","f2","b0@Some(1)","f0",
+        ]);
+    }
+
+    #[test]
+    fn stopped_events_go_no_further() {
+        let stop = Rc::new(Cell::new(Some((1,Phase::Bubble))));
+        let (mut h,log) = host(stop.clone());
+        // the filter of the entity which stopped it still sees the event
+        assert_eq!(send(&mut h,&log,&[moved(10.,10.)]),["c0>1","c1>1","b1@None","f1"]);
+        stop.set(Some((0,Phase::Capture)));
+        assert_eq!(send(&mut h,&log,&[click()]),["c0>1"]);
+    }
+
+    #[test]
+    fn leaving_goes_to_the_hovered_entities() {
+        let (mut h,log) = host(Rc::default());
+        // a click before the cursor is known goes nowhere
+        assert!(send(&mut h,&log,&[click()]).is_empty());
+        send(&mut h,&log,&[moved(48.,10.)]);
+        assert_eq!(send(&mut h,&log,&[left()]),["c0>2","c2>2","b2@None","f2","b0@Some(1)","f0"]);
+        assert!(send(&mut h,&log,&[left(),click()]).is_empty());
+        // so does moving out of the window
+        send(&mut h,&log,&[moved(10.,10.)]);
+        assert_eq!(send(&mut h,&log,&[moved(-1.,3.)]),["c0>1","c1>1","b1@None","f1","b0@Some(0)","f0"]);
+        // other events are for everyone
+        let mut all = send(&mut h,&log,&[event(WindowEvent::Focused(true))]);
+        all.sort();
+        assert_eq!(all,["f0","f1","f2","f3"]);
+    }
+}
+
+mod systems {
+    use std::path::PathBuf;
+
+    use types::assets::Handle;
+    use types::errors::assets::AssetError;
+    use types::traits::{Context, GlobalState, Hosts, System};
+
+    use super::*;
+
+    struct Stateless;
+
+    impl GlobalState<Host> for Stateless {
+        fn init() -> Self {
+            Stateless
+        }
+
+        fn register(&mut self, _: &mut Host) {}
+
+        fn update(&mut self, _: impl FnOnce(Self) -> Self) {}
+    }
+
+    /// Adds up the messages it gets, starting from its props
+    struct Counter(u32);
+
+    impl System<Host> for Counter {
+        type Message = u32;
+        type State = Stateless;
+        type Props = u32;
+
+        fn init(props: &u32) -> Self {
+            Counter(*props)
+        }
+
+        fn update<'s,'h: 's>(&'s mut self, msg: u32, _: &mut impl Context<'h,Host>) {
+            self.0 += msg;
+        }
+
+        fn view<'v>(&'v self, _: &'v mut dyn render::Renderer<Host>, _: Viewport, _: usize) {}
+    }
+
+    fn count(h: &mut Host) -> u32 {
+        <Host as Hosts<Counter>>::get_state(h,0).unwrap().0
+    }
+
+    #[test]
+    fn subscribing_again_starts_over() {
+        let mut h = Host::new();
+        h.set_entity_data(0,view(&[],vp(8,8)),0);
+        <Host as Hosts<Counter>>::subscribe(&mut h,0,5);
+        <Host as Hosts<Counter>>::send(&mut h,3,0);
+        h.update_round();
+        assert_eq!(count(&mut h),8);
+        // new props replace the state, a message sent before still arrives
+        <Host as Hosts<Counter>>::send(&mut h,2,0);
+        <Host as Hosts<Counter>>::subscribe(&mut h,0,10);
+        assert_eq!(count(&mut h),10);
+        h.update_round();
+        assert_eq!(count(&mut h),12);
+    }
+
+    enum Request {
+        Text(&'static str),
+        Bytes(&'static str),
+        Loaded(Result<PathBuf,AssetError>),
+        Forget,
+    }
+
+    /// Holds what it loads until told to forget it, notes paths as they finish loading
+    #[derive(Default)]
+    struct Loader {
+        texts: Vec<Handle<String>>,
+        bytes: Vec<Handle<Vec<u8>>>,
+        loaded: Vec<Result<PathBuf,AssetError>>,
+    }
+
+    impl System<Host> for Loader {
+        type Message = Request;
+        type State = Stateless;
+        type Props = ();
+
+        fn init(_: &()) -> Self {
+            Loader::default()
+        }
+
+        fn update<'s,'h: 's>(&'s mut self, msg: Request, ctx: &mut impl Context<'h,Host>) {
+            fn done<A>(r: Result<Handle<A>,AssetError>) -> Request {
+                Request::Loaded(r.map(|h| h.path().to_path_buf()))
+            }
+            match msg {
+                Request::Text(path) => self.texts.push(ctx.load::<String,_,Loader>(path,done)),
+                Request::Bytes(path) => self.bytes.push(ctx.load::<Vec<u8>,_,Loader>(path,done)),
+                Request::Loaded(outcome) => self.loaded.push(outcome),
+                Request::Forget => {
+                    self.texts.clear();
+                    self.bytes.clear();
+                }
+            }
+        }
+
+        fn view<'v>(&'v self, _: &'v mut dyn render::Renderer<Host>, _: Viewport, _: usize) {}
+    }
+
+    /// A host loading from a fresh directory holding `files`, removed once the test is done with it
+    struct Assets {
+        h: Host,
+        root: PathBuf,
+    }
+
+    impl Assets {
+        fn new(name: &str, files: &[(&str,&str)]) -> Self {
+            let root = std::env::temp_dir().join(format!("type_erased_host_{}_{}",name,std::process::id()));
+            let _ = std::fs::remove_dir_all(&root);
+            std::fs::create_dir_all(&root).unwrap();
+            for (path,contents) in files {
+                std::fs::write(root.join(path),contents).unwrap();
+            }
+            let mut h = Host::new();
+            h.set_asset_root(&root);
+            h.set_entity_data(0,view(&[],vp(8,8)),0);
+            <Host as Hosts<Loader>>::subscribe(&mut h,0,());
+            Self { h, root }
+        }
+
+        fn send(&mut self, msg: Request) {
+            <Host as Hosts<Loader>>::send(&mut self.h,msg,0);
+            self.h.update_round();
+        }
+
+        fn loader(&mut self) -> &mut Loader {
+            <Host as Hosts<Loader>>::get_state(&mut self.h,0).unwrap()
+        }
+
+        /// Run rounds until `n` loads have been reported
+        fn wait_for(&mut self, n: usize) -> &[Result<PathBuf,AssetError>] {
+            for _ in 0..5000 {
+                if self.loader().loaded.len() >= n {
+                    break;
+                }
+                std::thread::sleep(std::time::Duration::from_millis(1));
+                self.h.update_round();
+            }
+            &self.loader().loaded
+        }
+    }
+
+    impl Drop for Assets {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.root);
+        }
+    }
+
+    #[test]
+    fn assets_are_shared_by_type_and_path() {
+        let mut a = Assets::new("shared",&[("a.txt","hello")]);
+        a.send(Request::Text("a.txt"));
+        a.send(Request::Text("a.txt"));
+        let texts = &a.loader().texts;
+        assert!(texts[0].ptr_eq(&texts[1]));
+        assert_eq!(a.h.assets().len(),1);
+        // the same file as another type is loaded on its own
+        a.send(Request::Bytes("a.txt"));
+        assert_eq!(a.h.assets().len(),2);
+        assert_eq!(a.wait_for(3).len(),3);
+        let loader = a.loader();
+        // pending loads don't hold on to the asset once they are delivered
+        assert_eq!(loader.texts[0].strong_count(),2);
+        assert_eq!(loader.texts[0].get().as_deref().map(String::as_str),Some("hello"));
+        assert_eq!(loader.bytes[0].get().as_deref().map(Vec::as_slice),Some(&b"hello"[..]));
+    }
+
+    #[test]
+    fn finished_loads_are_delivered() {
+        let mut a = Assets::new("delivered",&[("a.txt","hello")]);
+        a.send(Request::Text("a.txt"));
+        assert_eq!(a.wait_for(1),&[Ok(PathBuf::from("a.txt"))]);
+        assert!(a.loader().texts[0].is_ready());
+    }
+
+    #[test]
+    fn missing_files_fail() {
+        let mut a = Assets::new("missing",&[]);
+        a.send(Request::Text("missing.txt"));
+        assert_eq!(a.wait_for(1),&[Err(AssetError::Io(std::io::ErrorKind::NotFound))]);
+        let handle = &a.loader().texts[0];
+        assert!(matches!(handle.state(),types::assets::LoadState::Failed(AssetError::Io(std::io::ErrorKind::NotFound))));
+    }
+
+    #[test]
+    fn assets_are_freed_with_their_last_handle() {
+        let mut a = Assets::new("freed",&[("a.txt","hello")]);
+        a.send(Request::Text("a.txt"));
+        a.wait_for(1);
+        let weak = a.loader().texts[0].downgrade();
+        // rounds end with a purge
+        a.send(Request::Forget);
+        assert!(!weak.is_alive());
+        assert!(a.h.assets().is_empty());
+        // loading it again starts over
+        a.send(Request::Text("a.txt"));
+        assert_eq!(a.wait_for(2)[1],Ok(PathBuf::from("a.txt")));
+        assert!(a.loader().texts[0].is_ready() && !weak.is_alive());
+        assert_eq!(a.h.assets().len(),1);
+    }
+
+    /// Notes the scale factor every time it's viewed
+    struct Scaled(Arc<std::sync::Mutex<Vec<f64>>>);
+
+    impl System<Host> for Scaled {
+        type Message = ();
+        type State = Stateless;
+        type Props = Arc<std::sync::Mutex<Vec<f64>>>;
+
+        fn init(seen: &Self::Props) -> Self {
+            Scaled(seen.clone())
+        }
+
+        fn update<'s,'h: 's>(&'s mut self, _: (), _: &mut impl Context<'h,Host>) {}
+
+        fn view<'v>(&'v self, r: &'v mut dyn render::Renderer<Host>, _: Viewport, _: usize) {
+            self.0.lock().unwrap().push(r.scale_factor().0);
+            if r.anchors().iter().any(|a| a.0 == "s") {
+                r.layout(Some(layout(rect(0.,0.,1.,1.),Color::rgb(0,0,255),vec![])),anchor("s"),ZIndex::Current(0));
+            }
+        }
+    }
+
+    #[test]
+    fn views_follow_the_scale_factor() {
+        let seen = Arc::new(std::sync::Mutex::new(vec![]));
+        let mut h = Host::new();
+        h.set_entity_data(0,view(&["s"],vp(10,6)),0);
+        h.set_root_entity(0);
+        <Host as Hosts<Scaled>>::subscribe(&mut h,0,seen.clone());
+        h.update_round();
+        assert_eq!(render(&mut h,vp(10,6)).viewport(),vp(10,6));
+        h.set_scale_factor(0,render::ScaleFactor(1.5));
+        h.update_round();
+        assert_eq!(*seen.lock().unwrap(),[1.,1.5]);
+        // drawn again at the physical size of the window
+        let physical = render::ScaleFactor(1.5).physical_viewport(vp(10,6));
+        let frame = render(&mut h,physical);
+        assert_eq!(frame.viewport(),vp(15,9));
+        assert_eq!(px(&frame,14,8),Color::rgb(0,0,255));
+        let view = &h.data_view[&0][0].1;
+        assert_eq!((view.scale_factor(),view.vp),(render::ScaleFactor(1.5),vp(15,9)));
+    }
+}
@@ -0,0 +1,201 @@
+//! Animations: values of an entity's view tweened over time, driven by the host's frame clock.
+//! Style colors and weights, dims of layouts and rects of parts can be animated.
+use std::borrow::Cow;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::render::{Point, Primitive, Rect};
+use crate::traits::Host;
+
+/// Values which can be interpolated; `t` is `0.0` at `self` and `1.0` at `to`
+pub trait Lerp: Copy {
+    fn lerp(&self, to: &Self, t: f32) -> Self;
+}
+
+impl Lerp for f32 {
+    fn lerp(&self, to: &Self, t: f32) -> Self {
+        self + (to - self) * t
+    }
+}
+
+impl Lerp for u16 {
+    fn lerp(&self, to: &Self, t: f32) -> Self {
+        (*self as f32).lerp(&(*to as f32),t).round().clamp(0.,u16::MAX as f32) as u16
+    }
+}
+
+impl Lerp for Rect<f32,f32> {
+    fn lerp(&self, to: &Self, t: f32) -> Self {
+        let point = |a: Point<f32>, b: Point<f32>| Point::relative(a.x().lerp(&b.x(),t),a.y().lerp(&b.y(),t));
+        Rect::<(),()>::zero()
+            .upper_left_relative(point(self.upper_left(),to.upper_left()))
+            .down_right_relative(point(self.down_right(),to.down_right()))
+    }
+}
+
+/// How progress of a tween is spread over its duration
+#[derive(Clone,Copy,Debug,Default,PartialEq)]
+pub enum Easing {
+    Linear,
+    /// starts slow
+    EaseIn,
+    /// ends slow
+    EaseOut,
+    #[default]
+    EaseInOut,
+    /// a CSS-like curve through `(0,0)`, `(x1,y1)`, `(x2,y2)` and `(1,1)`; `x1` and `x2` are within `0..=1`
+    CubicBezier(f32,f32,f32,f32),
+}
+
+impl Easing {
+    /// Eased progress at `t`, both in `0..=1`
+    pub fn apply(&self, t: f32) -> f32 {
+        let t = t.clamp(0.,1.);
+        match *self {
+            Easing::Linear => t,
+            Easing::EaseIn => t * t * t,
+            Easing::EaseOut => 1. - (1. - t).powi(3),
+            Easing::EaseInOut => if t < 0.5 { 4. * t * t * t } else { 1. - (-2. * t + 2.).powi(3) / 2. },
+            Easing::CubicBezier(x1,y1,x2,y2) => {
+                let bezier = |a: f32, b: f32, s: f32| 3. * a * s * (1. - s).powi(2) + 3. * b * s * s * (1. - s) + s * s * s;
+                // x grows with s, so the s giving x = t is found by bisection
+                let (mut lo,mut hi) = (0f32,1f32);
+                for _ in 0..24 {
+                    let mid = (lo + hi) / 2.;
+                    if bezier(x1.clamp(0.,1.),x2.clamp(0.,1.),mid) < t { lo = mid } else { hi = mid }
+                }
+                bezier(y1,y2,(lo + hi) / 2.)
+            }
+        }
+    }
+}
+
+/// A value of the current entity, with the value it's animated to.
+/// Values are changed in all portals of the entity; an animation starts from the value it finds
+pub enum Target<H: Host + ?Sized> {
+    /// color of a style in the entity's table, overwritten in place: the style isn't detached from the scope it's inherited from,
+    /// and the default table leaves inherited styles alone
+    StyleColor(PathBuf,<<H as Host>::Primitive as Primitive>::Color),
+    /// weight of a style in the entity's table, overwritten in place like colors
+    StyleWeight(PathBuf,u16),
+    /// dims of the layout attached at an anchor
+    Dims(Cow<'static,str>,Rect<f32,f32>),
+    /// rect of a part of the layout attached at an anchor, by its position in `Layout::parts`
+    PartRect(Cow<'static,str>,usize,Rect<f32,f32>),
+}
+
+/// Change of a value over `duration`
+pub struct Tween<H: Host + ?Sized> {
+    pub target: Target<H>,
+    pub duration: Duration,
+    pub easing: Easing,
+    /// wait before starting
+    pub delay: Duration,
+}
+
+impl<H: Host + ?Sized> Tween<H> {
+    pub fn new(target: Target<H>, duration: Duration) -> Self {
+        Self { target, duration, easing: Easing::default(), delay: Duration::ZERO }
+    }
+
+    pub fn easing(mut self, easing: Easing) -> Self {
+        self.easing = easing;
+        self
+    }
+
+    pub fn delay(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    /// Eased progress, `elapsed` since the tween was started
+    pub fn progress(&self, elapsed: Duration) -> f32 {
+        let Some(elapsed) = elapsed.checked_sub(self.delay) else { return 0. };
+        if self.duration.is_zero() {
+            return 1.;
+        }
+        self.easing.apply(elapsed.as_secs_f32() / self.duration.as_secs_f32())
+    }
+
+    /// Time from start to end, delay included
+    pub fn total(&self) -> Duration {
+        self.delay + self.duration
+    }
+}
+
+/// Tweens run one after another
+pub struct Animation<H: Host + ?Sized> {
+    pub steps: Vec<Tween<H>>,
+}
+
+impl<H: Host + ?Sized> Animation<H> {
+    pub fn new(first: Tween<H>) -> Self {
+        Self { steps: vec![first] }
+    }
+
+    /// Run `next` once the tweens so far are done
+    pub fn then(mut self, next: Tween<H>) -> Self {
+        self.steps.push(next);
+        self
+    }
+}
+
+impl<H: Host + ?Sized> From<Tween<H>> for Animation<H> {
+    fn from(tween: Tween<H>) -> Self {
+        Self::new(tween)
+    }
+}
+
+/// A running animation, as returned by `Context::animate`
+#[derive(Clone,Copy,Debug,Hash,Eq,PartialEq)]
+pub struct AnimationId(pub u64);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_host::TestHost;
+
+    fn close(a: f32, b: f32) -> bool {
+        (a - b).abs() < 1e-3
+    }
+
+    #[test]
+    fn easings_span_zero_to_one() {
+        for easing in [Easing::Linear,Easing::EaseIn,Easing::EaseOut,Easing::EaseInOut,Easing::CubicBezier(0.25,0.1,0.25,1.)] {
+            assert!(close(easing.apply(0.),0.) && close(easing.apply(1.),1.),"{:?}",easing);
+            // progress out of range is clamped
+            assert!(close(easing.apply(-1.),0.) && close(easing.apply(2.),1.),"{:?}",easing);
+            let samples: Vec<_> = (0..=20).map(|i| easing.apply(i as f32 / 20.)).collect();
+            assert!(samples.windows(2).all(|w| w[0] <= w[1]),"{:?} isn't monotonic",easing);
+        }
+        assert!(Easing::EaseIn.apply(0.5) < 0.5 && Easing::EaseOut.apply(0.5) > 0.5);
+        assert!(close(Easing::EaseInOut.apply(0.3) + Easing::EaseInOut.apply(0.7),1.));
+    }
+
+    #[test]
+    fn cubic_bezier() {
+        // a straight line is linear
+        for t in [0.1,0.3,0.5,0.9] {
+            assert!(close(Easing::CubicBezier(0.,0.,1.,1.).apply(t),t));
+        }
+        // CSS `ease`
+        assert!(close(Easing::CubicBezier(0.25,0.1,0.25,1.).apply(0.5),0.8024));
+        // CSS `ease-in-out` is symmetric
+        let ease_in_out = Easing::CubicBezier(0.42,0.,0.58,1.);
+        assert!(close(ease_in_out.apply(0.5),0.5));
+        assert!(close(ease_in_out.apply(0.2) + ease_in_out.apply(0.8),1.));
+        // control points may overshoot in y
+        assert!(Easing::CubicBezier(0.3,1.6,0.7,1.6).apply(0.6) > 1.);
+    }
+
+    #[test]
+    fn tween_progress() {
+        let tween = |duration,delay| Tween::<TestHost>::new(Target::Dims("a".into(),Rect::full_box()),Duration::from_millis(duration))
+            .easing(Easing::Linear)
+            .delay(Duration::from_millis(delay));
+        assert_eq!(tween(100,50).progress(Duration::from_millis(40)),0.);
+        assert!(close(tween(100,50).progress(Duration::from_millis(75)),0.25));
+        assert_eq!(tween(100,50).total(),Duration::from_millis(150));
+        assert_eq!(tween(0,10).progress(Duration::from_millis(10)),1.);
+    }
+}
@@ -1,37 +1,207 @@
-use crate::render::Viewport;
-use std::collections::{BTreeMap, HashMap};
-use std::ops::Add;
+use std::collections::HashMap;
 
-use crate::traits::Host;
+use crate::render::{Primitive, ScaleFactor, Viewport, Visitor};
+
+use crate::traits::{Host, Hosts, System};
 
 use winit::{
-    event::{Event,WindowEvent},
+    dpi::LogicalSize,
+    event::{Event as LoopEvent, WindowEvent},
     event_loop::{ControlFlow, EventLoop},
+    platform::run_return::EventLoopExtRunReturn,
     window::WindowBuilder,
 };
 
+mod window;
+
+/// An event of one of the application's windows
+#[derive(Clone,Debug,PartialEq)]
+pub struct Event {
+    /// root portal the window shows
+    pub window: usize,
+    pub event: WindowEvent<'static>,
+}
+
+/// Primitives which can be shown in a window
+pub trait Present: Primitive {
+    /// Pixels row by row from the top left, 4 bytes each: red, green, blue and (straight) alpha
+    fn rgba8(&self) -> Vec<u8>;
+}
 
-//todo: fill this with wgpu states
-pub struct Application<H: Host<Event = WindowEvent<'static>>>
+/// A source of event batches for a headless run
+pub trait EventSource<E> {
+    /// Next batch of events; `None` halts the application
+    fn next_batch(&mut self) -> Option<Vec<E>>;
+}
+
+/// Any iterator over batches is a scripted event source
+impl<E,I: Iterator<Item = Vec<E>>> EventSource<E> for I {
+    fn next_batch(&mut self) -> Option<Vec<E>> {
+        self.next()
+    }
+}
+
+/// One rendered root portal of a headless run
+pub struct Frame<P> {
+    /// number of the loop iteration the frame was drawn at
+    pub round: usize,
+    /// root portal the frame belongs to
+    pub portal: usize,
+    pub primitive: P,
+}
+
+/// Called with the root portal whose window got closed
+type CloseHandler<H> = Box<dyn FnMut(&mut H,usize)>;
+
+pub struct Application<H: Host<Event = Event>>
 {
     vp: Viewport,
     host: H,
+    on_close: Option<CloseHandler<H>>,
 }
 
-//TODO: finish this: make wgpu and host to work
-impl<H: Host<Event = WindowEvent<'static>>> Application<H> {
+impl<H: Host<Event = Event>> Application<H> {
+
+    /// `host` is expected to have its root entity set up; `vp` is the initial size of windows, in logical pixels.
+    /// Headless runs render at `vp` as is, until the window of a root portal is resized
+    pub fn new(host: H, vp: Viewport) -> Self {
+        Self { vp, host, on_close: None }
+    }
+
+    pub fn host(&mut self) -> &mut H {
+        &mut self.host
+    }
 
-    pub fn new(vp: Viewport) -> Self {
-        unimplemented!()
+    /// Send a message built by `f` to the root entity whenever the window of a root portal is closed
+    pub fn on_close<S: System<H>>(mut self, f: fn(usize) -> S::Message) -> Self where H: Hosts<S> {
+        self.on_close = Some(Box::new(move |host: &mut H, portal| {
+            if let Some(root) = host.get_root_entity() {
+                <H as Hosts<S>>::send(host,f(portal),root);
+            }
+        }));
+        self
     }
 
-    pub fn run(&mut self) {
-        //ScaleFactorChanged event SHOULD NOT go further through event loop.
-        let event_loop = EventLoop::new();
-        let window = WindowBuilder::new()
-            .with_resizable(true)
-            // .with_inner_size((self.vp.height,self.vp.width)) //todo: fix this
-            .build(&event_loop).unwrap();
+    /// Open a window for every root portal and run the application loop until all of them are closed,
+    /// or until a window can't present its frames anymore
+    pub fn run(&mut self) -> Result<(),wgpu::SurfaceError>
+        where H::EntityData: for<'c> Visitor<'c, H::Primitive>, H::Primitive: Present
+    {
+        let mut event_loop = EventLoop::new();
+        let instance = wgpu::Instance::new(wgpu::Backends::all());
+        let mut windows: HashMap<_,_> = (0..self.host.get_root_portal_count()).map(|portal| {
+            let w = WindowBuilder::new()
+                .with_resizable(true)
+                .with_inner_size(LogicalSize::new(self.vp.width,self.vp.height))
+                .build(&event_loop).expect("failed to open a window");
+            self.host.set_scale_factor(portal,ScaleFactor(w.scale_factor()));
+            (w.id(),window::Window::new(&instance,w,portal))
+        }).collect();
+        let gpu = match windows.values().next() {
+            Some(w) => window::Gpu::new(&instance,w.surface()),
+            None => return Ok(()),
+        };
+
+        let mut batch = vec![];
+        let mut closed = vec![];
+        let mut failure = None;
+        event_loop.run_return(|event, _, control_flow| {
+            *control_flow = ControlFlow::Poll;
+            match event {
+                LoopEvent::WindowEvent { window_id, event } => {
+                    let Some(window) = windows.get(&window_id) else { return };
+                    // the window is given the size suggested with the new scale, the next frame is drawn at it;
+                    // components learn about the change through `Renderer::scale_factor`, so the event goes no further
+                    if let WindowEvent::ScaleFactorChanged { scale_factor, .. } = event {
+                        self.host.set_scale_factor(window.portal,ScaleFactor(scale_factor));
+                        window.window.request_redraw();
+                        return;
+                    }
+                    if event == WindowEvent::CloseRequested {
+                        closed.push(window_id);
+                    }
+                    if let Some(event) = event.to_static() {
+                        batch.push(Event { window: window.portal, event });
+                    }
+                }
+                LoopEvent::MainEventsCleared => {
+                    // receive event batch
+                    self.host.receive_events(batch.iter());
+                    batch.clear();
+                    for id in closed.drain(..) {
+                        if let (Some(window),Some(f)) = (windows.remove(&id),self.on_close.as_mut()) {
+                            f(&mut self.host,window.portal);
+                        }
+                    }
+                    // run update round
+                    self.host.update_round();
+                    if windows.is_empty() {
+                        *control_flow = ControlFlow::Exit;
+                        return;
+                    }
+                    for w in windows.values() {
+                        w.window.request_redraw();
+                    }
+                }
+                // draw
+                LoopEvent::RedrawRequested(window_id) => {
+                    if let Some(w) = windows.get_mut(&window_id) {
+                        let vp = w.viewport();
+                        let mut presented = Ok(());
+                        self.host.render(w.portal,vp,|primitive| presented = w.present(&gpu,&primitive.rgba8(),primitive.size()));
+                        match presented {
+                            // the frame is skipped, the next one is drawn as usual
+                            Ok(()) | Err(wgpu::SurfaceError::Timeout) => {}
+                            Err(e) => {
+                                failure = Some(e);
+                                *control_flow = ControlFlow::Exit;
+                            }
+                        }
+                    }
+                }
+                _ => {}
+            }
+        });
+        failure.map_or(Ok(()),Err)
+    }
+
+    /// Run the application loop without a window: events come from `events`,
+    /// every root portal drawn goes to `sink`. A `Resized` event sets the size its root portal is drawn at from the next frame on.
+    /// The loop halts right before receiving a batch once `events` is exhausted, so there is always one more frame than batches.
+    pub fn run_headless_with(&mut self, mut events: impl EventSource<H::Event>, mut sink: impl FnMut(Frame<H::Primitive>))
+        where H::EntityData: for<'c> Visitor<'c, H::Primitive>
+    {
+        let mut round = 0;
+        let mut sizes = HashMap::new();
+        loop {
+            // draw
+            for portal in 0..self.host.get_root_portal_count() {
+                let vp = sizes.get(&portal).copied().unwrap_or(self.vp);
+                self.host.render(portal,vp,|primitive| sink(Frame { round, portal, primitive }));
+            }
+            // receive event batch
+            let batch = match events.next_batch() {
+                Some(batch) => batch,
+                None => break,
+            };
+            for ev in batch.iter() {
+                if let WindowEvent::Resized(size) = ev.event {
+                    sizes.insert(ev.window,Viewport { width: size.width, height: size.height });
+                }
+            }
+            self.host.receive_events(batch.iter());
+            // run update round
+            self.host.update_round();
+            round += 1;
+        }
+    }
 
+    /// Same as `run_headless_with`, but collects frames in memory
+    pub fn run_headless(&mut self, events: impl EventSource<H::Event>) -> Vec<Frame<H::Primitive>>
+        where H::EntityData: for<'c> Visitor<'c, H::Primitive>
+    {
+        let mut frames = vec![];
+        self.run_headless_with(events,|frame| frames.push(frame));
+        frames
     }
 }
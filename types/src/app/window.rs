@@ -0,0 +1,236 @@
+//! Windows of an application, each showing one root portal on its own surface.
+//! Frames are uploaded into a texture, which is then stretched over the surface.
+use std::borrow::Cow;
+use std::num::NonZeroU32;
+
+use crate::render::Viewport;
+
+const SHADER: &str = r#"
+struct VertexOutput {
+    [[builtin(position)]] position: vec4<f32>;
+    [[location(0)]] uv: vec2<f32>;
+};
+
+[[stage(vertex)]]
+fn vs_main([[builtin(vertex_index)]] i: u32) -> VertexOutput {
+    // a triangle covering the whole surface
+    let x = f32(i32(i & 1u) * 4 - 1);
+    let y = f32(i32(i >> 1u) * 4 - 1);
+    var out: VertexOutput;
+    out.position = vec4<f32>(x, y, 0.0, 1.0);
+    out.uv = vec2<f32>((x + 1.0) / 2.0, (1.0 - y) / 2.0);
+    return out;
+}
+
+[[group(0), binding(0)]]
+var frame: texture_2d<f32>;
+[[group(0), binding(1)]]
+var frame_sampler: sampler;
+
+[[stage(fragment)]]
+fn fs_main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+    return textureSample(frame, frame_sampler, in.uv);
+}
+"#;
+
+/// A device shared by all windows
+pub(super) struct Gpu {
+    adapter: wgpu::Adapter,
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    shader: wgpu::ShaderModule,
+    layout: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
+}
+
+impl Gpu {
+    /// Open a device able to present to `compatible`
+    pub(super) fn new(instance: &wgpu::Instance, compatible: &wgpu::Surface) -> Self {
+        let adapter = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::default(),
+            force_fallback_adapter: false,
+            compatible_surface: Some(compatible),
+        })).expect("no suitable graphics adapter");
+        let (device,queue) = pollster::block_on(adapter.request_device(&wgpu::DeviceDescriptor::default(),None))
+            .expect("failed to open a graphics device");
+        let shader = device.create_shader_module(&wgpu::ShaderModuleDescriptor {
+            label: Some("present"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(SHADER)),
+        });
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("frame"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+        // frames are drawn at the size of their window, so there is nothing to filter
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            mag_filter: wgpu::FilterMode::Nearest,
+            min_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+        Self { adapter, device, queue, shader, layout, sampler }
+    }
+}
+
+/// What is configured for the current size of a window
+struct Target {
+    size: Viewport,
+    texture: wgpu::Texture,
+    bind_group: wgpu::BindGroup,
+}
+
+/// A window with the root portal it shows
+pub(super) struct Window {
+    // fields drop in order, the surface has to go before the window it refers to
+    surface: wgpu::Surface,
+    pipeline: Option<(wgpu::TextureFormat,wgpu::RenderPipeline)>,
+    target: Option<Target>,
+    pub(super) window: winit::window::Window,
+    pub(super) portal: usize,
+}
+
+impl Window {
+    pub(super) fn new(instance: &wgpu::Instance, window: winit::window::Window, portal: usize) -> Self {
+        let surface = unsafe { instance.create_surface(&window) };
+        Self { window, portal, surface, pipeline: None, target: None }
+    }
+
+    pub(super) fn surface(&self) -> &wgpu::Surface {
+        &self.surface
+    }
+
+    /// Size of the drawable area, in pixels
+    pub(super) fn viewport(&self) -> Viewport {
+        let size = self.window.inner_size();
+        Viewport { width: size.width, height: size.height }
+    }
+
+    /// Make the pipeline drawing into a surface of `format`, unless it's there already
+    fn pipeline(&mut self, gpu: &Gpu, format: wgpu::TextureFormat) {
+        if !matches!(&self.pipeline, Some((f,_)) if *f == format) {
+            let layout = gpu.device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("present"),
+                bind_group_layouts: &[&gpu.layout],
+                push_constant_ranges: &[],
+            });
+            let pipeline = gpu.device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some("present"),
+                layout: Some(&layout),
+                vertex: wgpu::VertexState { module: &gpu.shader, entry_point: "vs_main", buffers: &[] },
+                fragment: Some(wgpu::FragmentState {
+                    module: &gpu.shader,
+                    entry_point: "fs_main",
+                    targets: &[wgpu::ColorTargetState { format, blend: None, write_mask: wgpu::ColorWrites::ALL }],
+                }),
+                primitive: wgpu::PrimitiveState::default(),
+                depth_stencil: None,
+                multisample: wgpu::MultisampleState::default(),
+                multiview: None,
+            });
+            self.pipeline = Some((format,pipeline));
+        }
+    }
+
+    /// Configure the surface and the frame texture for `size`, unless they already are
+    fn configure(&mut self, gpu: &Gpu, size: Viewport) {
+        if self.target.as_ref().is_some_and(|t| t.size == size) {
+            return;
+        }
+        let format = self.surface.get_preferred_format(&gpu.adapter).unwrap_or(wgpu::TextureFormat::Bgra8UnormSrgb);
+        self.pipeline(gpu,format);
+        self.surface.configure(&gpu.device,&wgpu::SurfaceConfiguration {
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            format,
+            width: size.width,
+            height: size.height,
+            present_mode: wgpu::PresentMode::Fifo,
+        });
+        // frames hold sRGB encoded colors, the texture decodes them only if the surface encodes them back
+        let texture_format = if format.describe().srgb { wgpu::TextureFormat::Rgba8UnormSrgb } else { wgpu::TextureFormat::Rgba8Unorm };
+        let texture = gpu.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("frame"),
+            size: wgpu::Extent3d { width: size.width, height: size.height, depth_or_array_layers: 1 },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: texture_format,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let bind_group = gpu.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("frame"),
+            layout: &gpu.layout,
+            entries: &[
+                wgpu::BindGroupEntry { binding: 0, resource: wgpu::BindingResource::TextureView(&view) },
+                wgpu::BindGroupEntry { binding: 1, resource: wgpu::BindingResource::Sampler(&gpu.sampler) },
+            ],
+        });
+        self.target = Some(Target { size, texture, bind_group });
+    }
+
+    /// Show a frame of `size` pixels, given as RGBA bytes
+    pub(super) fn present(&mut self, gpu: &Gpu, rgba: &[u8], size: Viewport) -> Result<(),wgpu::SurfaceError> {
+        if size.width == 0 || size.height == 0 {
+            return Ok(());
+        }
+        self.configure(gpu,size);
+        let frame = match self.surface.get_current_texture() {
+            Ok(frame) => frame,
+            // the surface doesn't match the window anymore, it's configured anew and tried once more
+            Err(wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated) => {
+                self.target = None;
+                self.configure(gpu,size);
+                self.surface.get_current_texture()?
+            }
+            Err(e) => return Err(e),
+        };
+        let target = self.target.as_ref().expect("configured above");
+        gpu.queue.write_texture(
+            wgpu::ImageCopyTexture {
+                texture: &target.texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            rgba,
+            wgpu::ImageDataLayout { offset: 0, bytes_per_row: NonZeroU32::new(size.width * 4), rows_per_image: None },
+            wgpu::Extent3d { width: size.width, height: size.height, depth_or_array_layers: 1 },
+        );
+        let view = frame.texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let mut encoder = gpu.device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: Some("present") });
+        {
+            let (_,pipeline) = self.pipeline.as_ref().expect("configured above");
+            let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("present"),
+                color_attachments: &[wgpu::RenderPassColorAttachment {
+                    view: &view,
+                    resolve_target: None,
+                    ops: wgpu::Operations { load: wgpu::LoadOp::Clear(wgpu::Color::BLACK), store: true },
+                }],
+                depth_stencil_attachment: None,
+            });
+            pass.set_pipeline(pipeline);
+            pass.set_bind_group(0,&target.bind_group,&[]);
+            pass.draw(0..3,0..1);
+        }
+        gpu.queue.submit(Some(encoder.finish()));
+        frame.present();
+        Ok(())
+    }
+}
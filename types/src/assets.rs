@@ -0,0 +1,130 @@
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock, Weak};
+
+use crate::errors::assets::AssetError;
+
+/// Something which can be loaded from a file
+pub trait Asset: Send + Sync + Sized + 'static {
+    /// Decode an asset from the contents of the file at `path`
+    fn from_bytes(bytes: Vec<u8>, path: &Path) -> Result<Self,AssetError>;
+}
+
+/// Raw file contents, e.g. fonts
+impl Asset for Vec<u8> {
+    fn from_bytes(bytes: Vec<u8>, _path: &Path) -> Result<Self,AssetError> {
+        Ok(bytes)
+    }
+}
+
+/// UTF-8 text, e.g. stylesheets
+impl Asset for String {
+    fn from_bytes(bytes: Vec<u8>, path: &Path) -> Result<Self,AssetError> {
+        String::from_utf8(bytes).map_err(|e| AssetError::Decoding(format!("{}: {}",path.display(),e)))
+    }
+}
+
+/// Progress of an asset behind a handle
+pub enum LoadState<A> {
+    Loading,
+    Ready(Arc<A>),
+    Failed(AssetError),
+}
+
+impl<A> Clone for LoadState<A> {
+    fn clone(&self) -> Self {
+        match self {
+            LoadState::Loading => LoadState::Loading,
+            LoadState::Ready(a) => LoadState::Ready(a.clone()),
+            LoadState::Failed(e) => LoadState::Failed(e.clone()),
+        }
+    }
+}
+
+struct Slot<A> {
+    path: PathBuf,
+    state: RwLock<LoadState<A>>,
+}
+
+/// A typed, reference counted handle to an asset.
+/// Clones share the asset; it's freed once the last handle is dropped.
+pub struct Handle<A> {
+    slot: Arc<Slot<A>>,
+}
+
+impl<A> Clone for Handle<A> {
+    fn clone(&self) -> Self {
+        Self { slot: self.slot.clone() }
+    }
+}
+
+impl<A> Handle<A> {
+    /// A handle to an asset which is not loaded yet; hosts hand these out and `complete` them
+    pub fn loading<P: Into<PathBuf>>(path: P) -> Self {
+        Self { slot: Arc::new(Slot { path: path.into(), state: RwLock::new(LoadState::Loading) }) }
+    }
+
+    /// Store the outcome of loading, seen by every clone of the handle
+    pub fn complete(&self, result: Result<A,AssetError>) {
+        let state = match result {
+            Ok(a) => LoadState::Ready(Arc::new(a)),
+            Err(e) => LoadState::Failed(e),
+        };
+        *self.slot.state.write().unwrap_or_else(|e| e.into_inner()) = state;
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.slot.path
+    }
+
+    pub fn state(&self) -> LoadState<A> {
+        self.slot.state.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// The asset, if it's loaded
+    pub fn get(&self) -> Option<Arc<A>> {
+        match self.state() {
+            LoadState::Ready(a) => Some(a),
+            _ => None,
+        }
+    }
+
+    pub fn is_ready(&self) -> bool {
+        matches!(self.state(),LoadState::Ready(_))
+    }
+
+    /// Number of live handles to this asset
+    pub fn strong_count(&self) -> usize {
+        Arc::strong_count(&self.slot)
+    }
+
+    pub fn downgrade(&self) -> WeakHandle<A> {
+        WeakHandle { slot: Arc::downgrade(&self.slot) }
+    }
+
+    /// Whether both handles point to the same asset
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.slot,&other.slot)
+    }
+}
+
+/// A handle which doesn't keep the asset alive
+pub struct WeakHandle<A> {
+    slot: Weak<Slot<A>>,
+}
+
+impl<A> Clone for WeakHandle<A> {
+    fn clone(&self) -> Self {
+        Self { slot: self.slot.clone() }
+    }
+}
+
+impl<A> WeakHandle<A> {
+    pub fn upgrade(&self) -> Option<Handle<A>> {
+        self.slot.upgrade().map(|slot| Handle { slot })
+    }
+
+    /// Whether any strong handle is still around
+    pub fn is_alive(&self) -> bool {
+        self.slot.strong_count() > 0
+    }
+}
@@ -4,4 +4,41 @@ pub mod traits {
         IllFormedState,
     }
     pub struct AllocError;
-}
\ No newline at end of file
+}
+
+pub mod assets {
+    #[derive(Clone,Debug,PartialEq)]
+    pub enum AssetError {
+        /// the file couldn't be read
+        Io(std::io::ErrorKind),
+        /// the file was read but isn't a valid asset
+        Decoding(String),
+    }
+
+    impl From<std::io::Error> for AssetError {
+        fn from(e: std::io::Error) -> Self {
+            AssetError::Io(e.kind())
+        }
+    }
+}
+
+pub mod constraints {
+    #[derive(Clone,Debug,PartialEq)]
+    pub enum ConstraintError {
+        /// a required constraint contradicts the ones added before
+        Unsatisfiable,
+        /// the very same constraint was already added
+        Duplicate,
+        Internal(&'static str),
+    }
+
+    impl From<cassowary::AddConstraintError> for ConstraintError {
+        fn from(e: cassowary::AddConstraintError) -> Self {
+            match e {
+                cassowary::AddConstraintError::UnsatisfiableConstraint => ConstraintError::Unsatisfiable,
+                cassowary::AddConstraintError::DuplicateConstraint => ConstraintError::Duplicate,
+                cassowary::AddConstraintError::InternalSolverError(e) => ConstraintError::Internal(e),
+            }
+        }
+    }
+}
@@ -1,6 +1,12 @@
 extern crate winit;
 
 pub mod traits;
+pub mod anim;
 pub mod app;
+pub mod assets;
 pub mod errors;
-pub mod render;
\ No newline at end of file
+pub mod pointer;
+pub mod render;
+pub mod time;
+#[cfg(test)]
+mod test_host;
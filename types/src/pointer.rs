@@ -0,0 +1,55 @@
+//! Routing of pointer events: mouse and touch events go only to the entities under the pointer, along the nesting of components.
+//! They travel from the root down to the innermost entity (capture), then back up (bubble); any listener may stop them.
+use crate::render::{Anchor, Point};
+use crate::traits::Host;
+
+/// Way of a pointer event through the entities under the pointer
+#[derive(Clone,Copy,Debug,Default,Hash,Eq,PartialEq)]
+pub enum Phase {
+    /// from the root down to the innermost entity
+    Capture,
+    /// from the innermost entity up to the root
+    #[default]
+    Bubble,
+}
+
+/// What a listener makes of a pointer event, with the message to send to its entity if any
+#[derive(Clone,Debug,Eq,PartialEq)]
+pub enum Propagation<M> {
+    /// let the event go on
+    Continue(Option<M>),
+    /// entities after the current one don't see the event; other listeners of the current one still do
+    Stop(Option<M>),
+}
+
+impl<M> Propagation<M> {
+    pub fn is_stopped(&self) -> bool {
+        matches!(self,Propagation::Stop(_))
+    }
+
+    pub fn into_message(self) -> Option<M> {
+        match self {
+            Propagation::Continue(msg) | Propagation::Stop(msg) => msg,
+        }
+    }
+}
+
+/// Filters of `Context::subscribe` never stop events
+impl<M> From<Option<M>> for Propagation<M> {
+    fn from(msg: Option<M>) -> Self {
+        Propagation::Continue(msg)
+    }
+}
+
+/// Where a pointer event is, for the entity listening to it
+pub struct Target<H: Host + ?Sized> {
+    pub phase: Phase,
+    /// the innermost entity under the pointer
+    pub target: H::Index,
+    /// anchor of the listening entity's layout under the pointer
+    pub anchor: Anchor,
+    /// part of that layout under the pointer, `None` if it's the background
+    pub part: Option<usize>,
+    /// the pointer, relative to the view of the listening entity
+    pub at: Point<f32>,
+}
@@ -1,6 +1,10 @@
 use crate::traits::Host;
 use std::ops::{Range};
 
+pub mod constraints;
+pub mod display_list;
+pub mod flex;
+
 /// StyleData
 pub struct Style<H: crate::traits::Host + ?Sized> {
     pub weight: u16,
@@ -9,10 +13,7 @@ pub struct Style<H: crate::traits::Host + ?Sized> {
 
 impl<H: Host + ?Sized> Clone for Style<H> {
     fn clone(&self) -> Self {
-        Self {
-            weight: self.weight,
-            color: self.color,
-        }
+        *self
     }
 }
 
@@ -22,9 +23,20 @@ impl<H: Host + ?Sized> Copy for Style<H> {}
 /// An (x,y) point
 /// * Integer types serve for absolute screen space addressing
 /// * FP types server for logical addressing
-#[derive(Clone,Copy,Hash,Eq, PartialEq)]
+#[derive(Clone,Copy,Debug,Hash,Eq, PartialEq)]
 pub struct Point<T = u32>(T,T);
 
+impl<T: Copy> Point<T> {
+    /// horizontal coordinate
+    pub fn x(&self) -> T {
+        self.0
+    }
+    /// vertical coordinate
+    pub fn y(&self) -> T {
+        self.1
+    }
+}
+
 impl Point<u32> {
     pub fn absolute(x: u32,y: u32) -> Self {
         Point(x,y)
@@ -38,12 +50,84 @@ impl Point<f32> {
 }
 
 /// An rectangular of format (upper left corner,down right corner)
-#[derive(Clone,Copy,Hash,Eq, PartialEq)]
+#[derive(Clone,Copy,Debug,Hash,Eq, PartialEq)]
 pub struct Rect<L = u32,R =u32>(Point<L>,Point<R>);
 
 impl Rect<f32,f32> {
+    /// A rect covering the whole of the parent
     pub fn full_box() -> Self {
-        Rect(Point::relative(0.,0.),Point::relative(0.,0.))
+        Rect(Point::relative(0.,0.),Point::relative(1.,1.))
+    }
+
+    pub fn width(&self) -> f32 {
+        self.1.0 - self.0.0
+    }
+
+    pub fn height(&self) -> f32 {
+        self.1.1 - self.0.1
+    }
+
+    /// `self` is given relative to `outer`; get it relative to whatever `outer` is relative to
+    pub fn map_into(&self, outer: Rect<f32,f32>) -> Self {
+        Rect(
+            Point::relative(outer.0.0 + self.0.0 * outer.width(), outer.0.1 + self.0.1 * outer.height()),
+            Point::relative(outer.0.0 + self.1.0 * outer.width(), outer.0.1 + self.1.1 * outer.height()),
+        )
+    }
+
+    /// Inverse of `map_into`: get `self` relative to `inner`; the result may lie outside of the full box
+    pub fn relative_to(&self, inner: Rect<f32,f32>) -> Self {
+        let (w,h) = (inner.width(),inner.height());
+        let (w,h) = (if w == 0. { f32::EPSILON } else { w }, if h == 0. { f32::EPSILON } else { h });
+        Rect(
+            Point::relative((self.0.0 - inner.0.0) / w, (self.0.1 - inner.0.1) / h),
+            Point::relative((self.1.0 - inner.0.0) / w, (self.1.1 - inner.0.1) / h),
+        )
+    }
+
+    /// Common part of two rects, `None` if they don't overlap
+    pub fn intersection(&self, other: Rect<f32,f32>) -> Option<Self> {
+        let ul = Point::relative(self.0.0.max(other.0.0), self.0.1.max(other.0.1));
+        let dr = Point::relative(self.1.0.min(other.1.0), self.1.1.min(other.1.1));
+        if ul.0 < dr.0 && ul.1 < dr.1 {
+            Some(Rect(ul,dr))
+        } else {
+            None
+        }
+    }
+
+    /// Whether `other` lies within `self`, up to rounding errors
+    pub fn contains(&self, other: Rect<f32,f32>) -> bool {
+        const EPS: f32 = 1e-4;
+        other.0.0 >= self.0.0 - EPS && other.0.1 >= self.0.1 - EPS
+            && other.1.0 <= self.1.0 + EPS && other.1.1 <= self.1.1 + EPS
+    }
+
+    /// Whether a point lies within the rect
+    pub fn contains_point(&self, p: Point<f32>) -> bool {
+        p.0 >= self.0.0 && p.0 < self.1.0 && p.1 >= self.0.1 && p.1 < self.1.1
+    }
+
+    /// Bounding box of the rect transformed by `transform`, `vp` is the size of what the rect is relative to
+    pub fn transformed_bounds(&self, transform: &Transform, vp: Viewport) -> Self {
+        let t = transform.placed(*self,vp);
+        let (w,h) = (vp.width.max(1) as f32, vp.height.max(1) as f32);
+        let corners = [(self.0.0,self.0.1),(self.1.0,self.0.1),(self.1.0,self.1.1),(self.0.0,self.1.1)]
+            .map(|(x,y)| t.apply(Point(x * w,y * h)));
+        let (mut ul,mut dr) = (Point(f32::MAX,f32::MAX),Point(f32::MIN,f32::MIN));
+        for c in corners {
+            ul = Point(ul.0.min(c.0),ul.1.min(c.1));
+            dr = Point(dr.0.max(c.0),dr.1.max(c.1));
+        }
+        Rect(Point(ul.0 / w,ul.1 / h),Point(dr.0 / w,dr.1 / h))
+    }
+
+    /// Bounding box of two rects
+    pub fn union(&self, other: Rect<f32,f32>) -> Self {
+        Rect(
+            Point::relative(self.0.0.min(other.0.0), self.0.1.min(other.0.1)),
+            Point::relative(self.1.0.max(other.1.0), self.1.1.max(other.1.1)),
+        )
     }
 }
 
@@ -63,8 +147,8 @@ impl Rect<u32,u32> {
     }
 
     pub fn get_viewport(&self) -> Viewport {
-        let height = if self.1.1 >= self.0.1 { self.1.1 - self.0.1 } else { self.0.1 - self.1.1};
-        let width = if self.1.0 >= self.0.0 { self.1.0 - self.0.0 } else { self.0.0 - self.1.0};
+        let height = self.1.1.abs_diff(self.0.1);
+        let width = self.1.0.abs_diff(self.0.0);
         Viewport {
             height,
             width,
@@ -72,6 +156,15 @@ impl Rect<u32,u32> {
     }
 }
 
+impl<L: Copy,R: Copy> Rect<L,R> {
+    pub fn upper_left(&self) -> Point<L> {
+        self.0
+    }
+    pub fn down_right(&self) -> Point<R> {
+        self.1
+    }
+}
+
 impl<L,R> Rect<L,R> {
 
     pub fn zero() -> Rect<u32,u32> {
@@ -128,14 +221,128 @@ impl ZIndex {
 pub enum Filling<H: Host + ?Sized> {
     //We put there a component
     Component(H::Index,usize),
-    //We directly paint something here
-    Data(H::Primitive),
+    //We directly paint something here, pixels are shared with whoever draws them
+    Data(std::sync::Arc<H::Primitive>),
+    //We write a label here
+    Text(Text),
+    //We draw a vector shape here
+    Shape(Shape,Paint<<<H as Host>::Primitive as Primitive>::Color>),
+    //We show an image here, shared with the asset it was loaded from
+    Image(std::sync::Arc<H::Primitive>,Fit),
+}
+
+/// How an image is fitted into its rect
+#[derive(Clone,Copy,Debug,Eq,PartialEq)]
+pub enum Fit {
+    /// fill the rect, ignoring the aspect ratio
+    Stretch,
+    /// the largest size fitting into the rect, centered; keeps the aspect ratio
+    Contain,
+    /// the smallest size covering the rect, centered and cropped; keeps the aspect ratio
+    Cover,
+    /// corners keep their size, edges are stretched along and the center both ways;
+    /// insets are in pixels of the image: top, right, bottom, left
+    NineSlice([u32; 4]),
+}
+
+impl Fit {
+    /// Where a point of a `place` sized rect falls in an `image` sized image fitted into it, both relative;
+    /// `None` if no part of the image is drawn there
+    pub fn image_point(&self, at: Point<f32>, place: Viewport, image: Viewport) -> Option<Point<f32>> {
+        let (dw,dh) = (place.width as f32, place.height as f32);
+        let (sw,sh) = (image.width as f32, image.height as f32);
+        if dw <= 0. || dh <= 0. || sw <= 0. || sh <= 0. {
+            return None;
+        }
+        match *self {
+            Fit::Stretch => Some(at),
+            Fit::Contain => {
+                let scale = (dw / sw).min(dh / sh);
+                let (mx,my) = ((1. - sw * scale / dw) / 2., (1. - sh * scale / dh) / 2.);
+                let inside = (mx..=1. - mx).contains(&at.0) && (my..=1. - my).contains(&at.1);
+                inside.then(|| Point((at.0 - mx) / (1. - 2. * mx),(at.1 - my) / (1. - 2. * my)))
+            }
+            Fit::Cover => {
+                let scale = (dw / sw).max(dh / sh);
+                // visible fraction of the image
+                let (fx,fy) = (dw / scale / sw,dh / scale / sh);
+                Some(Point((1. - fx) / 2. + at.0 * fx,(1. - fy) / 2. + at.1 * fy))
+            }
+            Fit::NineSlice([top,right,bottom,left]) => {
+                // corners keep their pixels, the rest is stretched, as in `Primitive::draw_image`
+                let axis = |at: f32, d: f32, s: f32, lo: u32, hi: u32| {
+                    let k = (d / (lo + hi).max(1) as f32).min(1.);
+                    let (lo,hi) = ((lo as f32).min(s),(hi as f32).min(s));
+                    let px = at * d;
+                    let x = if px < lo * k {
+                        px / k
+                    } else if px > d - hi * k {
+                        s - (d - px) / k
+                    } else {
+                        lo + (px - lo * k) / (d - (lo + hi) * k).max(f32::EPSILON) * (s - lo - hi)
+                    };
+                    x / s
+                };
+                Some(Point(axis(at.0,dw,sw,left,right),axis(at.1,dh,sh,top,bottom)))
+            }
+        }
+    }
+}
+
+/// Horizontal alignment of text within its rect; lines are centered vertically
+#[derive(Clone,Copy,Debug,Eq,PartialEq)]
+pub enum TextAlign {
+    Left,
+    Center,
+    Right,
+}
+
+/// A label; lines are separated by `\n`, text not fitting its rect is clipped
+#[derive(Clone,Debug,PartialEq)]
+pub struct Text {
+    pub content: std::borrow::Cow<'static,str>,
+    /// font size, in pixels of the primitive it's drawn into
+    pub size: f32,
+    pub align: TextAlign,
+    /// style used for the text, like `text/body`;
+    /// if there is no such style its ancestors are tried: `text`
+    pub style: std::borrow::Cow<'static,std::path::Path>,
+}
+
+impl Text {
+    /// Left aligned `text/body` label
+    pub fn new<S: Into<std::borrow::Cow<'static,str>>>(content: S, size: f32) -> Self {
+        Self {
+            content: content.into(),
+            size,
+            align: TextAlign::Left,
+            style: std::borrow::Cow::Borrowed(std::path::Path::new("text/body")),
+        }
+    }
+
+    pub fn align(mut self, align: TextAlign) -> Self {
+        self.align = align;
+        self
+    }
+
+    pub fn style<P: Into<std::path::PathBuf>>(mut self, style: P) -> Self {
+        self.style = std::borrow::Cow::Owned(style.into());
+        self
+    }
+
+    /// Resolve the style of the text: the closest ancestor of `style` having an entry in `table`
+    pub fn resolve_style<H: Host + ?Sized>(&self, table: &dyn StyleTable<H>) -> Option<Style<H>> {
+        self.style.ancestors()
+            .filter(|p| !p.as_os_str().is_empty())
+            .find_map(|p| table.get(p))
+    }
 }
 
 /// An Visitor for producing render-able primitives
-pub trait Visitor<P: Primitive> {
+/// `'c` is the lifetime of data the visitor context borrows
+pub trait Visitor<'c, P: Primitive> {
     /// A type for ctx of visitor
-    type Ctx: Default;
+    type Ctx;
 
     fn visit(&self, ctx: Self::Ctx) -> P;
 }
@@ -147,6 +354,7 @@ pub trait Primitive {
     /// Copy another primitive into a part of current one; edge cases ruled out as follows:
     /// * In case of `src` being smaller than `place` scaling up takes a place;
     /// * In case of `src` being larger than `place` `src` is first resized to fit given place
+    ///
     /// This operation should respect transparency of `src`
     fn copy_from(&mut self,place: Rect<f32,f32>,src: &Self);
     /// Copy a part of primitive
@@ -155,10 +363,294 @@ pub trait Primitive {
     fn resize(&self,scale: (f32,f32)) -> Self;
     /// Associated function returning blank (an empty and fully transparent) primitive;
     fn blank(size: Viewport) -> Self;
+    /// Make a part of primitive fully transparent
+    fn clear(&mut self,part: Rect);
+    /// Size of primitive in pixels
+    fn size(&self) -> Viewport;
+    /// Restrict all drawing to a part of primitive; `None` lifts the restriction
+    fn set_scissor(&mut self,part: Option<Rect>);
+    /// Like `copy_from`, with `src` transformed around the center of `place` (in pixels of `self`).
+    /// By default `src` is stretched over the bounding box of the transformed place
+    fn copy_transformed(&mut self,place: Rect<f32,f32>,src: &Self,transform: Transform) where Self: Sized {
+        let bounds = place.transformed_bounds(&transform,self.size());
+        self.copy_from(bounds,src)
+    }
+    /// Like `copy_from`, with `src` transformed, faded and mixed in as `params` say.
+    /// By default opacity and the blend mode are ignored
+    fn blend_from(&mut self,place: Rect<f32,f32>,src: &Self,params: Compositing) where Self: Sized {
+        match params.transform {
+            Some(transform) => self.copy_transformed(place,src,transform),
+            None => self.copy_from(place,src),
+        }
+    }
+    /// Approximate amount of memory held by the primitive, in bytes; used for cache budgets
+    fn footprint(&self) -> usize where Self: Sized {
+        std::mem::size_of::<Self>()
+    }
+    /// Draw `image` into `place` according to `fit`
+    fn draw_image(&mut self,place: Rect<f32,f32>,image: &Self,fit: Fit) where Self: Sized {
+        let (size,src) = (self.size(),image.size());
+        // size of the place in pixels
+        let (dw,dh) = (place.width() * size.width as f32, place.height() * size.height as f32);
+        if dw <= 0. || dh <= 0. || src.width == 0 || src.height == 0 {
+            return;
+        }
+        let (sw,sh) = (src.width as f32, src.height as f32);
+        let (ul,dr) = (place.upper_left(),place.down_right());
+        match fit {
+            Fit::Stretch => self.copy_from(place,image),
+            Fit::Contain => {
+                let scale = (dw / sw).min(dh / sh);
+                // margins as fractions of the place
+                let (mx,my) = ((1. - sw * scale / dw) / 2., (1. - sh * scale / dh) / 2.);
+                let inner = Rect(Point(mx,my),Point(1. - mx,1. - my)).map_into(place);
+                let resized = image.resize((scale,scale));
+                self.copy_from(inner,&resized);
+            }
+            Fit::Cover => {
+                let scale = (dw / sw).max(dh / sh);
+                // visible part of the image, in its pixels
+                let (vw,vh) = ((dw / scale).round() as u32, (dh / scale).round() as u32);
+                let (x,y) = ((src.width - vw.min(src.width)) / 2, (src.height - vh.min(src.height)) / 2);
+                let visible = image.cut(Rect(Point(x,y),Point(x + vw,y + vh)));
+                self.copy_from(place,&visible);
+            }
+            Fit::NineSlice([top,right,bottom,left]) => {
+                // corners shrink proportionally when the place is too small for them
+                let kx = (dw / (left + right).max(1) as f32).min(1.);
+                let ky = (dh / (top + bottom).max(1) as f32).min(1.);
+                let (l,r) = (left as f32 * kx / dw * place.width(), right as f32 * kx / dw * place.width());
+                let (t,b) = (top as f32 * ky / dh * place.height(), bottom as f32 * ky / dh * place.height());
+                let xs = [(0,ul.x()),(left.min(src.width),ul.x() + l),(src.width - right.min(src.width),dr.x() - r),(src.width,dr.x())];
+                let ys = [(0,ul.y()),(top.min(src.height),ul.y() + t),(src.height - bottom.min(src.height),dr.y() - b),(src.height,dr.y())];
+                for row in ys.windows(2) {
+                    for col in xs.windows(2) {
+                        let (from,to) = (Point(col[0].0,row[0].0),Point(col[1].0,row[1].0));
+                        if from.0 >= to.0 || from.1 >= to.1 || col[0].1 >= col[1].1 || row[0].1 >= row[1].1 {
+                            continue;
+                        }
+                        let cell = image.cut(Rect(from,to));
+                        self.copy_from(Rect(Point(col[0].1,row[0].1),Point(col[1].1,row[1].1)),&cell);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// A segment of a `Shape::Path`
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub enum PathSegment {
+    /// start a new contour
+    MoveTo(Point<f32>),
+    LineTo(Point<f32>),
+    /// quadratic bezier: (control,end)
+    QuadTo(Point<f32>,Point<f32>),
+    /// cubic bezier: (first control,second control,end)
+    CubicTo(Point<f32>,Point<f32>,Point<f32>),
+    /// close the current contour
+    Close,
+}
+
+/// A vector shape; coordinates are relative to the rect it's drawn into, lengths are in pixels
+#[derive(Clone,Debug,PartialEq)]
+pub enum Shape {
+    /// radii of corners go clockwise from the upper left one
+    Rect { rect: Rect<f32,f32>, radii: [f32; 4] },
+    Line { from: Point<f32>, to: Point<f32> },
+    Ellipse { bounds: Rect<f32,f32> },
+    /// filled with the non-zero rule, unclosed contours are closed for filling only;
+    /// segments before the first `MoveTo` start at the upper left corner of the place
+    Path(Vec<PathSegment>),
+}
+
+impl Shape {
+    /// A rect covering the whole place
+    pub fn rect() -> Self {
+        Shape::Rect { rect: Rect::full_box(), radii: [0.; 4] }
+    }
+
+    /// A rect covering the whole place with all corners rounded by `radius`
+    pub fn rounded_rect(radius: f32) -> Self {
+        Shape::Rect { rect: Rect::full_box(), radii: [radius; 4] }
+    }
+
+    /// An ellipse inscribed into the place
+    pub fn ellipse() -> Self {
+        Shape::Ellipse { bounds: Rect::full_box() }
+    }
+
+    pub fn line(from: Point<f32>, to: Point<f32>) -> Self {
+        Shape::Line { from, to }
+    }
+}
+
+/// How a shape is painted: it's filled first, then stroked
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub struct Paint<C> {
+    pub fill: Option<C>,
+    /// color and width in pixels, centered on the outline
+    pub stroke: Option<(C,f32)>,
+}
+
+impl<C> Paint<C> {
+    pub fn fill(color: C) -> Self {
+        Self { fill: Some(color), stroke: None }
+    }
+
+    pub fn stroke(color: C, width: f32) -> Self {
+        Self { fill: None, stroke: Some((color,width)) }
+    }
+
+    pub fn with_stroke(mut self, color: C, width: f32) -> Self {
+        self.stroke = Some((color,width));
+        self
+    }
+}
+
+/// Primitives able to draw anti-aliased vector shapes
+pub trait ShapePrimitive: Primitive {
+    /// Fill the interior of `shape` placed into `place`
+    fn fill_shape(&mut self,place: Rect<f32,f32>,shape: &Shape,color: Self::Color);
+    /// Stroke the outline of `shape` placed into `place`, `width` is in pixels.
+    /// Joins are mitered, or bevelled when the miter is over 4 widths long, and ends are butt, as SVG does by default
+    fn stroke_shape(&mut self,place: Rect<f32,f32>,shape: &Shape,color: Self::Color,width: f32);
+
+    fn draw_shape(&mut self,place: Rect<f32,f32>,shape: &Shape,paint: &Paint<Self::Color>) {
+        if let Some(color) = paint.fill {
+            self.fill_shape(place,shape,color);
+        }
+        if let Some((color,width)) = paint.stroke {
+            self.stroke_shape(place,shape,color,width);
+        }
+    }
+}
+
+/// Primitives able to rasterise text
+pub trait TextPrimitive: Primitive {
+    /// Draw `text` inside of `place` with a resolved style
+    fn draw_text(&mut self,place: Rect<f32,f32>,text: &Text,color: Self::Color,weight: u16);
+}
+
+/// A 2D affine transform of pixel space: `x' = a*x + c*y + e`, `y' = b*x + d*y + f`
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub struct Transform {
+    pub a: f32,
+    pub b: f32,
+    pub c: f32,
+    pub d: f32,
+    pub e: f32,
+    pub f: f32,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+impl Transform {
+    pub const IDENTITY: Transform = Transform { a: 1., b: 0., c: 0., d: 1., e: 0., f: 0. };
+
+    /// Move by `(x,y)` pixels
+    pub fn translate(x: f32, y: f32) -> Self {
+        Transform { e: x, f: y, ..Self::IDENTITY }
+    }
+
+    pub fn scale(sx: f32, sy: f32) -> Self {
+        Transform { a: sx, d: sy, ..Self::IDENTITY }
+    }
+
+    /// Rotate clockwise on screen, by `radians`
+    pub fn rotate(radians: f32) -> Self {
+        let (sin,cos) = radians.sin_cos();
+        Transform { a: cos, b: sin, c: -sin, d: cos, ..Self::IDENTITY }
+    }
+
+    /// Apply `self` first, then `next`
+    pub fn then(&self, next: Transform) -> Self {
+        Transform {
+            a: next.a * self.a + next.c * self.b,
+            b: next.b * self.a + next.d * self.b,
+            c: next.a * self.c + next.c * self.d,
+            d: next.b * self.c + next.d * self.d,
+            e: next.a * self.e + next.c * self.f + next.e,
+            f: next.b * self.e + next.d * self.f + next.f,
+        }
+    }
+
+    /// The same transform, but centered at `origin` instead of `(0,0)`
+    pub fn around(&self, origin: Point<f32>) -> Self {
+        Transform::translate(-origin.0,-origin.1).then(*self).then(Transform::translate(origin.0,origin.1))
+    }
+
+    /// The transform of a rect `place` relative to a `vp` sized area: centered at the middle of `place`, in pixels of `vp`
+    pub fn placed(&self, place: Rect<f32,f32>, vp: Viewport) -> Self {
+        let center = Point(
+            (place.0.0 + place.1.0) / 2. * vp.width as f32,
+            (place.0.1 + place.1.1) / 2. * vp.height as f32,
+        );
+        self.around(center)
+    }
+
+    pub fn apply(&self, p: Point<f32>) -> Point<f32> {
+        Point(self.a * p.0 + self.c * p.1 + self.e, self.b * p.0 + self.d * p.1 + self.f)
+    }
+
+    /// `None` for degenerate transforms, e.g. scaling by zero
+    pub fn invert(&self) -> Option<Self> {
+        let det = self.a * self.d - self.b * self.c;
+        if det.abs() < f32::EPSILON {
+            return None;
+        }
+        let (a,b,c,d) = (self.d / det, -self.b / det, -self.c / det, self.a / det);
+        Some(Transform { a, b, c, d, e: -(a * self.e + c * self.f), f: -(b * self.e + d * self.f) })
+    }
+
+    pub fn is_identity(&self) -> bool {
+        *self == Self::IDENTITY
+    }
+}
+
+/// How colors of a layer are mixed with what's beneath it
+#[derive(Clone,Copy,Debug,Default,Hash,Eq,PartialEq)]
+pub enum BlendMode {
+    /// the layer is painted over
+    #[default]
+    Normal,
+    /// darkens: colors are multiplied
+    Multiply,
+    /// lightens: inverted colors are multiplied
+    Screen,
+    /// colors are summed up
+    Additive,
+}
+
+/// How a primitive is composited into another one
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub struct Compositing {
+    /// applied around the center of the place, in pixels of the destination
+    pub transform: Option<Transform>,
+    /// `0.0` is invisible, `1.0` is opaque
+    pub opacity: f32,
+    pub blend: BlendMode,
+}
+
+impl Default for Compositing {
+    fn default() -> Self {
+        Self { transform: None, opacity: 1., blend: BlendMode::Normal }
+    }
+}
+
+impl Compositing {
+    /// Plain `copy_from` does the same
+    pub fn is_plain(&self) -> bool {
+        self.transform.is_none() && self.opacity >= 1. && self.blend == BlendMode::Normal
+    }
 }
 
 /// A data structure describing absolute size of some part of screen space
-#[derive(Copy,Clone)]
+#[derive(Copy,Clone,Debug,Hash,Eq,PartialEq)]
 pub struct Viewport {
     pub height: u32,
     pub width: u32,
@@ -166,24 +658,265 @@ pub struct Viewport {
 
 impl Viewport {
     /// width to height ratio
-    fn ratio(&self) -> f32 {
+    pub fn ratio(&self) -> f32 {
         self.width as f32 / self.height as f32
     }
 
     /// as point
-    fn as_point(&self) -> Point<u32> {
+    pub fn as_point(&self) -> Point<u32> {
         Point(self.width,self.height)
     }
+
+    /// Pixel box covering a relative rect of this viewport, rounded outwards and clamped to it
+    pub fn pixel_box(&self, rect: Rect<f32,f32>) -> Rect<u32,u32> {
+        let clamp = |v: f32, max: u32| (v.max(0.) as u32).min(max);
+        Rect(
+            Point(clamp((rect.0.0 * self.width as f32).floor(),self.width), clamp((rect.0.1 * self.height as f32).floor(),self.height)),
+            Point(clamp((rect.1.0 * self.width as f32).ceil(),self.width), clamp((rect.1.1 * self.height as f32).ceil(),self.height)),
+        )
+    }
+
+    /// Pixel box of a relative rect of this viewport with edges truncated, as parts are placed,
+    /// so a scissor set to it doesn't bleed into a neighbouring part
+    pub fn scissor_box(&self, rect: Rect<f32,f32>) -> Rect<u32,u32> {
+        let clamp = |v: f32, max: u32| (v.max(0.) as u32).min(max);
+        Rect(
+            Point(clamp(rect.0.0 * self.width as f32,self.width), clamp(rect.0.1 * self.height as f32,self.height)),
+            Point(clamp(rect.1.0 * self.width as f32,self.width), clamp(rect.1.1 * self.height as f32,self.height)),
+        )
+    }
+
+    /// Relative rect of a pixel box of this viewport
+    pub fn relative_box(&self, rect: Rect<u32,u32>) -> Rect<f32,f32> {
+        let (w,h) = (self.width.max(1) as f32, self.height.max(1) as f32);
+        Rect(
+            Point(rect.0.0 as f32 / w, rect.0.1 as f32 / h),
+            Point(rect.1.0 as f32 / w, rect.1.1 as f32 / h),
+        )
+    }
+}
+
+/// Number of physical pixels per logical one, as reported by the window system
+#[derive(Copy,Clone,Debug,PartialEq)]
+pub struct ScaleFactor(pub f64);
+
+impl Default for ScaleFactor {
+    fn default() -> Self {
+        ScaleFactor(1.)
+    }
+}
+
+impl ScaleFactor {
+    /// Smallest physical pixel box covering a rect given in logical pixels
+    pub fn to_physical(&self, rect: Rect<f32,f32>) -> Rect<u32,u32> {
+        let s = self.0 as f32;
+        let px = |v: f32, round: fn(f32) -> f32| round(v * s).max(0.) as u32;
+        Rect(
+            Point(px(rect.0.0,f32::floor),px(rect.0.1,f32::floor)),
+            Point(px(rect.1.0,f32::ceil),px(rect.1.1,f32::ceil)),
+        )
+    }
+
+    /// A physical pixel box in logical pixels
+    pub fn to_logical(&self, rect: Rect<u32,u32>) -> Rect<f32,f32> {
+        let s = self.0 as f32;
+        Rect(
+            Point(rect.0.0 as f32 / s,rect.0.1 as f32 / s),
+            Point(rect.1.0 as f32 / s,rect.1.1 as f32 / s),
+        )
+    }
+
+    /// Size in physical pixels of a viewport given in logical ones
+    pub fn physical_viewport(&self, vp: Viewport) -> Viewport {
+        Viewport {
+            width: (vp.width as f64 * self.0).round() as u32,
+            height: (vp.height as f64 * self.0).round() as u32,
+        }
+    }
+
+    /// Size in logical pixels of a viewport given in physical ones.
+    /// Since relative rects don't depend on the scale, solving a `flex::Flex` for it sizes things in logical pixels
+    pub fn logical_viewport(&self, vp: Viewport) -> Viewport {
+        Viewport {
+            width: (vp.width as f64 / self.0).round() as u32,
+            height: (vp.height as f64 / self.0).round() as u32,
+        }
+    }
+}
+
+/// An entry of a layout
+pub struct Part<H: Host + ?Sized> {
+    /// containment rect, relative to the layout;
+    /// begins in left upper corner (x,y)
+    pub rect: Rect<f32,f32>,
+    pub filling: Filling<H>,
+    /// applied around the center of `rect`, in pixels of the layout
+    pub transform: Option<Transform>,
+    pub opacity: f32,
+    pub blend: BlendMode,
+}
+
+impl<H: Host + ?Sized> Part<H> {
+    pub fn new(rect: Rect<f32,f32>, filling: Filling<H>) -> Self {
+        Self { rect, filling, transform: None, opacity: 1., blend: BlendMode::Normal }
+    }
+
+    pub fn transform(mut self, transform: Transform) -> Self {
+        self.transform = Some(transform);
+        self
+    }
+
+    pub fn opacity(mut self, opacity: f32) -> Self {
+        self.opacity = opacity;
+        self
+    }
+
+    pub fn blend(mut self, blend: BlendMode) -> Self {
+        self.blend = blend;
+        self
+    }
+
+    pub fn compositing(&self) -> Compositing {
+        Compositing { transform: self.transform, opacity: self.opacity, blend: self.blend }
+    }
+
+    /// Area covered by the part, relative to a `layout_vp` sized layout
+    pub fn bounds(&self, layout_vp: Viewport) -> Rect<f32,f32> {
+        match &self.transform {
+            Some(t) => self.rect.transformed_bounds(t,layout_vp),
+            None => self.rect,
+        }
+    }
+
+    /// Map a point relative to a `layout_vp` sized layout into the part's rect; `None` if the part doesn't cover it
+    pub fn to_local(&self, p: Point<f32>, layout_vp: Viewport) -> Option<Point<f32>> {
+        to_local(self.rect,self.transform.as_ref(),p,layout_vp)
+    }
+}
+
+impl<H: Host + ?Sized> From<(Rect<f32,f32>,Filling<H>)> for Part<H> {
+    fn from((rect,filling): (Rect<f32,f32>,Filling<H>)) -> Self {
+        Self::new(rect,filling)
+    }
+}
+
+/// Map `p`, relative to a `vp` sized area, into `rect` transformed by `transform`; `None` if it's outside
+fn to_local(rect: Rect<f32,f32>, transform: Option<&Transform>, p: Point<f32>, vp: Viewport) -> Option<Point<f32>> {
+    let p = match transform {
+        Some(t) => {
+            let (w,h) = (vp.width.max(1) as f32, vp.height.max(1) as f32);
+            let q = t.placed(rect,vp).invert()?.apply(Point(p.0 * w,p.1 * h));
+            Point(q.0 / w,q.1 / h)
+        }
+        None => p,
+    };
+    if rect.contains_point(p) {
+        Some(Point((p.0 - rect.0.0) / rect.width(),(p.1 - rect.0.1) / rect.height()))
+    } else {
+        None
+    }
 }
 
 pub struct Layout<H: Host + ?Sized> {
     /// Size (relative to entities viewport)
     pub dims: Rect<f32,f32>,
-    /// Data format: (containment rect,its filling)
-    /// begins in left upper corner (x,y)
-    pub parts: Vec<(Rect<f32,f32>,Filling<H>)>,
+    /// drawn in order, the latter on top
+    pub parts: Vec<Part<H>>,
     /// background color, can be transparent
     pub bgc: <<H as Host>::Primitive as Primitive>::Color,
+    /// applied around the center of `dims`, in pixels of the entity
+    pub transform: Option<Transform>,
+    /// opacity of the whole layout, background included
+    pub opacity: f32,
+    /// how the layout is mixed with layouts beneath it
+    pub blend: BlendMode,
+}
+
+/// A suspicious entry of `Layout::parts`, parts are referred to by their position
+#[derive(Clone,Copy,Debug,Eq,PartialEq)]
+pub enum LayoutIssue {
+    /// the part sticks out of the layout; it's clipped
+    OutOfBounds(usize),
+    /// two parts cover a common area; the latter is drawn on top
+    Overlap(usize,usize),
+}
+
+impl<H: Host + ?Sized> Layout<H> {
+    /// An empty layout
+    pub fn new(dims: Rect<f32,f32>, bgc: <<H as Host>::Primitive as Primitive>::Color) -> Self {
+        Self { dims, parts: vec![], bgc, transform: None, opacity: 1., blend: BlendMode::Normal }
+    }
+
+    pub fn part<P: Into<Part<H>>>(mut self, part: P) -> Self {
+        self.parts.push(part.into());
+        self
+    }
+
+    /// Append parts solved from a container filling the layout; `vp` is the size of the layout in pixels
+    pub fn flex(mut self, flex: flex::Flex<H>, vp: Viewport) -> Self {
+        self.parts.extend(flex.into_parts(vp));
+        self
+    }
+
+    pub fn transform(mut self, transform: Transform) -> Self {
+        self.transform = Some(transform);
+        self
+    }
+
+    pub fn opacity(mut self, opacity: f32) -> Self {
+        self.opacity = opacity;
+        self
+    }
+
+    pub fn blend(mut self, blend: BlendMode) -> Self {
+        self.blend = blend;
+        self
+    }
+
+    pub fn compositing(&self) -> Compositing {
+        Compositing { transform: self.transform, opacity: self.opacity, blend: self.blend }
+    }
+
+    /// Area covered by the layout, relative to a `vp` sized entity
+    pub fn bounds(&self, vp: Viewport) -> Rect<f32,f32> {
+        match &self.transform {
+            Some(t) => self.dims.transformed_bounds(t,vp),
+            None => self.dims,
+        }
+    }
+
+    /// Map a point relative to a `vp` sized entity into the layout; `None` if the layout doesn't cover it
+    pub fn to_local(&self, p: Point<f32>, vp: Viewport) -> Option<Point<f32>> {
+        to_local(self.dims,self.transform.as_ref(),p,vp)
+    }
+
+    /// The topmost part under a point relative to a `vp` sized entity, with the point mapped into the part's rect.
+    /// Transforms of the layout and of its parts are respected
+    pub fn part_at(&self, p: Point<f32>, vp: Viewport) -> Option<(usize,Point<f32>)> {
+        let local = self.to_local(p,vp)?;
+        let layout_vp = Viewport {
+            width: (self.dims.width() * vp.width as f32).round() as u32,
+            height: (self.dims.height() * vp.height as f32).round() as u32,
+        };
+        self.parts.iter().enumerate().rev()
+            .find_map(|(i,part)| part.to_local(local,layout_vp).map(|p| (i,p)))
+    }
+
+    /// Check parts for overlaps and for sticking out of the layout, by their untransformed rects
+    pub fn validate(&self) -> Vec<LayoutIssue> {
+        let mut issues = vec![];
+        for (i,Part { rect, .. }) in self.parts.iter().enumerate() {
+            if !Rect::full_box().contains(*rect) {
+                issues.push(LayoutIssue::OutOfBounds(i));
+            }
+            for (j,Part { rect: other, .. }) in self.parts.iter().enumerate().skip(i + 1) {
+                if rect.intersection(*other).is_some() {
+                    issues.push(LayoutIssue::Overlap(i,j));
+                }
+            }
+        }
+        issues
+    }
 }
 
 pub enum StyleChange<'p,H: Host + ?Sized> {
@@ -215,9 +948,10 @@ pub trait StyleTable<H: Host + ?Sized> {
     fn scope(&mut self, shadow_commands: &[StyleShadow]) -> Box<dyn StyleTable<H>>;
 }
 
-#[derive(Clone,Hash,Eq, PartialEq)]
+#[derive(Clone,Debug,Hash,Eq,PartialEq)]
 pub struct Anchor(pub std::borrow::Cow<'static,str>, pub Point);
 
+#[allow(dead_code)]
 impl Anchor {
     fn from<S: Into<String>>(s: S,p: Point) -> Self {
         Self(std::borrow::Cow::Owned(s.into()),p)
@@ -233,9 +967,135 @@ pub trait Renderer<H: Host + ?Sized> {
     fn anchors(&mut self) -> &[Anchor];
     /// We attach layouts to labels
     fn layout(&mut self, layout: Option<Layout<H>>, label: Anchor, z_index: ZIndex);
+    /// Teleport a layout into a global overlay, drawn above the whole tree and clipped only by the window.
+    /// Its `dims` are relative to the layout attached at `origin`, it's shown while that one is; higher layers go on top.
+    /// `None` removes the overlay of `origin`
+    fn overlay(&mut self, overlay: Option<Layout<H>>, origin: Anchor, layer: isize);
     /// Here we can interact with styling.
     fn styles(&self) -> &dyn StyleTable<H>;
     /// Change StyleTable entity vise, in a new scope.
     fn patch_style_scope(&mut self, patch: &mut dyn FnMut(&mut dyn StyleTable<H>));
+    /// Scale factor of the window the component is shown in; the viewport is in physical pixels
+    fn scale_factor(&self) -> ScaleFactor;
+
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_host::TestHost;
+
+    use super::*;
+
+    fn close(p: Option<Point<f32>>, x: f32, y: f32) -> bool {
+        p.is_some_and(|p| (p.0 - x).abs() < 1e-3 && (p.1 - y).abs() < 1e-3)
+    }
+
+    #[test]
+    fn fitted_image_points() {
+        let (place,wide) = (Viewport { width: 40, height: 40 },Viewport { width: 20, height: 10 });
+        assert!(close(Fit::Stretch.image_point(Point(0.3,0.6),place,wide),0.3,0.6));
+        // drawn 40x20, 10 pixels of letterbox above and below
+        assert_eq!(Fit::Contain.image_point(Point(0.5,0.2),place,wide),None);
+        assert_eq!(Fit::Contain.image_point(Point(0.5,0.8),place,wide),None);
+        assert!(close(Fit::Contain.image_point(Point(0.25,0.5),place,wide),0.25,0.5));
+        assert!(close(Fit::Contain.image_point(Point(1.,0.25),place,wide),1.,0.));
+        // drawn 80x40, a quarter cropped on each side
+        assert!(close(Fit::Cover.image_point(Point(0.,0.5),place,wide),0.25,0.5));
+        assert!(close(Fit::Cover.image_point(Point(1.,1.),place,wide),0.75,1.));
+        // 2 pixel corners of a 10x10 image in 40x40: corners are kept, the middle stretched
+        let nine = Fit::NineSlice([2,2,2,2]);
+        let square = Viewport { width: 10, height: 10 };
+        assert!(close(nine.image_point(Point(1. / 40.,0.5),place,square),0.1,0.5));
+        assert!(close(nine.image_point(Point(39. / 40.,0.),place,square),0.9,0.));
+        assert!(close(nine.image_point(Point(0.5,0.5),place,square),0.5,0.5));
+        assert_eq!(Fit::Stretch.image_point(Point(0.5,0.5),place,Viewport { width: 0, height: 10 }),None);
+    }
+
+    /// Styles told apart by their weight
+    struct Weights(Vec<(&'static str,u16)>);
+
+    impl StyleTable<TestHost> for Weights {
+        fn get(&self, which: &std::path::Path) -> Option<Style<TestHost>> {
+            self.0.iter().find(|(p,_)| std::path::Path::new(p) == which).map(|(_,weight)| Style { weight: *weight, color: () })
+        }
+
+        fn update(&mut self, _: StyleChange<TestHost>) {}
+
+        fn scope(&mut self, _: &[StyleShadow]) -> Box<dyn StyleTable<TestHost>> {
+            Box::new(Weights(self.0.clone()))
+        }
+    }
+
+    #[test]
+    fn text_styles_fall_back_to_ancestors() {
+        let weight = |text: &Text,table: &Weights| text.resolve_style(table).map(|s| s.weight);
+        let table = Weights(vec![("text",400),("text/body",500),("text/body/strong",700)]);
+        assert_eq!(weight(&Text::new("a",10.),&table),Some(500));
+        assert_eq!(weight(&Text::new("a",10.).style("text/body/strong"),&table),Some(700));
+        assert_eq!(weight(&Text::new("a",10.).style("text/body/em/small"),&table),Some(500));
+        assert_eq!(weight(&Text::new("a",10.).style("text/title"),&table),Some(400));
+        assert_eq!(weight(&Text::new("a",10.).style("heading"),&table),None);
+        assert_eq!(weight(&Text::new("a",10.),&Weights(vec![("text",400)])),Some(400));
+        assert_eq!(weight(&Text::new("a",10.),&Weights(vec![])),None);
+    }
 
+    #[test]
+    fn viewports_round_trip_through_scales() {
+        let vp = |width,height| Viewport { width, height };
+        for (scale,physical) in [(1.25,vp(126,9)),(1.5,vp(152,11))] {
+            let scale = ScaleFactor(scale);
+            assert_eq!(scale.physical_viewport(vp(101,7)),physical);
+            assert_eq!(scale.logical_viewport(physical),vp(101,7));
+        }
+        // odd sizes are rounded to the closest pixel
+        assert_eq!(ScaleFactor(1.5).physical_viewport(vp(3,1)),vp(5,2));
+        assert_eq!(ScaleFactor(1.25).logical_viewport(vp(3,1)),vp(2,1));
+        assert_eq!(ScaleFactor(1.5).logical_viewport(vp(5,2)),vp(3,1));
+    }
+
+    #[test]
+    fn rects_round_trip_through_scales() {
+        let logical = Rect(Point(1.,1.),Point(3.,5.));
+        // physical boxes cover the logical rect
+        assert_eq!(ScaleFactor(1.25).to_physical(logical),Rect(Point(1,1),Point(4,7)));
+        assert_eq!(ScaleFactor(1.5).to_physical(logical),Rect(Point(1,1),Point(5,8)));
+        for scale in [1.25,1.5] {
+            let scale = ScaleFactor(scale);
+            assert!(scale.to_logical(scale.to_physical(logical)).contains(logical));
+        }
+        // whole physical pixels come back as they were
+        let even = Rect(Point(2.,2.),Point(4.,6.));
+        assert_eq!(ScaleFactor(1.5).to_physical(even),Rect(Point(3,3),Point(6,9)));
+        assert_eq!(ScaleFactor(1.5).to_logical(Rect(Point(3,3),Point(6,9))),even);
+        assert_eq!(ScaleFactor(1.25).to_logical(Rect(Point(5,5),Point(10,15))),Rect(Point(4.,4.),Point(8.,12.)));
+    }
+
+    #[test]
+    fn transforms_compose_in_order() {
+        let (shift,grow) = (Transform::translate(2.,0.),Transform::scale(2.,3.));
+        assert!(close(Some(shift.then(grow).apply(Point(1.,1.))),6.,3.));
+        assert!(close(Some(grow.then(shift).apply(Point(1.,1.))),4.,3.));
+        // clockwise on screen, y grows downwards
+        let quarter = Transform::rotate(std::f32::consts::FRAC_PI_2);
+        assert!(close(Some(quarter.apply(Point(1.,0.))),0.,1.));
+        assert!(close(Some(quarter.around(Point(1.,1.)).apply(Point(2.,1.))),1.,2.));
+        assert!(Transform::IDENTITY.then(grow) == grow && grow.then(Transform::IDENTITY) == grow);
+    }
+
+    #[test]
+    fn transforms_invert() {
+        let t = Transform::rotate(0.3).then(Transform::scale(2.,0.5)).then(Transform::translate(3.,-1.));
+        let inverse = t.invert().unwrap();
+        assert!(close(Some(inverse.apply(t.apply(Point(5.,7.)))),5.,7.));
+        assert!(close(Some(t.then(inverse).apply(Point(-2.,4.))),-2.,4.));
+        assert_eq!(Transform::translate(1.,2.).invert(),Some(Transform::translate(-1.,-2.)));
+        assert_eq!(Transform::scale(0.,1.).invert(),None);
+    }
+
+    #[test]
+    fn transformed_bounds_turn_with_the_rect() {
+        let vp = Viewport { width: 8, height: 8 };
+        let bounds = Rect(Point(0.,0.),Point(0.5,0.25)).transformed_bounds(&Transform::rotate(std::f32::consts::FRAC_PI_2),vp);
+        assert!(close(Some(bounds.0),0.125,-0.125) && close(Some(bounds.1),0.375,0.375));
+    }
 }
@@ -0,0 +1,438 @@
+//! Anchors placed by linear constraints, relative to the viewport and to each other.
+//! The constraints are solved with the Cassowary algorithm, and re-solved whenever the viewport changes.
+//! Values are in pixels.
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::ops::{Add, Mul, Neg, Sub};
+
+use cassowary::{strength, RelationalOperator, Solver, Term, Variable};
+
+use crate::errors::constraints::ConstraintError;
+use super::{Anchor, Point, Rect, Viewport};
+
+/// A side or a size of an anchor's rect
+#[derive(Clone,Copy,Debug,Eq,PartialEq,Hash)]
+pub enum Edge {
+    Left,
+    Right,
+    Top,
+    Bottom,
+    Width,
+    Height,
+    CenterX,
+    CenterY,
+}
+
+/// Whose edge an expression refers to
+#[derive(Clone,Debug,Eq,PartialEq,Hash)]
+enum Target {
+    Viewport,
+    Anchor(Cow<'static,str>),
+}
+
+/// Edges of an anchor or of the viewport, the starting point of expressions
+#[derive(Clone,Debug)]
+pub struct Edges(Target);
+
+impl Edges {
+    /// Edges of a named anchor
+    pub fn of<S: Into<Cow<'static,str>>>(name: S) -> Self {
+        Self(Target::Anchor(name.into()))
+    }
+
+    /// Edges of the viewport; its left and top are always 0
+    pub fn viewport() -> Self {
+        Self(Target::Viewport)
+    }
+
+    pub fn edge(&self, edge: Edge) -> Expr {
+        Expr { terms: vec![(self.0.clone(),edge,1.)], constant: 0. }
+    }
+
+    pub fn left(&self) -> Expr { self.edge(Edge::Left) }
+    pub fn right(&self) -> Expr { self.edge(Edge::Right) }
+    pub fn top(&self) -> Expr { self.edge(Edge::Top) }
+    pub fn bottom(&self) -> Expr { self.edge(Edge::Bottom) }
+    pub fn width(&self) -> Expr { self.edge(Edge::Width) }
+    pub fn height(&self) -> Expr { self.edge(Edge::Height) }
+    pub fn center_x(&self) -> Expr { self.edge(Edge::CenterX) }
+    pub fn center_y(&self) -> Expr { self.edge(Edge::CenterY) }
+}
+
+/// A linear combination of edges plus a constant
+#[derive(Clone,Debug)]
+pub struct Expr {
+    terms: Vec<(Target,Edge,f64)>,
+    constant: f64,
+}
+
+impl Expr {
+    fn relation(self, op: RelationalOperator, rhs: Expr) -> Constraint {
+        Constraint { expr: self - rhs, op, strength: Strength::Required }
+    }
+
+    /// `self == rhs`
+    pub fn eq<E: Into<Expr>>(self, rhs: E) -> Constraint {
+        self.relation(RelationalOperator::Equal,rhs.into())
+    }
+
+    /// `self >= rhs`
+    pub fn ge<E: Into<Expr>>(self, rhs: E) -> Constraint {
+        self.relation(RelationalOperator::GreaterOrEqual,rhs.into())
+    }
+
+    /// `self <= rhs`
+    pub fn le<E: Into<Expr>>(self, rhs: E) -> Constraint {
+        self.relation(RelationalOperator::LessOrEqual,rhs.into())
+    }
+}
+
+impl From<f32> for Expr {
+    fn from(v: f32) -> Self {
+        Expr { terms: vec![], constant: v as f64 }
+    }
+}
+
+impl<E: Into<Expr>> Add<E> for Expr {
+    type Output = Expr;
+
+    fn add(mut self, rhs: E) -> Expr {
+        let rhs = rhs.into();
+        self.terms.extend(rhs.terms);
+        self.constant += rhs.constant;
+        self
+    }
+}
+
+impl<E: Into<Expr>> Sub<E> for Expr {
+    type Output = Expr;
+
+    fn sub(self, rhs: E) -> Expr {
+        self + -rhs.into()
+    }
+}
+
+impl Mul<f32> for Expr {
+    type Output = Expr;
+
+    fn mul(mut self, k: f32) -> Expr {
+        for t in self.terms.iter_mut() {
+            t.2 *= k as f64;
+        }
+        self.constant *= k as f64;
+        self
+    }
+}
+
+impl Neg for Expr {
+    type Output = Expr;
+
+    fn neg(self) -> Expr {
+        self * -1.
+    }
+}
+
+/// How hard the solver tries to satisfy a constraint
+#[derive(Clone,Copy,Debug,Eq,PartialEq)]
+pub enum Strength {
+    /// must hold, adding a conflicting one fails
+    Required,
+    Strong,
+    Medium,
+    Weak,
+}
+
+impl Strength {
+    fn value(self) -> f64 {
+        match self {
+            Strength::Required => strength::REQUIRED,
+            Strength::Strong => strength::STRONG,
+            Strength::Medium => strength::MEDIUM,
+            Strength::Weak => strength::WEAK,
+        }
+    }
+}
+
+/// A relation between two expressions, e.g. `Edges::of("sidebar").right().eq(Edges::of("content").left())`
+#[derive(Clone,Debug)]
+pub struct Constraint {
+    /// `expr op 0`
+    expr: Expr,
+    op: RelationalOperator,
+    strength: Strength,
+}
+
+impl Constraint {
+    pub fn strength(mut self, strength: Strength) -> Self {
+        self.strength = strength;
+        self
+    }
+}
+
+/// Variables of an anchor's rect, the other edges are expressed through them
+#[derive(Clone,Copy)]
+struct Vars {
+    left: Variable,
+    top: Variable,
+    width: Variable,
+    height: Variable,
+}
+
+impl Vars {
+    fn new() -> Self {
+        Self { left: Variable::new(), top: Variable::new(), width: Variable::new(), height: Variable::new() }
+    }
+
+    /// Terms of an edge, with the coefficient it's taken with
+    fn terms(&self, edge: Edge, k: f64) -> Vec<Term> {
+        let t = |v,c| Term { variable: v, coefficient: c * k };
+        match edge {
+            Edge::Left => vec![t(self.left,1.)],
+            Edge::Top => vec![t(self.top,1.)],
+            Edge::Width => vec![t(self.width,1.)],
+            Edge::Height => vec![t(self.height,1.)],
+            Edge::Right => vec![t(self.left,1.),t(self.width,1.)],
+            Edge::Bottom => vec![t(self.top,1.),t(self.height,1.)],
+            Edge::CenterX => vec![t(self.left,1.),t(self.width,0.5)],
+            Edge::CenterY => vec![t(self.top,1.),t(self.height,0.5)],
+        }
+    }
+}
+
+/// A set of constrained anchors of a view.
+/// Anchors come into existence when a constraint first mentions them; their size never goes negative.
+pub struct Constraints {
+    solver: Solver,
+    /// constraints the solver holds, it's rebuilt from them after a failed addition
+    added: Vec<cassowary::Constraint>,
+    viewport: Vars,
+    anchors: HashMap<Cow<'static,str>,Vars>,
+    /// the viewport the current solution is for
+    solved: Option<Viewport>,
+}
+
+impl Default for Constraints {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Constraints {
+    pub fn new() -> Self {
+        let viewport = Vars::new();
+        let mut this = Self { solver: Self::solver(viewport), added: vec![], viewport, anchors: HashMap::new(), solved: None };
+        this.require([viewport.left,viewport.top].map(|v| cassowary::Constraint::new(
+            cassowary::Expression::from_term(Term { variable: v, coefficient: 1. }),RelationalOperator::Equal,strength::REQUIRED,
+        )).to_vec()).expect("fresh variables");
+        this
+    }
+
+    /// A solver taking the size of the viewport as an input
+    fn solver(viewport: Vars) -> Solver {
+        let mut solver = Solver::new();
+        // the viewport size is an input, which overrides anything but required constraints
+        let input = strength::REQUIRED - 1.;
+        for v in [viewport.width,viewport.height] {
+            solver.add_edit_variable(v,input).expect("a fresh variable");
+        }
+        solver
+    }
+
+    /// Add constraints to the solver, all of them or none; a failed addition may leave the solver inconsistent,
+    /// so it's rebuilt from the constraints accepted before
+    fn require(&mut self, constraints: Vec<cassowary::Constraint>) -> Result<(),ConstraintError> {
+        for constraint in constraints.iter() {
+            if let Err(e) = self.solver.add_constraint(constraint.clone()) {
+                let mut solver = Self::solver(self.viewport);
+                for c in self.added.iter() {
+                    solver.add_constraint(c.clone()).expect("constraints were accepted before");
+                }
+                self.solver = solver;
+                if let Some(vp) = self.solved.take() {
+                    self.resize(vp);
+                }
+                return Err(e.into());
+            }
+        }
+        self.added.extend(constraints);
+        Ok(())
+    }
+
+    /// Add a constraint, see `add`
+    pub fn with(mut self, constraint: Constraint) -> Result<Self,ConstraintError> {
+        self.add(constraint)?;
+        Ok(self)
+    }
+
+    /// Add a constraint; fails if it is required but contradicts the required ones added before.
+    /// Anchors it mentions first are only created once it's accepted
+    pub fn add(&mut self, constraint: Constraint) -> Result<(),ConstraintError> {
+        let Constraint { expr, op, strength } = constraint;
+        let (mut terms,mut fresh) = (vec![],vec![]);
+        for (target,edge,k) in expr.terms {
+            terms.extend(self.vars(target,&mut fresh).terms(edge,k));
+        }
+        let mut required: Vec<_> = fresh.iter().flat_map(|(_,vars): &(_,Vars)| [vars.width,vars.height]).map(|v| cassowary::Constraint::new(
+            cassowary::Expression::from_term(Term { variable: v, coefficient: 1. }),RelationalOperator::GreaterOrEqual,strength::REQUIRED,
+        )).collect();
+        required.push(cassowary::Constraint::new(cassowary::Expression::new(terms,expr.constant),op,strength.value()));
+        self.require(required)?;
+        self.anchors.extend(fresh);
+        Ok(())
+    }
+
+    /// Variables of a target; those of anchors not known yet are made and noted in `fresh`
+    fn vars(&self, target: Target, fresh: &mut Vec<(Cow<'static,str>,Vars)>) -> Vars {
+        let name = match target {
+            Target::Viewport => return self.viewport,
+            Target::Anchor(name) => name,
+        };
+        if let Some(vars) = self.anchors.get(&name).or_else(|| fresh.iter().find(|(n,_)| *n == name).map(|(_,v)| v)) {
+            return *vars;
+        }
+        let vars = Vars::new();
+        fresh.push((name,vars));
+        vars
+    }
+
+    /// Names of constrained anchors
+    pub fn names(&self) -> impl Iterator<Item = &Cow<'static,str>> {
+        self.anchors.keys()
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.anchors.contains_key(name)
+    }
+
+    /// Re-solve for a viewport, if the current solution isn't for it already.
+    /// Returns whether anything was solved
+    pub fn resize(&mut self, vp: Viewport) -> bool {
+        if self.solved == Some(vp) {
+            return false;
+        }
+        self.solver.suggest_value(self.viewport.width,vp.width as f64).expect("viewport size is an edit variable");
+        self.solver.suggest_value(self.viewport.height,vp.height as f64).expect("viewport size is an edit variable");
+        self.solved = Some(vp);
+        true
+    }
+
+    /// The viewport of the current solution
+    pub fn viewport(&self) -> Option<Viewport> {
+        self.solved
+    }
+
+    /// Rect of an anchor relative to the viewport of the current solution
+    pub fn rect(&self, name: &str) -> Option<Rect<f32,f32>> {
+        let vp = self.solved?;
+        let vars = self.anchors.get(name)?;
+        let (w,h) = (vp.width.max(1) as f64, vp.height.max(1) as f64);
+        let get = |v| self.solver.get_value(v);
+        let (left,top) = (get(vars.left),get(vars.top));
+        let (right,bottom) = (left + get(vars.width),top + get(vars.height));
+        Some(Rect(Point((left / w) as f32,(top / h) as f32),Point((right / w) as f32,(bottom / h) as f32)))
+    }
+
+    /// An anchor placed at the upper left corner of its solved rect, in pixels
+    pub fn anchor(&self, name: &str) -> Option<Anchor> {
+        let vp = self.solved?;
+        let rect = self.rect(name)?;
+        let (name,_) = self.anchors.get_key_value(name)?;
+        let x = (rect.0.0 * vp.width as f32).round().max(0.) as u32;
+        let y = (rect.0.1 * vp.height as f32).round().max(0.) as u32;
+        Some(Anchor(name.clone(),Point(x,y)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VP: Viewport = Viewport { width: 200, height: 100 };
+
+    /// Rect of an anchor as `[left,top,right,bottom]` in pixels of the solved viewport
+    fn px(c: &Constraints, name: &str) -> [f32;4] {
+        let vp = c.viewport().unwrap();
+        let r = c.rect(name).unwrap();
+        let (w,h) = (vp.width as f32,vp.height as f32);
+        [r.0.0 * w,r.0.1 * h,r.1.0 * w,r.1.1 * h].map(|v| (v * 1000.).round() / 1000.)
+    }
+
+    /// A sidebar taking a quarter of the width but at least 20px, and the content right of it
+    fn sidebar() -> Constraints {
+        let (side,content,vp) = (Edges::of("side"),Edges::of("content"),Edges::viewport());
+        Constraints::new()
+            .with(side.left().eq(0.)).unwrap()
+            .with(side.top().eq(0.)).unwrap()
+            .with(side.bottom().eq(vp.height())).unwrap()
+            .with(side.width().ge(20.)).unwrap()
+            .with((side.width() - vp.width() * 0.25).eq(0.).strength(Strength::Medium)).unwrap()
+            .with(side.right().eq(content.left())).unwrap()
+            .with(content.right().eq(vp.right())).unwrap()
+            .with(content.top().eq(0.)).unwrap()
+            .with(content.height().eq(vp.height())).unwrap()
+    }
+
+    #[test]
+    fn solved_on_resize() {
+        let mut c = sidebar();
+        assert!(c.rect("side").is_none());
+        assert!(c.resize(VP));
+        assert!(!c.resize(VP));
+        assert_eq!(px(&c,"side"),[0.,0.,50.,100.]);
+        assert_eq!(px(&c,"content"),[50.,0.,200.,100.]);
+        assert!(c.resize(Viewport { width: 400, height: 50 }));
+        assert_eq!(px(&c,"content"),[100.,0.,400.,50.]);
+        assert_eq!(c.anchor("content").map(|a| a.1),Some(Point(100,0)));
+        assert!(c.contains("side") && !c.contains("footer"));
+    }
+
+    #[test]
+    fn stronger_constraints_win() {
+        let mut c = sidebar();
+        // a quarter of 40px is less than the required minimum
+        c.resize(Viewport { width: 40, height: 10 });
+        assert_eq!(px(&c,"side"),[0.,0.,20.,10.]);
+        assert_eq!(px(&c,"content"),[20.,0.,40.,10.]);
+        // a weak preference loses to the medium one, a strong one beats it
+        c.add(Edges::of("side").width().eq(30.).strength(Strength::Weak)).unwrap();
+        c.resize(VP);
+        assert_eq!(px(&c,"side")[2],50.);
+        c.add(Edges::of("side").width().eq(30.).strength(Strength::Strong)).unwrap();
+        assert_eq!(px(&c,"side")[2],30.);
+        // the viewport itself can't be moved by anything but required constraints
+        c.add(Edges::viewport().width().eq(1000.).strength(Strength::Strong)).unwrap();
+        assert_eq!(px(&c,"content")[2],200.);
+    }
+
+    #[test]
+    fn conflicting_required_constraints() {
+        let side = Edges::of("side");
+        let mut c = Constraints::new().with(side.width().eq(10.)).unwrap();
+        assert_eq!(c.add(side.width().eq(20.)).err(),Some(ConstraintError::Unsatisfiable));
+        assert_eq!(c.add(side.width().ge(11.)).err(),Some(ConstraintError::Unsatisfiable));
+        // sizes never go negative
+        assert_eq!(c.add(Edges::of("other").height().eq(-5.)).err(),Some(ConstraintError::Unsatisfiable));
+        // anchors named first by a rejected constraint aren't created
+        assert!(!c.contains("other") && c.names().eq(["side"].iter()));
+        assert_eq!(c.add(side.left().eq(Edges::of("other").left()).strength(Strength::Weak)).err(),None);
+        assert!(c.contains("other"));
+        assert_eq!(c.add(Edges::of("other").width().le(-1.)).err(),Some(ConstraintError::Unsatisfiable));
+        // restating a required constraint is redundant, not a conflict
+        assert!(c.add(side.width().eq(10.)).is_ok());
+        // failed constraints leave the solution as it was
+        c.resize(VP);
+        assert_eq!(px(&c,"side")[2] - px(&c,"side")[0],10.);
+    }
+
+    #[test]
+    fn centers() {
+        let (dialog,vp) = (Edges::of("dialog"),Edges::viewport());
+        let mut c = Constraints::new()
+            .with(dialog.center_x().eq(vp.center_x())).unwrap()
+            .with(dialog.center_y().eq(vp.center_y())).unwrap()
+            .with(dialog.width().eq(vp.width() * 0.5)).unwrap()
+            .with(dialog.height().eq(40.)).unwrap();
+        c.resize(VP);
+        assert_eq!(px(&c,"dialog"),[50.,30.,150.,70.]);
+    }
+}
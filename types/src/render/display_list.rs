@@ -0,0 +1,199 @@
+//! Display lists: a tree of layouts flattened into a list of drawing commands, replayed into a primitive at once.
+//! Only layers with a transform, opacity or blend mode get a buffer of their own while replaying.
+//! Commands are kept in paint order, z-indices are resolved while a list is built.
+use std::sync::Arc;
+
+use super::{Compositing, Fit, Paint, Primitive, Rect, Shape, ShapePrimitive, Text, TextPrimitive, Viewport};
+
+/// What a command draws
+pub enum Draw<P: Primitive> {
+    /// a color all over the place
+    Fill(P::Color),
+    Shape(Shape,Paint<P::Color>),
+    /// a label, with the color and weight of its style
+    Text(Text,P::Color,u16),
+    Image(Arc<P>,Fit),
+}
+
+impl<P: Primitive> Clone for Draw<P> {
+    fn clone(&self) -> Self {
+        match self {
+            Draw::Fill(color) => Draw::Fill(*color),
+            Draw::Shape(shape,paint) => Draw::Shape(shape.clone(),*paint),
+            Draw::Text(text,color,weight) => Draw::Text(text.clone(),*color,*weight),
+            Draw::Image(image,fit) => Draw::Image(image.clone(),*fit),
+        }
+    }
+}
+
+/// Rects of a command are relative to the layer it's in; nothing outside of `clip` is drawn
+pub enum Command<P: Primitive> {
+    Draw { place: Rect<f32,f32>, clip: Option<Rect<f32,f32>>, draw: Draw<P> },
+    /// commands up to the matching `End` make a layer over `place`, composited as a whole
+    Begin { place: Rect<f32,f32>, clip: Option<Rect<f32,f32>>, params: Compositing },
+    End,
+}
+
+impl<P: Primitive> Clone for Command<P> {
+    fn clone(&self) -> Self {
+        match self {
+            Command::Draw { place, clip, draw } => Command::Draw { place: *place, clip: *clip, draw: draw.clone() },
+            Command::Begin { place, clip, params } => Command::Begin { place: *place, clip: *clip, params: *params },
+            Command::End => Command::End,
+        }
+    }
+}
+
+/// Drawing commands for a `vp` sized area; sizes of text and strokes are in its pixels
+pub struct DisplayList<P: Primitive> {
+    vp: Viewport,
+    commands: Vec<Command<P>>,
+    /// layers begun and not ended yet
+    open: usize,
+}
+
+impl<P: Primitive> DisplayList<P> {
+    pub fn new(vp: Viewport) -> Self {
+        Self { vp, commands: vec![], open: 0 }
+    }
+
+    pub fn viewport(&self) -> Viewport {
+        self.vp
+    }
+
+    pub fn commands(&self) -> &[Command<P>] {
+        &self.commands
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+    pub fn draw(&mut self, place: Rect<f32,f32>, clip: Option<Rect<f32,f32>>, draw: Draw<P>) {
+        self.commands.push(Command::Draw { place, clip, draw });
+    }
+
+    /// Start a layer, following commands are relative to `place`
+    pub fn begin(&mut self, place: Rect<f32,f32>, clip: Option<Rect<f32,f32>>, params: Compositing) {
+        self.open += 1;
+        self.commands.push(Command::Begin { place, clip, params });
+    }
+
+    /// End the last layer begun
+    pub fn end(&mut self) {
+        debug_assert!(self.open > 0,"no layer to end");
+        self.open = self.open.saturating_sub(1);
+        self.commands.push(Command::End);
+    }
+
+    /// Splice in commands of another list, stretched over `place` and clipped to it.
+    /// Commands falling outside of `clip` are left out
+    pub fn append(&mut self, place: Rect<f32,f32>, clip: Option<Rect<f32,f32>>, other: &DisplayList<P>) {
+        let bounds = match clip {
+            Some(clip) => match clip.intersection(place) {
+                Some(bounds) => bounds,
+                None => return,
+            },
+            None => place,
+        };
+        // only the outermost commands are relative to `other` as a whole
+        let remap = |p: Rect<f32,f32>, c: Option<Rect<f32,f32>>| {
+            let clip = match c {
+                Some(c) => c.map_into(place).intersection(bounds)?,
+                None => bounds,
+            };
+            Some((p.map_into(place),Some(clip)))
+        };
+        let mut depth = 0;
+        // the layer being copied is clipped away entirely
+        let mut skip = false;
+        for command in &other.commands {
+            let command = match command {
+                Command::Draw { place, clip, draw } if depth == 0 => match remap(*place,*clip) {
+                    Some((place,clip)) => Command::Draw { place, clip, draw: draw.clone() },
+                    None => continue,
+                },
+                Command::Begin { place, clip, params } if depth == 0 => {
+                    depth = 1;
+                    match remap(*place,*clip) {
+                        Some((place,clip)) => {
+                            skip = false;
+                            Command::Begin { place, clip, params: *params }
+                        }
+                        None => {
+                            skip = true;
+                            continue;
+                        }
+                    }
+                }
+                Command::Begin { .. } => {
+                    depth += 1;
+                    if skip {
+                        continue;
+                    }
+                    command.clone()
+                }
+                Command::End => {
+                    depth -= 1;
+                    if skip {
+                        continue;
+                    }
+                    Command::End
+                }
+                _ if skip => continue,
+                _ => command.clone(),
+            };
+            self.commands.push(command);
+        }
+    }
+}
+
+impl<P: Primitive> Clone for DisplayList<P> {
+    fn clone(&self) -> Self {
+        Self { vp: self.vp, commands: self.commands.clone(), open: self.open }
+    }
+}
+
+/// A layer being replayed, with the command that began it
+struct Layer<P> {
+    buffer: P,
+    place: Rect<f32,f32>,
+    clip: Option<Rect<f32,f32>>,
+    params: Compositing,
+}
+
+impl<P: ShapePrimitive + TextPrimitive> DisplayList<P> {
+    /// Draw the commands into `target`, which is expected to be of the size the list was built for.
+    /// Layers left open are not composited
+    pub fn replay(&self, target: &mut P) {
+        let mut layers: Vec<Layer<P>> = vec![];
+        for command in &self.commands {
+            match command {
+                Command::Draw { place, clip, draw } => {
+                    let top = layers.last_mut().map_or(&mut *target,|l| &mut l.buffer);
+                    let vp = top.size();
+                    top.set_scissor(clip.map(|c| vp.scissor_box(c)));
+                    match draw {
+                        Draw::Fill(color) => top.fill_shape(*place,&Shape::rect(),*color),
+                        Draw::Shape(shape,paint) => top.draw_shape(*place,shape,paint),
+                        Draw::Text(text,color,weight) => top.draw_text(*place,text,*color,*weight),
+                        Draw::Image(image,fit) => top.draw_image(*place,image,*fit),
+                    }
+                }
+                Command::Begin { place, clip, params } => {
+                    let vp = layers.last().map_or(target.size(),|l| l.buffer.size());
+                    let size = Rect::<(),()>::zero().down_right_absolute(vp.as_point()).get_absolute_rect(*place).get_viewport();
+                    layers.push(Layer { buffer: P::blank(size), place: *place, clip: *clip, params: *params });
+                }
+                Command::End => {
+                    let Some(layer) = layers.pop() else { continue };
+                    let top = layers.last_mut().map_or(&mut *target,|l| &mut l.buffer);
+                    let vp = top.size();
+                    top.set_scissor(layer.clip.map(|c| vp.scissor_box(c)));
+                    top.blend_from(layer.place,&layer.buffer,layer.params);
+                }
+            }
+        }
+        target.set_scissor(None);
+    }
+}
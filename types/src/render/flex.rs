@@ -0,0 +1,533 @@
+//! A flexbox-like solver: row and column containers distribute their space between items,
+//! the result is a `parts` vector for a `Layout`.
+//! Lengths are in pixels of the viewport the container is solved for.
+use std::ops::Range;
+
+use crate::traits::Host;
+
+use super::{Filling, Part, Point, Rect, Viewport};
+
+/// Main axis of a container
+#[derive(Clone,Copy,Debug,Eq,PartialEq)]
+pub enum Direction {
+    /// left to right
+    Row,
+    /// top to bottom
+    Column,
+}
+
+/// Placement of items along the main axis, when they don't take all of it
+#[derive(Clone,Copy,Debug,Eq,PartialEq)]
+pub enum Justify {
+    Start,
+    Center,
+    End,
+    /// first and last items touch the edges, the rest is spread between items
+    SpaceBetween,
+    /// every item gets the same margin on both sides
+    SpaceAround,
+    /// gaps between items and the edges are all equal
+    SpaceEvenly,
+}
+
+/// Placement of items along the cross axis
+#[derive(Clone,Copy,Debug,Eq,PartialEq)]
+pub enum Align {
+    Start,
+    Center,
+    End,
+    /// take the whole cross size
+    Stretch,
+}
+
+/// A size of an item
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub enum Length {
+    /// nothing by itself, items with it only get space by growing (or, on the cross axis, by stretching)
+    Auto,
+    Px(f32),
+    /// a fraction of the container's inner size
+    Relative(f32),
+}
+
+impl Length {
+    fn resolve(&self, inner: f32) -> Option<f32> {
+        match self {
+            Length::Auto => None,
+            Length::Px(px) => Some(*px),
+            Length::Relative(r) => Some(r * inner),
+        }
+    }
+}
+
+/// Space around the content of a container, in pixels
+#[derive(Clone,Copy,Debug,Default,PartialEq)]
+pub struct Insets {
+    pub top: f32,
+    pub right: f32,
+    pub bottom: f32,
+    pub left: f32,
+}
+
+impl Insets {
+    pub fn all(v: f32) -> Self {
+        Self { top: v, right: v, bottom: v, left: v }
+    }
+
+    pub fn symmetric(vertical: f32, horizontal: f32) -> Self {
+        Self { top: vertical, right: horizontal, bottom: vertical, left: horizontal }
+    }
+}
+
+/// What an item holds
+pub enum Node<H: Host + ?Sized> {
+    /// becomes a part of the layout
+    Leaf(Filling<H>),
+    /// a nested container, its leaves become parts of the layout
+    Container(Flex<H>),
+    /// takes space, but produces no part
+    Spacer,
+}
+
+/// An item of a container
+pub struct FlexItem<H: Host + ?Sized> {
+    pub node: Node<H>,
+    /// size along the main axis before growing or shrinking
+    pub basis: Length,
+    /// share of free space the item takes
+    pub grow: f32,
+    /// share of overflow the item gives up, weighted by its basis
+    pub shrink: f32,
+    /// bounds of the main size, in pixels
+    pub min: f32,
+    pub max: f32,
+    /// size along the cross axis; `Auto` takes the whole cross size
+    pub cross: Length,
+    /// overrides the alignment of the container
+    pub align: Option<Align>,
+}
+
+impl<H: Host + ?Sized> FlexItem<H> {
+    pub fn new(node: Node<H>) -> Self {
+        Self {
+            node,
+            basis: Length::Auto,
+            grow: 0.,
+            shrink: 1.,
+            min: 0.,
+            max: f32::INFINITY,
+            cross: Length::Auto,
+            align: None,
+        }
+    }
+
+    pub fn leaf(filling: Filling<H>) -> Self {
+        Self::new(Node::Leaf(filling))
+    }
+
+    pub fn container(flex: Flex<H>) -> Self {
+        Self::new(Node::Container(flex))
+    }
+
+    /// An empty item taking all free space
+    pub fn spacer() -> Self {
+        Self::new(Node::Spacer).grow(1.)
+    }
+
+    pub fn basis(mut self, basis: Length) -> Self {
+        self.basis = basis;
+        self
+    }
+
+    pub fn grow(mut self, grow: f32) -> Self {
+        self.grow = grow;
+        self
+    }
+
+    pub fn shrink(mut self, shrink: f32) -> Self {
+        self.shrink = shrink;
+        self
+    }
+
+    pub fn min(mut self, min: f32) -> Self {
+        self.min = min;
+        self
+    }
+
+    pub fn max(mut self, max: f32) -> Self {
+        self.max = max;
+        self
+    }
+
+    pub fn cross(mut self, cross: Length) -> Self {
+        self.cross = cross;
+        self
+    }
+
+    pub fn align(mut self, align: Align) -> Self {
+        self.align = Some(align);
+        self
+    }
+
+    /// Main size before growing or shrinking
+    fn hypothetical(&self, inner: f32) -> f32 {
+        self.basis.resolve(inner).unwrap_or(0.).min(self.max).max(self.min)
+    }
+}
+
+/// A row or a column of items
+pub struct Flex<H: Host + ?Sized> {
+    pub direction: Direction,
+    /// space between adjacent items, in pixels
+    pub gap: f32,
+    pub padding: Insets,
+    pub justify: Justify,
+    pub align: Align,
+    /// items which don't fit go to a new line; lines are stacked along the cross axis, `gap` apart
+    pub wrap: bool,
+    pub items: Vec<FlexItem<H>>,
+}
+
+impl<H: Host + ?Sized> Flex<H> {
+    pub fn new(direction: Direction) -> Self {
+        Self {
+            direction,
+            gap: 0.,
+            padding: Insets::default(),
+            justify: Justify::Start,
+            align: Align::Stretch,
+            wrap: false,
+            items: vec![],
+        }
+    }
+
+    pub fn row() -> Self {
+        Self::new(Direction::Row)
+    }
+
+    pub fn column() -> Self {
+        Self::new(Direction::Column)
+    }
+
+    pub fn gap(mut self, gap: f32) -> Self {
+        self.gap = gap;
+        self
+    }
+
+    pub fn padding(mut self, padding: Insets) -> Self {
+        self.padding = padding;
+        self
+    }
+
+    pub fn justify(mut self, justify: Justify) -> Self {
+        self.justify = justify;
+        self
+    }
+
+    pub fn align(mut self, align: Align) -> Self {
+        self.align = align;
+        self
+    }
+
+    pub fn wrap(mut self) -> Self {
+        self.wrap = true;
+        self
+    }
+
+    pub fn item(mut self, item: FlexItem<H>) -> Self {
+        self.items.push(item);
+        self
+    }
+
+    /// Rects of all leaves, depth first, relative to a `vp` sized area the container fills
+    pub fn solve(&self, vp: Viewport) -> Vec<Rect<f32,f32>> {
+        let mut out = vec![];
+        self.solve_into(Px::of(vp),vp,&mut |rect| out.push(rect));
+        out
+    }
+
+    /// Parts of a layout, relative to a `vp` sized layout the container fills
+    pub fn into_parts(self, vp: Viewport) -> Vec<Part<H>> {
+        let mut rects = self.solve(vp).into_iter();
+        let mut parts = vec![];
+        self.into_leaves(&mut |filling| {
+            let rect = rects.next().expect("a rect is solved for every leaf");
+            parts.push(Part::new(rect,filling));
+        });
+        parts
+    }
+
+    fn into_leaves(self, f: &mut dyn FnMut(Filling<H>)) {
+        for item in self.items {
+            match item.node {
+                Node::Leaf(filling) => f(filling),
+                Node::Container(flex) => flex.into_leaves(f),
+                Node::Spacer => {}
+            }
+        }
+    }
+
+    fn solve_into(&self, bounds: Px, vp: Viewport, emit: &mut dyn FnMut(Rect<f32,f32>)) {
+        let row = self.direction == Direction::Row;
+        let p = &self.padding;
+        let inner = Px {
+            x: bounds.x + p.left,
+            y: bounds.y + p.top,
+            w: (bounds.w - p.left - p.right).max(0.),
+            h: (bounds.h - p.top - p.bottom).max(0.),
+        };
+        let (main,cross) = if row { (inner.w,inner.h) } else { (inner.h,inner.w) };
+        let lines = self.lines(main);
+        let sizes = self.line_sizes(&lines,cross);
+        let mut at = 0.;
+        for (line,size) in lines.into_iter().zip(sizes) {
+            let bounds = if row { Px { y: inner.y + at, h: size, ..inner } } else { Px { x: inner.x + at, w: size, ..inner } };
+            self.solve_line(&self.items[line],bounds,vp,emit);
+            at += size + self.gap;
+        }
+    }
+
+    /// Ranges of items on each line: all of them, unless the container wraps and they don't fit into `main` pixels.
+    /// A line has at least one item
+    fn lines(&self, main: f32) -> Vec<Range<usize>> {
+        if !self.wrap {
+            return std::iter::once(0..self.items.len()).collect();
+        }
+        let (mut lines,mut start,mut used) = (vec![],0,0.);
+        for (i,item) in self.items.iter().enumerate() {
+            let size = item.hypothetical(main);
+            if i > start && used + self.gap + size > main {
+                lines.push(start..i);
+                (start,used) = (i,size);
+            } else {
+                used += if i > start { self.gap + size } else { size };
+            }
+        }
+        lines.push(start..self.items.len());
+        lines
+    }
+
+    /// Cross sizes of lines: the largest cross size of their items, space left is shared equally
+    fn line_sizes(&self, lines: &[Range<usize>], cross: f32) -> Vec<f32> {
+        let mut sizes: Vec<f32> = lines.iter().map(|line| {
+            self.items[line.clone()].iter().filter_map(|i| i.cross.resolve(cross)).fold(0.,f32::max)
+        }).collect();
+        let gaps = self.gap * lines.len().saturating_sub(1) as f32;
+        let left = (cross - gaps - sizes.iter().sum::<f32>()).max(0.) / lines.len() as f32;
+        sizes.iter_mut().for_each(|s| *s += left);
+        sizes
+    }
+
+    /// Place a line of items into `inner`
+    fn solve_line(&self, items: &[FlexItem<H>], inner: Px, vp: Viewport, emit: &mut dyn FnMut(Rect<f32,f32>)) {
+        let row = self.direction == Direction::Row;
+        let (main,cross) = if row { (inner.w,inner.h) } else { (inner.h,inner.w) };
+        let gaps = self.gap * items.len().saturating_sub(1) as f32;
+        let sizes = distribute(items,main - gaps,main);
+        let free = (main - gaps - sizes.iter().sum::<f32>()).max(0.);
+
+        let n = items.len() as f32;
+        let (mut pos,spacing) = match self.justify {
+            Justify::Start => (0.,0.),
+            Justify::Center => (free / 2.,0.),
+            Justify::End => (free,0.),
+            Justify::SpaceBetween if n > 1. => (0.,free / (n - 1.)),
+            Justify::SpaceBetween => (0.,0.),
+            Justify::SpaceAround => (free / n / 2.,free / n),
+            Justify::SpaceEvenly => (free / (n + 1.),free / (n + 1.)),
+        };
+        for (item,size) in items.iter().zip(sizes) {
+            let along = item.cross.resolve(cross).map_or(cross,|c| c.min(cross));
+            let offset = match item.align.unwrap_or(self.align) {
+                Align::Start => 0.,
+                Align::Center => (cross - along) / 2.,
+                Align::End => cross - along,
+                Align::Stretch => 0.,
+            };
+            let along = match (item.align.unwrap_or(self.align),item.cross) {
+                (Align::Stretch,Length::Auto) => cross,
+                _ => along,
+            };
+            let rect = if row {
+                Px { x: inner.x + pos, y: inner.y + offset, w: size, h: along }
+            } else {
+                Px { x: inner.x + offset, y: inner.y + pos, w: along, h: size }
+            };
+            match &item.node {
+                Node::Leaf(_) => emit(rect.relative(vp)),
+                Node::Container(flex) => flex.solve_into(rect,vp,emit),
+                Node::Spacer => {}
+            }
+            pos += size + self.gap + spacing;
+        }
+    }
+}
+
+/// A box in pixels
+#[derive(Clone,Copy,Debug)]
+struct Px {
+    x: f32,
+    y: f32,
+    w: f32,
+    h: f32,
+}
+
+impl Px {
+    fn of(vp: Viewport) -> Self {
+        Px { x: 0., y: 0., w: vp.width as f32, h: vp.height as f32 }
+    }
+
+    fn relative(&self, vp: Viewport) -> Rect<f32,f32> {
+        let (w,h) = (vp.width.max(1) as f32, vp.height.max(1) as f32);
+        Rect(Point(self.x / w,self.y / h),Point((self.x + self.w) / w,(self.y + self.h) / h))
+    }
+}
+
+/// Main sizes of items sharing `available` pixels; `inner` is what relative lengths refer to.
+/// Items grow or shrink in rounds: an item hitting its bounds is frozen there and the rest is redistributed.
+fn distribute<H: Host + ?Sized>(items: &[FlexItem<H>], available: f32, inner: f32) -> Vec<f32> {
+    let clamp = |item: &FlexItem<H>,v: f32| v.min(item.max).max(item.min);
+    let bases: Vec<f32> = items.iter().map(|i| i.basis.resolve(inner).unwrap_or(0.)).collect();
+    let mut sizes: Vec<f32> = items.iter().map(|i| i.hypothetical(inner)).collect();
+    let growing = available > sizes.iter().sum::<f32>();
+    let mut frozen: Vec<bool> = items.iter().map(|i| if growing { i.grow <= 0. } else { i.shrink <= 0. }).collect();
+
+    loop {
+        let used: f32 = sizes.iter().zip(&frozen).filter(|(_,f)| **f).map(|(s,_)| s).sum();
+        let open: Vec<usize> = (0..items.len()).filter(|i| !frozen[*i]).collect();
+        if open.is_empty() {
+            break;
+        }
+        let free = available - used - open.iter().map(|i| bases[*i]).sum::<f32>();
+        let weight = |i: usize| if growing { items[i].grow } else { items[i].shrink * bases[i] };
+        let total: f32 = open.iter().map(|i| weight(*i)).sum();
+        if total <= 0. {
+            for i in open {
+                sizes[i] = clamp(&items[i],bases[i]);
+            }
+            break;
+        }
+        let mut violated = false;
+        for &i in open.iter() {
+            let target = bases[i] + free * weight(i) / total;
+            let clamped = clamp(&items[i],target.max(0.));
+            if clamped != target {
+                frozen[i] = true;
+                violated = true;
+            }
+            sizes[i] = clamped;
+        }
+        if !violated {
+            break;
+        }
+    }
+    sizes
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_host::TestHost;
+
+    use super::*;
+
+    fn leaf() -> FlexItem<TestHost> {
+        FlexItem::leaf(Filling::Component(0,0))
+    }
+
+    /// Rects solved for a `w`x`h` viewport, as `[x,y,width,height]` in pixels
+    fn solve(flex: &Flex<TestHost>, w: u32, h: u32) -> Vec<[f32;4]> {
+        let (fw,fh) = (w as f32,h as f32);
+        flex.solve(Viewport { width: w, height: h }).into_iter()
+            .map(|r| [r.0.0 * fw,r.0.1 * fh,(r.1.0 - r.0.0) * fw,(r.1.1 - r.0.1) * fh])
+            .collect()
+    }
+
+    fn assert_rects(got: Vec<[f32;4]>, expected: &[[f32;4]]) {
+        assert_eq!(got.len(),expected.len(),"{:?}",got);
+        for (g,e) in got.iter().zip(expected) {
+            assert!(g.iter().zip(e).all(|(g,e)| (g - e).abs() < 1e-3),"{:?} != {:?}",got,expected);
+        }
+    }
+
+    #[test]
+    fn grow_shares_free_space() {
+        let flex = Flex::row()
+            .item(leaf().basis(Length::Px(50.)).grow(1.))
+            .item(leaf().basis(Length::Px(50.)).grow(3.))
+            .item(leaf().basis(Length::Relative(0.1)));
+        assert_rects(solve(&flex,200,10),&[[0.,0.,70.,10.],[70.,0.,110.,10.],[180.,0.,20.,10.]]);
+    }
+
+    #[test]
+    fn shrink_is_weighted_by_basis() {
+        let flex = Flex::column()
+            .item(leaf().basis(Length::Px(100.)))
+            .item(leaf().basis(Length::Px(50.)))
+            .item(leaf().basis(Length::Px(20.)).shrink(0.));
+        assert_rects(solve(&flex,10,120),&[[0.,0.,10.,66.667],[0.,66.667,10.,33.333],[0.,100.,10.,20.]]);
+    }
+
+    #[test]
+    fn min_and_max_clamp_and_redistribute() {
+        let flex = Flex::row()
+            .item(leaf().grow(1.).max(30.))
+            .item(leaf().grow(1.));
+        assert_rects(solve(&flex,200,10),&[[0.,0.,30.,10.],[30.,0.,170.,10.]]);
+        let flex = Flex::row()
+            .item(leaf().basis(Length::Px(100.)).min(80.))
+            .item(leaf().basis(Length::Px(100.)));
+        assert_rects(solve(&flex,120,10),&[[0.,0.,80.,10.],[80.,0.,40.,10.]]);
+        // a minimum larger than the container overflows it
+        let flex = Flex::row().item(leaf().min(150.));
+        assert_rects(solve(&flex,100,10),&[[0.,0.,150.,10.]]);
+    }
+
+    #[test]
+    fn gap_padding_and_alignment() {
+        let flex = Flex::row().gap(10.).padding(Insets::all(5.))
+            .item(leaf().basis(Length::Px(50.)))
+            .item(leaf().grow(1.))
+            .item(FlexItem::container(Flex::column().justify(Justify::SpaceBetween).align(Align::Center)
+                .item(leaf().basis(Length::Px(20.)).cross(Length::Px(10.)))
+                .item(leaf().basis(Length::Px(20.)).cross(Length::Px(10.)))).basis(Length::Px(40.)));
+        assert_rects(solve(&flex,200,100),&[
+            [5.,5.,50.,90.],
+            [65.,5.,80.,90.],
+            [170.,5.,10.,20.],
+            [170.,75.,10.,20.],
+        ]);
+        let flex = Flex::row().justify(Justify::SpaceEvenly).align(Align::End)
+            .item(leaf().basis(Length::Px(20.)).cross(Length::Px(4.)))
+            .item(FlexItem::spacer().grow(0.).basis(Length::Px(20.)))
+            .item(leaf().basis(Length::Px(20.)).align(Align::Start));
+        assert_rects(solve(&flex,100,10),&[[10.,6.,20.,4.],[70.,0.,20.,10.]]);
+    }
+
+    #[test]
+    fn wrapping() {
+        let flex = Flex::row().wrap().gap(10.)
+            .item(leaf().basis(Length::Px(40.)))
+            .item(leaf().basis(Length::Px(40.)).grow(1.))
+            .item(leaf().basis(Length::Px(40.)).cross(Length::Px(15.)))
+            .item(leaf().basis(Length::Px(150.)));
+        // lines take the cross size of their items, and share what is left;
+        // the last item is wider than the container, it gets a line of its own and shrinks into it
+        assert_rects(solve(&flex,100,95),&[
+            [0.,0.,40.,20.],
+            [50.,0.,50.,20.],
+            [0.,30.,40.,15.],
+            [0.,75.,100.,20.],
+        ]);
+        // without wrapping everything shares one line
+        let flex = Flex { wrap: false, ..flex };
+        assert_eq!(solve(&flex,100,95).iter().map(|r| r[1]).collect::<Vec<_>>(),[0.;4]);
+    }
+
+    #[test]
+    fn into_parts_skips_spacers() {
+        let parts = Flex::<TestHost>::column().item(leaf().grow(1.)).item(FlexItem::spacer()).into_parts(Viewport { width: 10, height: 10 });
+        assert_eq!(parts.len(),1);
+        assert!((parts[0].rect.height() - 0.5).abs() < 1e-6);
+        assert!(Flex::<TestHost>::row().wrap().solve(Viewport { width: 10, height: 10 }).is_empty());
+    }
+}
@@ -0,0 +1,108 @@
+//! A host which does nothing, for testing what is generic over hosts
+use crate::render::{self, Anchor, Layout, Rect, Viewport, ZIndex};
+use crate::traits::{Host, View};
+
+pub(crate) struct TestHost;
+
+/// Has no pixels
+pub(crate) struct NoPrimitive;
+
+impl render::Primitive for NoPrimitive {
+    type Color = ();
+
+    fn copy_from(&mut self, _: Rect<f32,f32>, _: &Self) {}
+
+    fn cut(&self, _: Rect) -> Self {
+        NoPrimitive
+    }
+
+    fn resize(&self, _: (f32,f32)) -> Self {
+        NoPrimitive
+    }
+
+    fn blank(_: Viewport) -> Self {
+        NoPrimitive
+    }
+
+    fn clear(&mut self, _: Rect) {}
+
+    fn size(&self) -> Viewport {
+        Viewport { width: 0, height: 0 }
+    }
+
+    fn set_scissor(&mut self, _: Option<Rect>) {}
+}
+
+/// Has no styles
+pub(crate) struct NoStyles;
+
+impl render::StyleTable<TestHost> for NoStyles {
+    fn get(&self, _: &std::path::Path) -> Option<render::Style<TestHost>> {
+        None
+    }
+
+    fn update(&mut self, _: render::StyleChange<TestHost>) {}
+
+    fn scope(&mut self, _: &[render::StyleShadow]) -> Box<dyn render::StyleTable<TestHost>> {
+        Box::new(NoStyles)
+    }
+}
+
+/// Shows nothing
+pub(crate) struct NoView(NoStyles);
+
+impl View<TestHost> for NoView {
+    fn anchors(&self) -> &[Anchor] {
+        &[]
+    }
+
+    fn set_layout(&mut self, _: Anchor, _: Option<Layout<TestHost>>, _: ZIndex) {}
+
+    fn set_overlay(&mut self, _: Anchor, _: Option<Layout<TestHost>>, _: isize) {}
+
+    fn viewport(&self) -> Viewport {
+        Viewport { width: 0, height: 0 }
+    }
+
+    fn get_style_table(&self) -> &dyn render::StyleTable<TestHost> {
+        &self.0
+    }
+
+    fn get_style_table_mut(&mut self) -> &mut dyn render::StyleTable<TestHost> {
+        &mut self.0
+    }
+}
+
+impl Host for TestHost {
+    type Index = usize;
+    type Event = ();
+    type EntityData = NoView;
+    type Primitive = NoPrimitive;
+
+    fn allocate_entity(&mut self) -> Result<usize,crate::errors::traits::AllocError> {
+        Ok(0)
+    }
+
+    fn set_entity_data(&mut self, _: usize, _: impl Into<NoView>, _: usize) {}
+
+    fn set_root_entity(&mut self, _: usize) {}
+
+    fn drop_entity(&mut self, _: usize) {}
+
+    fn get_root_entity(&self) -> Option<usize> {
+        None
+    }
+
+    fn get_root_portal_count(&self) -> usize {
+        0
+    }
+
+    fn set_scale_factor(&mut self, _: usize, _: render::ScaleFactor) {}
+
+    fn render(&mut self, _: usize, _: Viewport, _: impl FnOnce(NoPrimitive))
+        where NoView: for<'c> render::Visitor<'c, NoPrimitive> {}
+
+    fn receive_events<'a>(&'a mut self, _: impl Iterator<Item = &'a ()>) {}
+
+    fn update_round(&mut self) {}
+}
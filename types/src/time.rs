@@ -0,0 +1,90 @@
+//! Frame clock: hosts read a `Clock` once per update round and hand `Tick`s to subscribed systems.
+use std::cell::Cell;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+/// A source of time for a host, read at the start of every update round
+pub trait Clock {
+    /// Time since the clock was started; expected to never go backwards
+    fn now(&mut self) -> Duration;
+}
+
+/// Wall clock time
+pub struct SystemClock(Instant);
+
+impl SystemClock {
+    pub fn new() -> Self {
+        Self(Instant::now())
+    }
+}
+
+impl Default for SystemClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for SystemClock {
+    fn now(&mut self) -> Duration {
+        self.0.elapsed()
+    }
+}
+
+/// A clock only moving when told to, for tests and recordings.
+/// Clones share the time, so one can be given to a host and another kept to drive it
+#[derive(Clone,Default)]
+pub struct ManualClock(Rc<Cell<Duration>>);
+
+impl ManualClock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn advance(&self, by: Duration) {
+        self.0.set(self.0.get() + by);
+    }
+
+    /// Move to `to`, unless it's in the past
+    pub fn set(&self, to: Duration) {
+        self.0.set(self.0.get().max(to));
+    }
+
+    pub fn get(&self) -> Duration {
+        self.0.get()
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&mut self) -> Duration {
+        self.0.get()
+    }
+}
+
+/// How update rounds are turned into ticks
+#[derive(Clone,Copy,Debug,Default,Eq,PartialEq)]
+pub enum Timestep {
+    /// a tick every round, with the time passed since the previous one
+    #[default]
+    Variable,
+    /// ticks of exactly `step` each, as many as fit in the time passed; the remainder is carried to the next round.
+    /// At most `max_steps` ticks are given per round, time beyond them is dropped so a slow round doesn't snowball
+    Fixed { step: Duration, max_steps: u32 },
+}
+
+impl Timestep {
+    /// `step` long ticks, at most 8 per round
+    pub fn fixed(step: Duration) -> Self {
+        Timestep::Fixed { step, max_steps: 8 }
+    }
+}
+
+/// A frame of the clock, as received by systems
+#[derive(Clone,Copy,Debug,Eq,PartialEq)]
+pub struct Tick {
+    /// time since the previous tick
+    pub delta: Duration,
+    /// time of the tick, since the host's clock started
+    pub now: Duration,
+    /// number of the tick, starting from 0
+    pub frame: u64,
+}
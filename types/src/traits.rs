@@ -1,8 +1,13 @@
 use std::future::Future;
+use std::path::Path;
 
+use crate::anim;
+use crate::pointer;
 use crate::render;
+use crate::time;
+use crate::assets::{Asset, Handle};
+use crate::errors::assets::AssetError;
 
-//TODO: think of an asset server
 pub trait Host {
     /// A type used to identify entities
     type Index;
@@ -23,11 +28,15 @@ pub trait Host {
     /// Deallocate given index
     fn drop_entity(&mut self,which: Self::Index);
 
+    /// Get root entity, if it's set
+    fn get_root_entity(&self) -> Option<Self::Index>;
     /// Get roots portal count
     fn get_root_portal_count(&self) -> usize;
+    /// Set scale factor of the window showing a root portal, for it and everything nested in it
+    fn set_scale_factor(&mut self, screen_idx: usize, scale: render::ScaleFactor);
     /// Function to render an entity's portal on a window
     fn render(&mut self,screen_idx: usize,vp: render::Viewport, by: impl FnOnce(Self::Primitive))
-        where Self::EntityData: render::Visitor<Self::Primitive>;
+        where Self::EntityData: for<'c> render::Visitor<'c, Self::Primitive>;
     /// Dispatch a batch of events
     fn receive_events<'a>(&'a mut self,events: impl Iterator<Item = &'a Self::Event>);
     /// Run one update round
@@ -41,18 +50,25 @@ pub trait Host {
 pub trait View<H: Host + ?Sized> {
     fn anchors(&self) -> &[render::Anchor];
     fn set_layout(&mut self,anc: render::Anchor,filling: Option<render::Layout<H>>, z_index: render::ZIndex);
+    /// Set the overlay of an anchor, see `Renderer::overlay`
+    fn set_overlay(&mut self,origin: render::Anchor,overlay: Option<render::Layout<H>>, layer: isize);
     fn viewport(&self) -> render::Viewport;
     fn get_style_table(&self) -> &dyn render::StyleTable<H>;
     fn get_style_table_mut(&mut self) -> &mut dyn render::StyleTable<H>;
 }
 
-pub trait Hosts<S: System<Self> + ?Sized + 'static>: Host {
+pub trait Hosts<S: System<Self> + 'static>: Host {
 
     fn get_state(&mut self, which: Self::Index) -> Option<&mut S>;
 
+    /// Attach a component made from `with` to an entity. Subscribing it again starts the component over
+    /// from the new props, messages already sent to it are still delivered
     fn subscribe(&mut self, who: Self::Index, with: S::Props);
 
     fn unsubscribe(&mut self, who: Self::Index);
+
+    /// Send a message to a component from outside of update rounds, it is processed during the next one
+    fn send(&mut self, msg: S::Message, whom: Self::Index);
 }
 
 pub trait GlobalState<H: Host + ?Sized>: Sized + 'static {
@@ -77,7 +93,7 @@ pub trait System<H: Host + Hosts<Self> + ?Sized>: 'static + Unpin + Sized {
     /// Initialize a component with some data
     fn init(props: &Self::Props) -> Self;
     /// Note: Global state of the system can be accessed via a ctx
-    fn update<'s,'h: 's>(&'s mut self,msg: Self::Message, ctx: &mut impl Context<'h,H>) where 'h: 's;
+    fn update<'s,'h: 's>(&'s mut self,msg: Self::Message, ctx: &mut impl Context<'h,H>);
     /// Draw a component; viewport describes boundaries of a component, view_index is the number of view we are going to draw
     fn view<'v>(&'v self,renderer: &'v mut dyn render::Renderer<H>,viewport: render::Viewport,view_index: usize);
 }
@@ -91,11 +107,30 @@ pub trait Context<'s,H: Host + ?Sized> {
 
     /// Send a strongly typed message to a component, if the component isn't registered for index, nothing will happen
     fn send<S: System<H>>(&mut self,msg: S::Message,whom: H::Index) where H: Hosts<S>;
-    /// Set current event -> message transform for current (entity, system) pair
+    /// Set current event -> message transform for current (entity, system) pair.
+    /// Pointer events reach it only while the entity is under the pointer, or the cursor leaves it, in the bubble phase after pointer listeners
     fn subscribe<S: System<H>>(&mut self,filter: fn(&H::Event) -> Option<S::Message>) where H: Hosts<S>;
+    /// Set the pointer listener of a phase for current (entity, system) pair, it sees pointer events while the entity is under the pointer
+    fn subscribe_pointer<S: System<H>>(&mut self,phase: pointer::Phase,listener: fn(&H::Event,&pointer::Target<H>) -> pointer::Propagation<S::Message>)
+        where H: Hosts<S>;
 
     /// spawn a future with a result -> message transform.
     fn spawn<T: 'static + Send,F,Fut,S: System<H>>(&mut self,fut: Fut, f: F,whom: H::Index) -> bool
         where Fut: Future<Output = T> + Send + 'static , F: Fn(T) -> S::Message + 'static, H: Hosts<S>;
     fn with_state<S: System<H>,T,F: FnOnce(&mut S::State) -> T>(&mut self,f: F) -> Option<T> where H: Hosts<S>;
+    /// Request an asset by path; the handle is returned right away and loads in the background.
+    /// Once loading finishes, `f` turns its outcome into a message for the current entity
+    fn load<A: Asset,F,S: System<H>>(&mut self,path: impl AsRef<Path>,f: F) -> Handle<A>
+        where F: FnOnce(Result<Handle<A>,AssetError>) -> S::Message + 'static, H: Hosts<S>;
+    /// Start animating values of the current entity, `done` is sent to it once all steps are finished
+    fn animate<S: System<H>>(&mut self,animation: impl Into<anim::Animation<H>>,done: Option<S::Message>) -> anim::AnimationId
+        where H: Hosts<S>;
+    /// Stop an animation; values stay as it left them and no message is sent. `false` if it wasn't running
+    fn cancel_animation(&mut self,id: anim::AnimationId) -> bool;
+    /// Time of the current update round, as read from the host's clock
+    fn now(&mut self) -> std::time::Duration;
+    /// Set the tick -> message transform for current (entity, system) pair, it receives every tick of the host's clock
+    fn subscribe_ticks<S: System<H>>(&mut self,f: fn(time::Tick) -> S::Message) where H: Hosts<S>;
+    /// Stop receiving ticks for current (entity, system) pair
+    fn unsubscribe_ticks<S: System<H>>(&mut self) where H: Hosts<S>;
 }